@@ -2,13 +2,37 @@
 // Licensed under the BSD 3-Clause License.
 // SPDX-License-Identifier: BSD-3-Clause
 
+pub const DIRECTIVE_REFERENCE: &str = "https://freemarker.apache.org/docs/ref_directives.html";
 pub const DIRECTIVE_ASSIGN: &str = "https://freemarker.apache.org/docs/ref_directive_assign.html";
+pub const DIRECTIVE_FUNCTION: &str =
+    "https://freemarker.apache.org/docs/ref_directive_function.html";
+pub const DIRECTIVE_FTL: &str = "https://freemarker.apache.org/docs/ref_directive_ftl.html";
+pub const DIRECTIVE_GLOBAL: &str = "https://freemarker.apache.org/docs/ref_directive_global.html";
+pub const DIRECTIVE_IF: &str = "https://freemarker.apache.org/docs/ref_directive_if.html";
 pub const DIRECTIVE_IMPORT: &str = "https://freemarker.apache.org/docs/ref_directive_import.html";
+pub const DIRECTIVE_INCLUDE: &str = "https://freemarker.apache.org/docs/ref_directive_include.html";
+pub const DIRECTIVE_LIST: &str = "https://freemarker.apache.org/docs/ref_directive_list.html";
+pub const DIRECTIVE_LOCAL: &str = "https://freemarker.apache.org/docs/ref_directive_local.html";
+pub const DIRECTIVE_MACRO: &str = "https://freemarker.apache.org/docs/ref_directive_macro.html";
+pub const DIRECTIVE_SETTING: &str = "https://freemarker.apache.org/docs/ref_directive_setting.html";
+pub const DIRECTIVE_SWITCH: &str = "https://freemarker.apache.org/docs/ref_directive_switch.html";
+pub const DIRECTIVE_USER_DEFINED: &str =
+    "https://freemarker.apache.org/docs/ref_directive_userDefined.html";
 pub const DIRECTIVE_LIST_BREAK: &str =
     "https://freemarker.apache.org/docs/ref_directive_list.html#ref_list_break";
+pub const DIRECTIVE_LIST_SEP: &str =
+    "https://freemarker.apache.org/docs/ref_directive_list.html#ref_list_sep";
 
 pub const COMPARISION_EXPRESSION: &str =
     "https://freemarker.apache.org/docs/dgui_template_exp.html#dgui_template_exp_comparison";
 
+pub const STRING_INTERPOLATION: &str =
+    "https://freemarker.apache.org/docs/dgui_template_exp.html#dgui_template_exp_stringop_interpolation";
+
+pub const MISSING_DEFAULT: &str =
+    "https://freemarker.apache.org/docs/dgui_template_exp.html#dgui_template_exp_missing_default";
+
 pub const TOPLEVEL_VARIABLE: &str =
     "https://freemarker.apache.org/docs/dgui_template_exp.html#dgui_template_exp_var_toplevel";
+
+pub const WHITESPACE_STRIPPING: &str = "https://freemarker.apache.org/docs/dgui_misc_whitespace.html";
@@ -47,6 +47,7 @@ pub const TAGS_QUERY: &str = include_str!("../../queries/tags.scm");
 // const literals
 pub const SEMANTICS: &str = "freemarker semantics";
 pub const SYNTAX: &str = "freemarker syntax";
+pub const STYLE: &str = "freemarker style";
 
 // extra public mods
 pub mod grammar; // expose grammar rules via codegen
@@ -2,18 +2,33 @@
 // Licensed under the BSD 3-Clause License.
 // SPDX-License-Identifier: BSD-3-Clause
 
-use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
 use tower_lsp_server::{
     jsonrpc::Result as JsonRpcResult,
     ls_types::{
         CodeAction, CodeActionKind, CodeActionOptions, CodeActionOrCommand, CodeActionParams,
-        CodeActionProviderCapability, Diagnostic, NumberOrString, TextEdit, Uri, WorkspaceEdit,
+        CodeActionProviderCapability, Command, CreateFile, CreateFileOptions, Diagnostic,
+        DocumentChangeOperation, DocumentChanges, NumberOrString, Position, Range, ResourceOp,
+        TextEdit, Uri, WorkspaceEdit,
     },
 };
 
 use tree_sitter_freemarker::grammar::Rule;
 
-use crate::{reactor::Reactor, server::ActionFeature};
+use crate::{
+    command::OPEN_REFERENCE,
+    diagnosis::Scenario,
+    import::import_candidate,
+    reactor::Reactor,
+    server::{ActionFeature, ActionResolveFeature},
+    symbol::{PATH_DUPLICATED_CODE, PATH_REF_SELF_CODE, PATH_UNCANONICAL_CODE},
+    utils,
+    workspace::Workspace,
+};
 
 #[allow(clippy::mutable_key_type)]
 fn create_fix_warning_action(
@@ -48,9 +63,384 @@ fn create_fix_warning_action(
     }))
 }
 
+// replaces the "#include" keyword by "#import"
+#[allow(clippy::mutable_key_type)]
+fn create_include_to_import_action(
+    uri: &Uri,
+    diagnostic: Diagnostic,
+) -> Option<CodeActionOrCommand> {
+    let include_location = diagnostic
+        .related_information
+        .as_ref()?
+        .first()?
+        .location
+        .clone();
+    let text_edit = TextEdit {
+        range: include_location.range,
+        new_text: "#import".to_owned(),
+    };
+    Some(CodeActionOrCommand::CodeAction(CodeAction {
+        title: "convert include to import".to_owned(),
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic]),
+        edit: Some(WorkspaceEdit {
+            changes: Some(vec![(uri.clone(), vec![text_edit])].into_iter().collect()),
+            ..Default::default()
+        }),
+        is_preferred: Some(true),
+        ..Default::default()
+    }))
+}
+
+// appends an empty sequence as the default value of the listed variable
+#[allow(clippy::mutable_key_type)]
+fn create_list_default_action(uri: &Uri, diagnostic: Diagnostic) -> CodeActionOrCommand {
+    let text_edit = TextEdit {
+        range: Range::new(diagnostic.range.end, diagnostic.range.end),
+        new_text: "![]".to_owned(),
+    };
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title: "default to an empty sequence".to_owned(),
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic]),
+        edit: Some(WorkspaceEdit {
+            changes: Some(vec![(uri.clone(), vec![text_edit])].into_iter().collect()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+// deletes the import statement at the diagnostic, e.g. an import of the template itself
+// or a duplicated one, its line goes along when nothing else is left on it
+#[allow(clippy::mutable_key_type)]
+fn create_remove_import_action(
+    reactor: &Reactor,
+    uri: &Uri,
+    diagnostic: Diagnostic,
+    title: &str,
+) -> Option<CodeActionOrCommand> {
+    let doc = reactor.get_document();
+    let point = utils::lsp_position_to_parser_point(doc, &diagnostic.range.start);
+    let parser = reactor.get_parser();
+    let mut node = parser.get_node_at_point(point);
+    while let Some(current) = node
+        && Rule::from_str(current.kind()) != Ok(Rule::ImportStmt)
+    {
+        node = current.parent();
+    }
+    let text_edit = TextEdit {
+        range: utils::removal_range(doc, node?.byte_range()),
+        new_text: String::new(),
+    };
+    Some(CodeActionOrCommand::CodeAction(CodeAction {
+        title: title.to_owned(),
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic]),
+        edit: Some(WorkspaceEdit {
+            changes: Some(vec![(uri.clone(), vec![text_edit])].into_iter().collect()),
+            ..Default::default()
+        }),
+        is_preferred: Some(true),
+        ..Default::default()
+    }))
+}
+
+// deletes the spaces after the directive
+#[allow(clippy::mutable_key_type)]
+fn create_trim_whitespace_action(uri: &Uri, diagnostic: Diagnostic) -> CodeActionOrCommand {
+    let text_edit = TextEdit {
+        range: diagnostic.range,
+        new_text: String::new(),
+    };
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title: "trim trailing whitespace".to_owned(),
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic]),
+        edit: Some(WorkspaceEdit {
+            changes: Some(vec![(uri.clone(), vec![text_edit])].into_iter().collect()),
+            ..Default::default()
+        }),
+        is_preferred: Some(true),
+        ..Default::default()
+    })
+}
+
+// renames the close tag after the innermost open directive, which is the related location
+#[allow(clippy::mutable_key_type)]
+fn create_rename_close_tag_action(
+    reactor: &Reactor,
+    uri: &Uri,
+    diagnostic: Diagnostic,
+) -> Option<CodeActionOrCommand> {
+    let opener = diagnostic
+        .related_information
+        .as_ref()?
+        .first()?
+        .location
+        .range;
+    let doc = reactor.get_document();
+    let name =
+        doc.get_ranged_text(doc.position_to_byte(&opener.start)..doc.position_to_byte(&opener.end));
+    let text_edit = TextEdit {
+        range: diagnostic.range,
+        new_text: name.clone(),
+    };
+    Some(CodeActionOrCommand::CodeAction(CodeAction {
+        title: format!("change the close tag to </#{}>", name),
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic]),
+        edit: Some(WorkspaceEdit {
+            changes: Some(vec![(uri.clone(), vec![text_edit])].into_iter().collect()),
+            ..Default::default()
+        }),
+        is_preferred: Some(true),
+        ..Default::default()
+    }))
+}
+
+// there is no rewrite of the loop into ?take_while, e.g. a break on "x?index" would need
+// a slice which fails on a shorter sequence, so the reference is opened instead
+fn create_take_while_reference_action(diagnostic: Diagnostic) -> Option<CodeActionOrCommand> {
+    let href = diagnostic.code_description.as_ref()?.href.to_string();
+    Some(CodeActionOrCommand::CodeAction(CodeAction {
+        title: "Learn: use ?take_while".to_owned(),
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic]),
+        command: Some(Command {
+            title: "Learn: use ?take_while".to_owned(),
+            command: OPEN_REFERENCE.to_owned(),
+            arguments: Some(vec![href.into()]),
+        }),
+        ..Default::default()
+    }))
+}
+
+// the template an <#import> refers to, if it does not exist yet
+fn missing_import_target(reactor: &Reactor, diagnostic: &Diagnostic) -> Option<Uri> {
+    let doc = reactor.get_document();
+    // the diagnostic covers the quoted path
+    let quoted = doc.get_ranged_text(
+        doc.position_to_byte(&diagnostic.range.start)..doc.position_to_byte(&diagnostic.range.end),
+    );
+    let path = quoted.strip_prefix('"')?.strip_suffix('"')?;
+    let target = import_candidate(reactor.get_config(), &doc.dir(), path);
+    if target.exists() {
+        return None;
+    }
+    Uri::from_file_path(target)
+}
+
+// the edits which are only computed when the action is resolved, the uri is the document's
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+enum CodeActionData {
+    CreateFile { uri: Uri },
+    OrganizeImports { uri: Uri },
+}
+
+impl CodeActionData {
+    fn uri(&self) -> &Uri {
+        match self {
+            CodeActionData::CreateFile { uri } | CodeActionData::OrganizeImports { uri } => uri,
+        }
+    }
+}
+
+// creates the missing template, an existing one is left untouched
+fn create_missing_file_action(uri: &Uri, diagnostic: Diagnostic) -> CodeActionOrCommand {
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title: "create file".to_owned(),
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic]),
+        data: serde_json::to_value(CodeActionData::CreateFile { uri: uri.clone() }).ok(),
+        ..Default::default()
+    })
+}
+
+fn create_file_edit(target: Uri) -> WorkspaceEdit {
+    let create_file = ResourceOp::Create(CreateFile {
+        uri: target,
+        options: Some(CreateFileOptions {
+            overwrite: Some(false),
+            ignore_if_exists: Some(true),
+        }),
+        annotation_id: None,
+    });
+    WorkspaceEdit {
+        document_changes: Some(DocumentChanges::Operations(vec![
+            DocumentChangeOperation::Op(create_file),
+        ])),
+        ..Default::default()
+    }
+}
+
+// e.g. "<#-- the layout macros -->" above an import belongs to it
+fn is_comment_line(line: &str) -> bool {
+    let line = line.trim();
+    (line.starts_with("<#--") && line.ends_with("-->"))
+        || (line.starts_with("[#--") && line.ends_with("--]"))
+}
+
+// the alias and the quoted path of the imports which take a whole line, by line
+fn import_lines(reactor: &Reactor) -> HashMap<usize, (String, String)> {
+    let doc = reactor.get_document();
+    let mut lines = HashMap::new();
+    let Some(ast) = reactor.get_parser().get_ast() else {
+        return lines;
+    };
+    let root = ast.root_node();
+    let mut cursor = root.walk();
+    for directive in root.named_children(&mut cursor) {
+        let Some(import) = directive.named_child(0) else {
+            continue;
+        };
+        if Rule::from_str(import.kind()) != Ok(Rule::ImportStmt)
+            || import.start_position().row != import.end_position().row
+        {
+            continue;
+        }
+        let row = import.start_position().row;
+        let line = doc.get_line_text(row);
+        let text = doc.get_ranged_text(import.byte_range());
+        if line.trim() != text {
+            continue;
+        }
+        let field_text = |name: &str| {
+            import
+                .child_by_field_name(name)
+                .map(|field| doc.get_ranged_text(field.byte_range()))
+        };
+        if let (Some(alias), Some(path)) = (
+            field_text(&Rule::ImportAlias.to_string()),
+            field_text(&Rule::ImportPath.to_string()),
+        ) {
+            lines.insert(row, (alias, path));
+        }
+    }
+    lines
+}
+
+// whether the import lines are sorted by alias without the exact duplicates, a cheap
+// check before the action is offered, the edit is only computed when it is resolved
+fn imports_in_order(imports: &HashMap<usize, (String, String)>) -> bool {
+    let mut rows: Vec<_> = imports.keys().collect();
+    rows.sort();
+    rows.windows(2).all(|pair| {
+        let (previous, next) = (&imports[pair[0]], &imports[pair[1]]);
+        previous.0 <= next.0 && previous != next
+    })
+}
+
+// the first block of import lines, sorted by alias without the exact duplicates, the
+// comment lines right above an import are moved along with it
+fn organize_imports(reactor: &Reactor) -> Option<TextEdit> {
+    let doc = reactor.get_document();
+    let imports = import_lines(reactor);
+    let first_row = *imports.keys().min()?;
+    let line_at = |row: usize| {
+        doc.get_line_text(row)
+            .trim_end_matches(['\r', '\n'])
+            .to_owned()
+    };
+    let mut start_row = first_row;
+    while start_row > 0 && is_comment_line(&line_at(start_row - 1)) {
+        start_row -= 1;
+    }
+    let mut entries: Vec<(&(String, String), Vec<String>)> = vec![];
+    let mut comments = vec![];
+    let mut end_row = start_row;
+    for row in start_row..doc.rope.len_lines() {
+        let line = line_at(row);
+        if let Some(import) = imports.get(&row) {
+            comments.push(line);
+            entries.push((import, std::mem::take(&mut comments)));
+            end_row = row;
+        } else if is_comment_line(&line) {
+            comments.push(line);
+        } else {
+            break;
+        }
+    }
+    let mut organized = entries.clone();
+    organized.sort_by(|a, b| a.0.0.cmp(&b.0.0));
+    let mut seen = HashSet::new();
+    organized.retain(|(import, _)| seen.insert(*import));
+    if organized == entries {
+        return None;
+    }
+    let line_break = match doc.get_line_text(first_row).ends_with("\r\n") {
+        true => "\r\n",
+        false => "\n",
+    };
+    let mut new_text: String = organized
+        .into_iter()
+        .flat_map(|(_, lines)| lines)
+        .map(|line| line + line_break)
+        .collect();
+    // the last import may end the document without a line break
+    let end = match end_row + 1 < doc.rope.len_lines() {
+        true => Position::new(end_row as u32 + 1, 0),
+        false => {
+            new_text.truncate(new_text.len() - line_break.len());
+            Position::new(
+                end_row as u32,
+                doc.byte_column_to_character(end_row, line_at(end_row).len()),
+            )
+        }
+    };
+    Some(TextEdit {
+        range: Range::new(
+            Position::new(start_row as u32, doc.byte_column_to_character(start_row, 0)),
+            end,
+        ),
+        new_text,
+    })
+}
+
+fn create_organize_imports_action(uri: &Uri) -> CodeActionOrCommand {
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title: "organize imports".to_owned(),
+        kind: Some(CodeActionKind::SOURCE_ORGANIZE_IMPORTS),
+        data: serde_json::to_value(CodeActionData::OrganizeImports { uri: uri.clone() }).ok(),
+        ..Default::default()
+    })
+}
+
+// fills the edit of an action which has been returned without it
+#[allow(clippy::mutable_key_type)]
+pub fn resolve_code_action(reactor: &Reactor, mut action: CodeAction) -> CodeAction {
+    let Some(data) = action
+        .data
+        .clone()
+        .and_then(|data| serde_json::from_value::<CodeActionData>(data).ok())
+    else {
+        return action;
+    };
+    action.edit = match data {
+        CodeActionData::CreateFile { .. } => action
+            .diagnostics
+            .as_ref()
+            .and_then(|diagnostics| diagnostics.first())
+            .and_then(|diagnostic| missing_import_target(reactor, diagnostic))
+            .map(create_file_edit),
+        CodeActionData::OrganizeImports { uri } => {
+            organize_imports(reactor).map(|text_edit| WorkspaceEdit {
+                changes: Some(vec![(uri, vec![text_edit])].into_iter().collect()),
+                ..Default::default()
+            })
+        }
+    };
+    action
+}
+
 pub fn code_action_capability() -> CodeActionProviderCapability {
     CodeActionProviderCapability::Options(CodeActionOptions {
-        code_action_kinds: Some(vec![CodeActionKind::QUICKFIX]),
+        code_action_kinds: Some(vec![
+            CodeActionKind::QUICKFIX,
+            CodeActionKind::SOURCE_ORGANIZE_IMPORTS,
+        ]),
+        resolve_provider: Some(true),
         ..Default::default()
     })
 }
@@ -64,7 +454,51 @@ impl ActionFeature for Reactor {
         for diagnostic in params.context.diagnostics {
             if let Some(NumberOrString::String(code)) = &diagnostic.code {
                 // string codes
-                if let Some(fix_action) =
+                if code == PATH_REF_SELF_CODE {
+                    actions.extend(create_remove_import_action(
+                        self,
+                        &params.text_document.uri,
+                        diagnostic.clone(),
+                        "remove self import",
+                    ));
+                } else if code == Scenario::INCLUDE_USED_AS_IMPORT.code() {
+                    actions.extend(create_include_to_import_action(
+                        &params.text_document.uri,
+                        diagnostic.clone(),
+                    ));
+                } else if code == Scenario::LIST_WITHOUT_DEFAULT.code() {
+                    actions.push(create_list_default_action(
+                        &params.text_document.uri,
+                        diagnostic.clone(),
+                    ));
+                } else if code == PATH_DUPLICATED_CODE {
+                    actions.extend(create_remove_import_action(
+                        self,
+                        &params.text_document.uri,
+                        diagnostic.clone(),
+                        "remove duplicate import",
+                    ));
+                } else if code == PATH_UNCANONICAL_CODE {
+                    if missing_import_target(self, &diagnostic).is_some() {
+                        actions.push(create_missing_file_action(
+                            &params.text_document.uri,
+                            diagnostic.clone(),
+                        ));
+                    }
+                } else if code == Scenario::DEPRECATED_LIST_BREAK.code() {
+                    actions.extend(create_take_while_reference_action(diagnostic.clone()));
+                } else if code == Scenario::MISMATCHED_CLOSE_TAG.code() {
+                    actions.extend(create_rename_close_tag_action(
+                        self,
+                        &params.text_document.uri,
+                        diagnostic.clone(),
+                    ));
+                } else if code == Scenario::TRAILING_WHITESPACE.code() {
+                    actions.push(create_trim_whitespace_action(
+                        &params.text_document.uri,
+                        diagnostic.clone(),
+                    ));
+                } else if let Some(fix_action) =
                     create_fix_warning_action(code, &params.text_document.uri, diagnostic.clone())
                 {
                     // Create a CodeAction for this specific diagnostic
@@ -72,6 +506,437 @@ impl ActionFeature for Reactor {
                 }
             }
         }
+        let organize_requested = params.context.only.as_ref().is_none_or(|only| {
+            only.iter().any(|kind| {
+                CodeActionKind::SOURCE_ORGANIZE_IMPORTS
+                    .as_str()
+                    .starts_with(kind.as_str())
+            })
+        });
+        if organize_requested && !imports_in_order(&import_lines(self)) {
+            actions.push(create_organize_imports_action(&params.text_document.uri));
+        }
         Ok(Some(actions))
     }
 }
+
+impl ActionResolveFeature for Workspace {
+    async fn on_code_action_resolve(&self, action: CodeAction) -> JsonRpcResult<CodeAction> {
+        let Some(data) = action
+            .data
+            .clone()
+            .and_then(|data| serde_json::from_value::<CodeActionData>(data).ok())
+        else {
+            return Ok(action);
+        };
+        let read_guard = self.reactors.read().await;
+        match read_guard.get(data.uri()) {
+            Some(reactor) => Ok(resolve_code_action(reactor, action)),
+            None => Ok(action),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use tower_lsp_server::ls_types::{
+        CodeAction, CodeActionContext, CodeActionKind, CodeActionOrCommand, CodeActionParams,
+        DiagnosticSeverity, DiagnosticTag, DidOpenTextDocumentParams, DocumentChangeOperation,
+        DocumentChanges, NumberOrString, Position, Range, ResourceOp, TextDocumentIdentifier,
+        TextDocumentItem, Uri,
+    };
+
+    use tree_sitter_freemarker::href::DIRECTIVE_LIST_BREAK;
+
+    use crate::{
+        action::resolve_code_action,
+        command::OPEN_REFERENCE,
+        config::Config,
+        reactor::Reactor,
+        server::{ActionFeature, ActionResolveFeature},
+        utils,
+        workspace::Workspace,
+    };
+
+    // the quick fixes of the diagnostics in the range, as a client asks for them
+    async fn actions_at(reactor: &Reactor, range: Range) -> Vec<CodeAction> {
+        let diagnostics = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items
+            .into_iter()
+            .filter(|d| d.range.start <= range.end && range.start <= d.range.end)
+            .collect();
+        reactor
+            .on_code_action(CodeActionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: reactor.get_document().uri(),
+                },
+                range,
+                context: CodeActionContext {
+                    diagnostics,
+                    only: Some(vec![CodeActionKind::QUICKFIX]),
+                    ..Default::default()
+                },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|action| match action {
+                CodeActionOrCommand::CodeAction(action) => action,
+                CodeActionOrCommand::Command(_) => panic!("expect a code action"),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_remove_self_import() {
+        let cases = [
+            (
+                "<#import \"self.ftl\" as me>\n${x}\n",
+                Range::new(Position::new(0, 0), Position::new(1, 0)),
+            ),
+            // the interpolation stays on the line
+            (
+                "${x}<#import \"self.ftl\" as me>\n",
+                Range::new(Position::new(0, 4), Position::new(0, 30)),
+            ),
+        ];
+        for (source, range) in cases {
+            let dir = utils::fixture_dir("self_import", &[("self.ftl", source)]);
+            let uri = Uri::from_file_path(dir.join("self.ftl")).unwrap();
+            let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+            let diagnostic = reactor
+                .get_analysis()
+                .get_analyzed_full_diagnostics()
+                .full_document_diagnostic_report
+                .items
+                .into_iter()
+                .find(|d| d.code == Some(NumberOrString::String("path_refer_itself".to_owned())))
+                .unwrap();
+            assert_eq!(diagnostic.tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+
+            let actions = actions_at(&reactor, diagnostic.range).await;
+            assert_eq!(actions.len(), 1);
+            let action = &actions[0];
+            let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+            assert_eq!(edits[0].range, range, "source: {:?}", source);
+            assert!(edits[0].new_text.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remove_duplicate_import() {
+        let cases = [
+            // the second import and its line break
+            (
+                "<#import \"lib.ftl\" as a>\n<#import \"lib.ftl\" as b>\n${x}\n",
+                Range::new(Position::new(1, 0), Position::new(2, 0)),
+            ),
+            // the first import stays on the line
+            (
+                "<#import \"lib.ftl\" as a><#import \"lib.ftl\" as b>\n${x}\n",
+                Range::new(Position::new(0, 24), Position::new(0, 48)),
+            ),
+        ];
+        for (source, range) in cases {
+            let dir =
+                utils::fixture_dir("duplicate_import", &[("lib.ftl", ""), ("main.ftl", source)]);
+            let uri = Uri::from_file_path(dir.join("main.ftl")).unwrap();
+            let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+            let diagnostic = reactor
+                .get_analysis()
+                .get_analyzed_full_diagnostics()
+                .full_document_diagnostic_report
+                .items
+                .into_iter()
+                .find(|d| d.code == Some(NumberOrString::String("path_duplicated".to_owned())))
+                .unwrap();
+
+            let actions = actions_at(&reactor, diagnostic.range).await;
+            assert_eq!(actions.len(), 1);
+            let action = &actions[0];
+            assert_eq!(action.title, "remove duplicate import");
+            let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+            assert_eq!(edits[0].range, range, "source: {:?}", source);
+            assert!(edits[0].new_text.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deprecated_list_break() {
+        let uri = Uri::from_str("file:///tmp/list_break.ftl").unwrap();
+        let source = "<#list xs as x><#if x?index == 3><#break></#if>${x}</#list>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let diagnostic = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items
+            .into_iter()
+            .find(|d| d.code == Some(NumberOrString::String("deprecated_list_break".to_owned())))
+            .unwrap();
+
+        let actions = actions_at(&reactor, diagnostic.range).await;
+        let action = &actions[0];
+        assert!(action.edit.is_none());
+        let command = action.command.as_ref().unwrap();
+        assert_eq!(command.command, OPEN_REFERENCE);
+        assert_eq!(command.arguments, Some(vec![DIRECTIVE_LIST_BREAK.into()]));
+    }
+
+    #[tokio::test]
+    async fn test_organize_imports() {
+        let uri = Uri::from_str("file:///tmp/organize_imports.ftl").unwrap();
+        let source = "<#-- the page layout -->\n<#import \"layout.ftl\" as layout>\n<#import \"base.ftl\" as base>\n<#import \"layout.ftl\" as layout>\n\n${x}\n";
+        let workspace = Workspace::new();
+        workspace.set_code_action_resolve(true).await;
+        workspace
+            .on_did_open(&DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: uri.clone(),
+                    language_id: "ftl".to_owned(),
+                    version: 0,
+                    text: source.to_owned(),
+                },
+            })
+            .await;
+        let code_action = |only| {
+            workspace.on_code_action(CodeActionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                range: Range::new(Position::new(0, 0), Position::new(0, 0)),
+                context: CodeActionContext {
+                    only,
+                    ..Default::default()
+                },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+        };
+        let quickfixes = code_action(Some(vec![CodeActionKind::QUICKFIX]))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(quickfixes.is_empty());
+
+        let actions = code_action(Some(vec![CodeActionKind::SOURCE]))
+            .await
+            .unwrap()
+            .unwrap();
+        let CodeActionOrCommand::CodeAction(action) = &actions[0] else {
+            panic!("expect a code action");
+        };
+        assert_eq!(action.kind, Some(CodeActionKind::SOURCE_ORGANIZE_IMPORTS));
+        // the edit is computed when the action is resolved
+        assert!(action.edit.is_none());
+        let resolved = workspace
+            .on_code_action_resolve(action.clone())
+            .await
+            .unwrap();
+        let edits = &resolved.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+        assert_eq!(
+            edits[0].range,
+            Range::new(Position::new(0, 0), Position::new(4, 0))
+        );
+        // the comment stays above its import
+        assert_eq!(
+            edits[0].new_text,
+            "<#import \"base.ftl\" as base>\n<#-- the page layout -->\n<#import \"layout.ftl\" as layout>\n"
+        );
+
+        // a client without resolve support gets the edit at once
+        workspace.set_code_action_resolve(false).await;
+        let actions = code_action(None).await.unwrap().unwrap();
+        let CodeActionOrCommand::CodeAction(action) = &actions[0] else {
+            panic!("expect a code action");
+        };
+        assert_eq!(action.edit, resolved.edit);
+
+        // nothing to organize, the action is not offered, the later blocks are left alone
+        // so only the eager edit tells that the first one is in order
+        let sorted = "<#import \"base.ftl\" as base>\n<#import \"layout.ftl\" as layout>\n";
+        let later_block = format!("{}\n<#import \"all.ftl\" as all>\n", sorted);
+        for (name, text, code_action_resolve) in [
+            ("sorted_imports", sorted, true),
+            ("sorted_imports", sorted, false),
+            ("later_import_block", later_block.as_str(), false),
+        ] {
+            let uri = Uri::from_str(&format!("file:///tmp/{}.ftl", name)).unwrap();
+            workspace
+                .on_did_open(&DidOpenTextDocumentParams {
+                    text_document: TextDocumentItem {
+                        uri: uri.clone(),
+                        language_id: "ftl".to_owned(),
+                        version: 0,
+                        text: text.to_owned(),
+                    },
+                })
+                .await;
+            workspace.set_code_action_resolve(code_action_resolve).await;
+            let actions = workspace
+                .on_code_action(CodeActionParams {
+                    text_document: TextDocumentIdentifier { uri },
+                    range: Range::new(Position::new(0, 0), Position::new(0, 0)),
+                    context: CodeActionContext {
+                        only: Some(vec![CodeActionKind::SOURCE_ORGANIZE_IMPORTS]),
+                        ..Default::default()
+                    },
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                })
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(actions.is_empty(), "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_without_default() {
+        let uri = Uri::from_str("file:///tmp/list_default.ftl").unwrap();
+        let source = "<#list items as x>${x}</#list>\n<#list [1, 2] as y>${y}</#list>\n";
+        let config = Config {
+            enabled_diagnostics: ["list_without_default".to_owned()].into(),
+            ..Default::default()
+        };
+        let reactor = Reactor::new(&uri, source, 0, Arc::new(config), Default::default());
+        let diagnostics: Vec<_> = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items
+            .into_iter()
+            .filter(|d| d.code == Some(NumberOrString::String("list_without_default".to_owned())))
+            .collect();
+        // the sequence literal needs no default
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::HINT));
+        assert_eq!(diagnostics[0].range.start, Position::new(0, 7));
+        assert_eq!(diagnostics[0].range.end, Position::new(0, 12));
+
+        let actions = actions_at(&reactor, diagnostics[0].range).await;
+        let action = &actions[0];
+        let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+        assert_eq!(edits[0].range.start, Position::new(0, 12));
+        assert_eq!(edits[0].range.end, Position::new(0, 12));
+        assert_eq!(edits[0].new_text, "![]");
+
+        // off by default
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        assert!(
+            !reactor
+                .get_analysis()
+                .get_analyzed_full_diagnostics()
+                .full_document_diagnostic_report
+                .items
+                .iter()
+                .any(|d| d.code == Some(NumberOrString::String("list_without_default".to_owned())))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trailing_whitespace() {
+        let uri = Uri::from_str("file:///tmp/trailing_whitespace.ftl").unwrap();
+        let source = "<#if x>  \n  text  \n[#list xs as x]\t\r\n</#if> \n<p> \n";
+        let config = Config {
+            enabled_diagnostics: ["trailing_whitespace".to_owned()].into(),
+            ..Default::default()
+        };
+        let reactor = Reactor::new(&uri, source, 0, Arc::new(config), Default::default());
+        let diagnostics: Vec<_> = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items
+            .into_iter()
+            .filter(|d| d.code == Some(NumberOrString::String("trailing_whitespace".to_owned())))
+            .collect();
+        // the text and html lines are not reported
+        let ranges: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.range.start, d.range.end))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                (Position::new(0, 7), Position::new(0, 9)),
+                (Position::new(2, 15), Position::new(2, 16)),
+                (Position::new(3, 6), Position::new(3, 7)),
+            ]
+        );
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::HINT));
+        assert_eq!(diagnostics[0].tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+
+        let actions = actions_at(&reactor, diagnostics[0].range).await;
+        let action = &actions[0];
+        let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+        assert_eq!(edits[0].range, diagnostics[0].range);
+        assert_eq!(edits[0].new_text, "");
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_close_tag() {
+        let uri = Uri::from_str("file:///tmp/mismatched_close_tag.ftl").unwrap();
+        let source = "<#if x>\n  ${x}\n</#list>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let diagnostic = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items
+            .into_iter()
+            .find(|d| d.code == Some(NumberOrString::String("mismatched_close_tag".to_owned())))
+            .unwrap();
+        let actions = actions_at(&reactor, diagnostic.range).await;
+        let action = &actions[0];
+        assert_eq!(action.title, "change the close tag to </#if>");
+        let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+        assert_eq!(
+            edits[0].range,
+            Range::new(Position::new(2, 3), Position::new(2, 7))
+        );
+        assert_eq!(edits[0].new_text, "if");
+    }
+
+    #[tokio::test]
+    async fn test_create_missing_import() {
+        let source = "<#import \"lib.ftl\" as lib>\n<#import \"sub/missing.ftl\" as m>\n";
+        let dir = utils::fixture_dir(
+            "create_missing_import",
+            &[("lib.ftl", ""), ("main.ftl", source)],
+        );
+        let uri = Uri::from_file_path(dir.join("main.ftl")).unwrap();
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let diagnostics: Vec<_> = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items
+            .into_iter()
+            .filter(|d| d.code == Some(NumberOrString::String("path_uncanonical".to_owned())))
+            .collect();
+        assert_eq!(diagnostics.len(), 1);
+
+        let actions = actions_at(&reactor, diagnostics[0].range).await;
+        let action = &actions[0];
+        let action = resolve_code_action(&reactor, action.clone());
+        let Some(DocumentChanges::Operations(operations)) =
+            &action.edit.as_ref().unwrap().document_changes
+        else {
+            panic!("expect document change operations");
+        };
+        let DocumentChangeOperation::Op(ResourceOp::Create(create_file)) = &operations[0] else {
+            panic!("expect a file creation");
+        };
+        assert_eq!(
+            create_file.uri,
+            Uri::from_file_path(dir.join("sub/missing.ftl")).unwrap()
+        );
+    }
+}
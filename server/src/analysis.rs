@@ -2,16 +2,32 @@
 // Licensed under the BSD 3-Clause License.
 // SPDX-License-Identifier: BSD-3-Clause
 
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    str::FromStr,
+    sync::Arc,
+};
 
 use thiserror::Error;
 use tower_lsp_server::ls_types::{
-    Diagnostic, FoldingRange, Range, RelatedFullDocumentDiagnosticReport, SemanticToken, Uri,
+    Diagnostic, DocumentSymbol, FoldingRange, Position, Range, RelatedFullDocumentDiagnosticReport,
+    SemanticToken, Uri,
 };
 use tree_sitter::{Node, Point};
 use tree_sitter_freemarker::grammar::Rule;
 
-use crate::{doc::TextDocument, parser::TextParser};
+use crate::{
+    config::Config,
+    diagnosis::sort_diagnostics,
+    doc::TextDocument,
+    escape::Escape,
+    import::{ImportCheck, ImportValidity},
+    include::Include,
+    parser::TextParser,
+    setting::Setting,
+    suppress::{Suppression, override_severity},
+};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Symbol {
@@ -21,13 +37,47 @@ pub struct Symbol {
     pub(crate) range: Range,
 }
 
+#[derive(Clone, Debug)]
+pub struct MacroCall {
+    pub(crate) namespace: String,
+    pub(crate) member: Option<String>,
+    pub(crate) caller: Option<String>,
+    pub(crate) namespace_range: Range,
+    pub(crate) range: Range,
+}
+
+// a quoted template path of <#import> or <#include>
+#[derive(Clone, Debug)]
+pub struct PathReference {
+    pub(crate) text: String,
+    // without the quotes
+    pub(crate) range: Range,
+    pub(crate) directive_range: Range,
+    pub(crate) target: PathBuf,
+}
+
 #[derive(Default)]
 pub struct AnalysisContext {
+    pub config: Arc<Config>,
+    pub imports: Arc<ImportValidity>,
     pub prev_start: Point,
     pub ranges_set: HashSet<usize>,
     pub scope: Vec<Rule>,
     pub import_map: HashMap<String, Vec<Symbol>>,
     pub macro_call_map: HashMap<String, Vec<Symbol>>,
+    // the names of the variables read or written, e.g. "util" of `${util.x}`
+    pub variable_names: HashSet<String>,
+    pub top_level_names: HashSet<String>,
+    pub default_references: Vec<(String, Range)>,
+    // the top-level function definitions and calls, in the textual order
+    pub function_definitions: HashMap<String, Range>,
+    pub function_calls: Vec<(String, Range)>,
+    // expression and start of the <#escape> blocks being analyzed
+    pub escape_stack: Vec<(Option<String>, Position)>,
+    // "lsp-disable-*" comments, applied once all diagnostics are reported
+    pub suppressions: Vec<Suppression>,
+    // the import checks of the previous analysis, by the quoted path
+    pub carried_import_checks: HashMap<String, Arc<ImportCheck>>,
 }
 
 #[derive(Error, Debug)]
@@ -42,25 +92,125 @@ pub struct Analysis {
     full_diagnostic: RelatedFullDocumentDiagnosticReport,
     folding_range: Vec<FoldingRange>,
     symbol_map: HashMap<String, Vec<Symbol>>,
+    // variables might be assigned multiple times, a <#local> is scoped by its macro or function
+    assignment_map: HashMap<String, Vec<(Symbol, Option<Range>)>>,
     import_uri_map: HashMap<String, Uri>,
+    import_alias_map: HashMap<String, Uri>,
+    macro_calls: Vec<MacroCall>,
+    // declared parameters of each macro, as written
+    macro_parameters: HashMap<String, Vec<String>>,
+    // macros whose body calls <#nested>
+    nested_macros: HashSet<String>,
+    settings: Vec<Setting>,
+    includes: Vec<Include>,
+    escapes: Vec<Escape>,
+    path_references: Vec<PathReference>,
+    // the <#import> statements, valid or not
+    import_checks: HashMap<String, Arc<ImportCheck>>,
+    // byte ranges of the <#import> statements, compared with the input edits of the parser
+    import_ranges: Vec<std::ops::Range<usize>>,
+    // hierarchical outline of the document
+    document_symbols: Vec<DocumentSymbol>,
+    suppressions: Vec<Suppression>,
+}
+
+// The whole analysis of a template without a language server session, e.g. for a batch
+// linter, with the default settings. The imports are resolved on the disk.
+pub fn analyze_source(uri: &Uri, text: &str) -> Analysis {
+    analyze_source_with(uri, text, Config::default())
+}
+
+// the same with the given settings, e.g. another position encoding for the ranges
+pub fn analyze_source_with(uri: &Uri, text: &str, config: Config) -> Analysis {
+    let (_, _, analysis) = analyze_text(uri, text, Arc::new(config), Default::default());
+    analysis
+}
+
+// the document and the parse tree are kept by a reactor for the later edits
+pub(crate) fn analyze_text(
+    uri: &Uri,
+    text: &str,
+    config: Arc<Config>,
+    imports: Arc<ImportValidity>,
+) -> (TextDocument, TextParser, Analysis) {
+    let doc = TextDocument::new(uri, text).with_position_encoding(config.position_encoding);
+    let parser = TextParser::new(&doc.to_string());
+    let analysis = Analysis::new(&doc, &parser, config, imports);
+    (doc, parser, analysis)
+}
+
+// FreeMarker leaves "<#" and "<@" as static text unless a name follows, e.g. "use <# for
+// directives" in a <pre>, but the grammar parses them as broken tags
+fn is_static_text_tag(node: &Node, doc: &TextDocument) -> bool {
+    if !node.is_error() && !matches!(Rule::from_str(node.kind()), Ok(Rule::MacroCall)) {
+        return false;
+    }
+    let text = doc.get_ranged_text(node.byte_range());
+    text.strip_prefix("<#")
+        .or_else(|| text.strip_prefix("<@"))
+        .is_some_and(|rest| {
+            !rest.starts_with(|c: char| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        })
 }
 
 // TODO: wrap parser methods and document methods
 impl Analysis {
-    pub fn new(doc: &TextDocument, parser: &TextParser) -> Self {
+    pub fn new(
+        doc: &TextDocument,
+        parser: &TextParser,
+        config: Arc<Config>,
+        imports: Arc<ImportValidity>,
+    ) -> Self {
+        Self::with_import_checks(doc, parser, config, imports, HashMap::new())
+    }
+
+    // the imports whose checks are given are not resolved again
+    pub fn with_import_checks(
+        doc: &TextDocument,
+        parser: &TextParser,
+        config: Arc<Config>,
+        imports: Arc<ImportValidity>,
+        import_checks: HashMap<String, Arc<ImportCheck>>,
+    ) -> Self {
         let mut analysis = Analysis {
             ..Default::default()
         };
         let mut ctx = AnalysisContext {
+            config,
+            imports,
+            carried_import_checks: import_checks,
             ..Default::default()
         };
         let ast = parser.get_ast().unwrap();
         analysis.syntatic_analysis(&ast.root_node(), doc, &mut ctx);
+        analysis.close_escapes(doc, &mut ctx);
         analysis.post_syntatic_analysis(doc, &mut ctx);
+        analysis.apply_suppressions(&mut ctx);
+        for diagnostic in &mut analysis
+            .full_diagnostic
+            .full_document_diagnostic_report
+            .items
+        {
+            override_severity(&ctx.config, diagnostic);
+        }
+        sort_diagnostics(
+            &mut analysis
+                .full_diagnostic
+                .full_document_diagnostic_report
+                .items,
+        );
         analysis
     }
 
     fn syntatic_analysis(&mut self, node: &Node, doc: &TextDocument, ctx: &mut AnalysisContext) {
+        // settings and includes are not parsed by the grammar, skip their ERROR nodes
+        if self.analyze_setting(node, doc)
+            || self.analyze_include(node, doc, ctx)
+            || self.analyze_escape(node, doc, ctx)
+            || is_static_text_tag(node, doc)
+        {
+            return;
+        }
         // semantic highlight
         self.analyze_semantic_highlight(node, doc, ctx);
         // folding range
@@ -69,6 +219,7 @@ impl Analysis {
         self.analyze_syntatic_symbols(node, doc, ctx);
         // diagnostics
         self.analyze_diagnostic_report(node, doc, ctx);
+        self.analyze_suppression(node, doc, ctx);
         // Perform a DFS traversing
         for i in 0..node.child_count() {
             if let Some(child) = node.child(i) {
@@ -100,6 +251,65 @@ impl Analysis {
         }
     }
 
+    pub fn add_assignment(&mut self, name: &str, symbol: Symbol, scope: Option<Range>) {
+        self.assignment_map
+            .entry(name.to_owned())
+            .or_default()
+            .push((symbol, scope));
+    }
+
+    // the nearest assignment which precedes the position, in scope of it
+    pub fn find_assignment(&self, name: &str, position: &Position) -> Option<&Symbol> {
+        self.assignment_map
+            .get(name)?
+            .iter()
+            .rev()
+            .find(|(symbol, scope)| {
+                symbol.range.start <= *position
+                    && scope.is_none_or(|scope| scope.start <= *position && *position <= scope.end)
+            })
+            .map(|(symbol, _)| symbol)
+    }
+
+    pub fn find_macro_definition(&self, name: &str) -> Option<Symbol> {
+        self.symbol_map
+            .get(name)
+            .and_then(|symbols| symbols.iter().find(|s| s.rule == Rule::MacroName))
+            .copied()
+    }
+
+    pub fn add_macro_parameters(&mut self, name: &str, parameters: Vec<String>) {
+        self.macro_parameters.insert(name.to_owned(), parameters);
+    }
+
+    pub fn get_macro_parameters(&self, name: &str) -> Option<&Vec<String>> {
+        self.macro_parameters.get(name)
+    }
+
+    pub fn mark_macro_nested(&mut self, name: &str) {
+        self.nested_macros.insert(name.to_owned());
+    }
+
+    pub fn macro_uses_nested(&self, name: &str) -> bool {
+        self.nested_macros.contains(name)
+    }
+
+    pub fn add_macro_call(&mut self, call: MacroCall) {
+        self.macro_calls.push(call);
+    }
+
+    pub fn get_macro_calls(&self) -> &Vec<MacroCall> {
+        &self.macro_calls
+    }
+
+    pub fn record_import_alias(&mut self, alias: &str, uri: Uri) {
+        self.import_alias_map.insert(alias.to_owned(), uri);
+    }
+
+    pub fn get_import_alias(&self, alias: &str) -> Option<&Uri> {
+        self.import_alias_map.get(alias)
+    }
+
     pub fn record_valid_import(&mut self, path: &str, uri: Uri) {
         self.import_uri_map.insert(path.to_owned(), uri);
     }
@@ -108,6 +318,77 @@ impl Analysis {
         self.import_uri_map.get(path)
     }
 
+    pub fn get_valid_imports(&self) -> impl Iterator<Item = &Uri> {
+        self.import_uri_map.values()
+    }
+
+    // symbols are added in DFS order, so the enclosing one is always the last
+    pub fn add_document_symbol(&mut self, symbol: DocumentSymbol) {
+        let mut siblings = &mut self.document_symbols;
+        while siblings.last().is_some_and(|last| {
+            last.range.start <= symbol.range.start && symbol.range.end <= last.range.end
+        }) {
+            siblings = siblings
+                .last_mut()
+                .unwrap()
+                .children
+                .get_or_insert_with(Vec::new);
+        }
+        siblings.push(symbol);
+    }
+
+    pub fn get_document_symbols(&self) -> Vec<DocumentSymbol> {
+        self.document_symbols.clone()
+    }
+
+    pub fn record_import_check(&mut self, path: &str, check: Arc<ImportCheck>) {
+        self.import_checks.insert(path.to_owned(), check);
+    }
+
+    pub fn get_import_checks(&self) -> &HashMap<String, Arc<ImportCheck>> {
+        &self.import_checks
+    }
+
+    pub fn add_import_range(&mut self, range: std::ops::Range<usize>) {
+        self.import_ranges.push(range);
+    }
+
+    pub fn get_import_ranges(&self) -> &Vec<std::ops::Range<usize>> {
+        &self.import_ranges
+    }
+
+    pub fn add_path_reference(&mut self, reference: PathReference) {
+        self.path_references.push(reference);
+    }
+
+    pub fn get_path_references(&self) -> &Vec<PathReference> {
+        &self.path_references
+    }
+
+    pub fn add_include(&mut self, include: Include) {
+        self.includes.push(include);
+    }
+
+    pub fn get_includes(&self) -> &Vec<Include> {
+        &self.includes
+    }
+
+    pub fn add_escape(&mut self, escape: Escape) {
+        self.escapes.push(escape);
+    }
+
+    pub fn get_escapes(&self) -> &Vec<Escape> {
+        &self.escapes
+    }
+
+    pub fn add_setting(&mut self, setting: Setting) {
+        self.settings.push(setting);
+    }
+
+    pub fn get_settings(&self) -> &Vec<Setting> {
+        &self.settings
+    }
+
     pub fn add_diagnostic(&mut self, item: Diagnostic) {
         self.full_diagnostic
             .full_document_diagnostic_report
@@ -122,6 +403,16 @@ impl Analysis {
             .extend(items);
     }
 
+    pub fn retain_diagnostics<F>(&mut self, func: F)
+    where
+        F: FnMut(&Diagnostic) -> bool,
+    {
+        self.full_diagnostic
+            .full_document_diagnostic_report
+            .items
+            .retain(func);
+    }
+
     pub fn add_folding_range(&mut self, range: FoldingRange) {
         self.folding_range.push(range);
     }
@@ -131,6 +422,14 @@ impl Analysis {
     }
 
     // For LSP responses
+    pub fn set_suppressions(&mut self, suppressions: Vec<Suppression>) {
+        self.suppressions = suppressions;
+    }
+
+    pub fn get_suppressions(&self) -> &Vec<Suppression> {
+        &self.suppressions
+    }
+
     pub fn get_analyzed_full_diagnostics(&self) -> RelatedFullDocumentDiagnosticReport {
         self.full_diagnostic.clone()
     }
@@ -168,6 +467,33 @@ pub trait SymbolAnalysis {
     fn post_syntatic_analysis(&mut self, doc: &TextDocument, ctx: &mut AnalysisContext);
 }
 
+pub trait EscapeAnalysis {
+    // returns true if the node is an <#escape> or <#noescape> tag
+    fn analyze_escape(
+        &mut self,
+        node: &Node,
+        doc: &TextDocument,
+        ctx: &mut AnalysisContext,
+    ) -> bool;
+
+    fn close_escapes(&mut self, doc: &TextDocument, ctx: &mut AnalysisContext);
+}
+
+pub trait IncludeAnalysis {
+    // returns true if the node is an <#include> directive
+    fn analyze_include(
+        &mut self,
+        node: &Node,
+        doc: &TextDocument,
+        ctx: &mut AnalysisContext,
+    ) -> bool;
+}
+
+pub trait SettingAnalysis {
+    // returns true if the node is a <#setting> directive
+    fn analyze_setting(&mut self, node: &Node, doc: &TextDocument) -> bool;
+}
+
 pub trait DiagnosticAnalysis {
     fn analyze_diagnostic_report(
         &mut self,
@@ -176,3 +502,41 @@ pub trait DiagnosticAnalysis {
         ctx: &mut AnalysisContext,
     );
 }
+
+pub trait SuppressAnalysis {
+    fn analyze_suppression(&mut self, node: &Node, doc: &TextDocument, ctx: &mut AnalysisContext);
+
+    // drops the diagnostics disabled by the comments
+    fn apply_suppressions(&mut self, ctx: &mut AnalysisContext);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tower_lsp_server::ls_types::{NumberOrString, Position, Uri};
+
+    use super::analyze_source;
+    use crate::reactor::Reactor;
+
+    #[test]
+    fn test_analyze_source() {
+        let uri = Uri::from_str("file:///tmp/analysis.ftl").unwrap();
+        let source = "<#macro foo>\n</#macro>\n<#list xs as x><#break></#list>\n";
+        let analysis = analyze_source(&uri, source);
+        let diagnostics = analysis.get_analyzed_full_diagnostics();
+        let diagnostic = &diagnostics.full_document_diagnostic_report.items[0];
+        assert_eq!(
+            diagnostic.code,
+            Some(NumberOrString::String("deprecated_list_break".to_owned()))
+        );
+        assert_eq!(diagnostic.range.start, Position::new(2, 15));
+        assert_eq!(analysis.get_document_symbols()[0].name, "foo");
+        // the same as the analysis of an opened document
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        assert_eq!(
+            reactor.get_analysis().get_analyzed_full_diagnostics(),
+            diagnostics
+        );
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+// The hover and completion assets are embedded, a development build can read them from
+// a directory instead when they are reloaded, see `Config::assets_dir`
+
+use std::{borrow::Cow, fs, path::Path};
+
+use rust_embed::Embed;
+
+fn collect_toml_files(root: &Path, dir: &Path, files: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_toml_files(root, &path, files);
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+            && let Ok(relative) = path.strip_prefix(root)
+        {
+            files.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+}
+
+// e.g. "built-ins/c.toml" of "hover", with the files which are only in the directory
+pub fn asset_files<E: Embed>(dir: Option<&Path>, folder: &str) -> Vec<String> {
+    let mut files: Vec<String> = E::iter().map(|file| file.into_owned()).collect();
+    if let Some(dir) = dir {
+        let root = dir.join(folder);
+        let mut extra = vec![];
+        collect_toml_files(&root, &root, &mut extra);
+        extra.retain(|file| !files.contains(file));
+        files.extend(extra);
+    }
+    files
+}
+
+// the file of the directory overrides the embedded one
+pub fn asset_bytes<E: Embed>(
+    dir: Option<&Path>,
+    folder: &str,
+    file: &str,
+) -> Option<Cow<'static, [u8]>> {
+    if let Some(dir) = dir {
+        let path = dir.join(folder).join(file);
+        if let Ok(bytes) = fs::read(&path) {
+            return Some(Cow::Owned(bytes));
+        }
+    }
+    E::get(file).map(|embedded| embedded.data)
+}
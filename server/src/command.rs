@@ -0,0 +1,110 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use serde::Deserialize;
+use tower_lsp_server::{
+    jsonrpc,
+    ls_types::{
+        ExecuteCommandOptions, ExecuteCommandParams, FormattingOptions, LSPAny, Range,
+        ShowDocumentParams, TextDocumentIdentifier, Uri,
+    },
+};
+
+use crate::{client, completion, format, hover, server::CommandFeature, workspace::Workspace};
+
+pub const FORMAT_RANGE: &str = "freemarker/formatRange";
+// opens a page of the FreeMarker manual, e.g. the href of a diagnostic
+pub const OPEN_REFERENCE: &str = "freemarker/openReference";
+// re-reads the hover and completion assets without restarting, for editing them
+pub const RELOAD_ASSETS: &str = "freemarker/reloadAssets";
+
+pub fn execute_command_capability() -> ExecuteCommandOptions {
+    ExecuteCommandOptions {
+        commands: vec![
+            FORMAT_RANGE.to_owned(),
+            OPEN_REFERENCE.to_owned(),
+            RELOAD_ASSETS.to_owned(),
+        ],
+        ..Default::default()
+    }
+}
+
+fn parse_argument<T: for<'de> Deserialize<'de>>(arguments: Vec<LSPAny>) -> jsonrpc::Result<T> {
+    match arguments.into_iter().next() {
+        Some(argument) => serde_json::from_value(argument)
+            .map_err(|e| jsonrpc::Error::invalid_params(e.to_string())),
+        None => Err(jsonrpc::Error::invalid_params("missing command argument")),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FormatRangeArgument {
+    text_document: TextDocumentIdentifier,
+    range: Range,
+    // the indentation of "textDocument/formatting" by default
+    options: Option<FormattingOptions>,
+}
+
+impl Workspace {
+    async fn format_range(&self, arguments: Vec<LSPAny>) -> jsonrpc::Result<Option<LSPAny>> {
+        let argument: FormatRangeArgument = parse_argument(arguments)?;
+        let read_guard = self.reactors.read().await;
+        match read_guard.get(&argument.text_document.uri) {
+            Some(reactor) => {
+                let options = argument
+                    .options
+                    .unwrap_or_else(format::default_formatting_options);
+                let edits = format::format_range_edits(reactor, &argument.range, &options);
+                serde_json::to_value(edits)
+                    .map(Some)
+                    .map_err(|_| jsonrpc::Error::internal_error())
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn reload_assets(&self) -> jsonrpc::Result<Option<LSPAny>> {
+        let config = self.get_config().await;
+        hover::reload_assets(config.assets_dir.as_deref());
+        completion::reload_assets(config.assets_dir.as_deref());
+        // the cached hovers are rendered from the previous assets
+        for reactor in self.reactors.read().await.values() {
+            reactor.get_hover_cache().clear();
+        }
+        Ok(None)
+    }
+}
+
+async fn open_reference(arguments: Vec<LSPAny>) -> jsonrpc::Result<Option<LSPAny>> {
+    let uri: Uri = parse_argument(arguments)?;
+    if let Some(client) = client::get_client() {
+        client
+            .show_document(ShowDocumentParams {
+                uri,
+                external: Some(true),
+                take_focus: Some(true),
+                selection: None,
+            })
+            .await?;
+    }
+    Ok(None)
+}
+
+impl CommandFeature for Workspace {
+    async fn on_execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> jsonrpc::Result<Option<LSPAny>> {
+        match params.command.as_str() {
+            FORMAT_RANGE => self.format_range(params.arguments).await,
+            OPEN_REFERENCE => open_reference(params.arguments).await,
+            RELOAD_ASSETS => self.reload_assets().await,
+            unknown => Err(jsonrpc::Error::invalid_params(format!(
+                "unknown command: {}",
+                unknown
+            ))),
+        }
+    }
+}
@@ -2,28 +2,56 @@
 // Licensed under the BSD 3-Clause License.
 // SPDX-License-Identifier: BSD-3-Clause
 
+use std::{
+    collections::HashMap,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+
 use tower_lsp_server::{
     jsonrpc::Result as JsonRpcResult,
     ls_types::{
         CompletionItem, CompletionItemKind, CompletionItemLabelDetails, CompletionOptions,
         CompletionOptionsCompletionItem, CompletionParams, CompletionResponse, Documentation,
-        InsertTextFormat, InsertTextMode, MarkupContent, MarkupKind, Position,
+        InsertTextFormat, InsertTextMode, MarkupContent, MarkupKind, Position, Uri,
     },
 };
 
 use once_cell::sync::Lazy;
 use rust_embed::Embed;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 use tree_sitter_freemarker::grammar::{Builtin, Rule};
 
+use crate::analysis::Analysis;
+use crate::assets;
+use crate::config::Config;
+use crate::hover::{builtin_group, builtin_markdown};
 use crate::reactor::Reactor;
-use crate::server::CompletionFeature;
+use crate::server::{CompletionFeature, CompletionResolveFeature};
+use crate::setting::FTL_ATTRIBUTES;
+use crate::signature::parameter_name;
+use crate::utils;
+use crate::workspace::Workspace;
 
 #[derive(Embed)]
 #[folder = "assets/completion"]
 struct CompletionAssetPath;
 
+// the structural characters after a directive name, e.g. "<#if " or "<#else>"
+const DIRECTIVE_COMMIT_CHARACTERS: [&str; 2] = [" ", ">"];
+
+const BUILTIN_REFERENCE: &str = "https://freemarker.apache.org/docs/ref_builtins_alphaidx.html";
+
+// the key to look the documentation up when the item is resolved
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "category", rename_all = "lowercase")]
+enum CompletionData {
+    Directive { file: String },
+    Builtin { name: String },
+}
+
 #[derive(Debug, Default, Deserialize)]
 struct LabelDetails {
     detail: Option<String>,
@@ -38,6 +66,8 @@ struct CompletionAssetItem {
     insert_text: String,
     documentation: String,
     label_details: Option<LabelDetails>,
+    // the enclosing blocks where the directive is allowed, anywhere if none
+    scopes: Option<Vec<String>>,
 }
 
 impl CompletionAssetItem {
@@ -57,21 +87,29 @@ impl CompletionAssetItem {
     }
 
     #[tracing::instrument(skip_all)]
-    fn from_embed(file: &str) -> Option<CompletionAssetItem> {
-        if let Some(completion_file) = CompletionAssetPath::get(file) {
-            return CompletionAssetItem::from_bytes(completion_file.data.as_ref());
+    fn from_asset(dir: Option<&Path>, file: &str) -> Option<CompletionAssetItem> {
+        if let Some(bytes) = assets::asset_bytes::<CompletionAssetPath>(dir, "completion", file) {
+            return CompletionAssetItem::from_bytes(bytes.as_ref());
         }
-        tracing::error!("rust-embed file not found: {}:", file);
+        tracing::error!("completion asset not found: {}", file);
         None
     }
 
-    fn as_directive_completion(&self) -> CompletionItem {
+    fn documentation(&self) -> Documentation {
+        Documentation::MarkupContent(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: self.documentation.clone(),
+        })
+    }
+
+    // the documentation is attached by "completionItem/resolve"
+    fn as_directive_completion(&self, file: &str) -> CompletionItem {
         assert_eq!(self.category, "directive");
         CompletionItem {
-            documentation: Some(Documentation::MarkupContent(MarkupContent {
-                kind: MarkupKind::Markdown,
-                value: self.documentation.clone(),
-            })),
+            data: serde_json::to_value(CompletionData::Directive {
+                file: file.to_owned(),
+            })
+            .ok(),
             deprecated: self.deprecated,
             label: self.label.clone(),
             label_details: self
@@ -85,6 +123,21 @@ impl CompletionAssetItem {
             insert_text: Some(self.insert_text.clone()),
             insert_text_format: Some(InsertTextFormat::SNIPPET),
             insert_text_mode: Some(InsertTextMode::ADJUST_INDENTATION),
+            // per item, a space must not commit a built-in or a variable
+            commit_characters: Some(DIRECTIVE_COMMIT_CHARACTERS.map(str::to_owned).to_vec()),
+            ..Default::default()
+        }
+    }
+
+    // only a handful of items, the documentation is attached at once
+    fn as_special_variable_completion(&self) -> CompletionItem {
+        assert_eq!(self.category, "special-var");
+        CompletionItem {
+            label: self.label.clone(),
+            kind: Some(CompletionItemKind::VARIABLE),
+            documentation: Some(self.documentation()),
+            insert_text: Some(self.insert_text.clone()),
+            insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
             ..Default::default()
         }
     }
@@ -92,48 +145,263 @@ impl CompletionAssetItem {
 
 #[derive(Debug, Clone)]
 struct CompletionAsset {
-    directive_completion: Vec<CompletionItem>,
-    // TODO: other completions
+    directive_completion: Vec<(Option<Vec<String>>, CompletionItem)>,
+    special_variable_completion: Vec<CompletionItem>,
+    // the documentation of the directives by their file, attached on resolve
+    directive_documentation: HashMap<String, Documentation>,
 }
 
 impl CompletionAsset {
     fn new() -> Self {
-        let mut directive_completion: Vec<CompletionItem> = vec![];
-        CompletionAssetPath::iter().for_each(|file| {
-            if let Some(item) = CompletionAssetItem::from_embed(&file)
-                && item.category.as_str() == "directive"
-            {
-                directive_completion.push(item.as_directive_completion())
-            }
-        });
+        CompletionAsset::load(None)
+    }
+
+    fn load(dir: Option<&Path>) -> Self {
+        let mut directive_completion = vec![];
+        let mut special_variable_completion = vec![];
+        let mut directive_documentation = HashMap::new();
+        assets::asset_files::<CompletionAssetPath>(dir, "completion")
+            .iter()
+            .for_each(|file| {
+                if let Some(item) = CompletionAssetItem::from_asset(dir, file) {
+                    match item.category.as_str() {
+                        "directive" => {
+                            directive_documentation.insert(file.clone(), item.documentation());
+                            directive_completion
+                                .push((item.scopes.clone(), item.as_directive_completion(file)))
+                        }
+                        "special-var" => {
+                            special_variable_completion.push(item.as_special_variable_completion())
+                        }
+                        _ => {}
+                    }
+                }
+            });
         CompletionAsset {
             directive_completion,
+            special_variable_completion,
+            directive_documentation,
         }
     }
+
+    // e.g. <#elseif> is only offered when the innermost block is an <#if>
+    fn directives_in_scope(&self, scope: Option<&str>) -> Vec<CompletionItem> {
+        self.directive_completion
+            .iter()
+            .filter(|(scopes, _)| match scopes {
+                Some(scopes) => scope.is_some_and(|scope| scopes.iter().any(|s| s == scope)),
+                None => true,
+            })
+            .map(|(_, item)| item.clone())
+            .collect()
+    }
+}
+
+// replaced as a whole by "freemarker/reloadAssets"
+static STATIC_ASSETS: Lazy<RwLock<Arc<CompletionAsset>>> =
+    Lazy::new(|| RwLock::new(Arc::new(CompletionAsset::new())));
+
+fn static_assets() -> Arc<CompletionAsset> {
+    STATIC_ASSETS.read().unwrap().clone()
 }
 
-static STATIC_ASSETS: Lazy<CompletionAsset> = Lazy::new(CompletionAsset::new);
+pub fn reload_assets(dir: Option<&Path>) {
+    *STATIC_ASSETS.write().unwrap() = Arc::new(CompletionAsset::load(dir));
+}
 
-fn completion_for_builtin() -> Vec<CompletionItem> {
-    // todo: improve filter result by partial identifier
+// case-insensitive, all the built-ins for an empty prefix; the built-ins of a group
+// (e.g. "?is_string" of "introspection") are sorted together and labelled with it
+fn completion_for_builtin(prefix: &str) -> Vec<CompletionItem> {
+    let prefix = prefix.to_lowercase();
     Builtin::iter()
-        .map(|i| CompletionItem {
-            label: i.to_string(),
+        .filter(|i| i.to_string().to_lowercase().starts_with(&prefix))
+        .map(|i| {
+            let name = i.to_string();
+            let group = builtin_group(&name);
+            CompletionItem {
+                label: name.clone(),
+                label_details: group.as_ref().map(|group| CompletionItemLabelDetails {
+                    detail: None,
+                    description: Some(group.clone()),
+                }),
+                sort_text: group.as_ref().map(|group| format!("{}.{}", group, name)),
+                kind: Some(CompletionItemKind::FIELD),
+                data: serde_json::to_value(CompletionData::Builtin { name }).ok(),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+// the built-ins of a loop variable, e.g. "?index" of "<#list xs as x>${x?index}</#list>"
+const LOOP_BUILTINS: [&str; 10] = [
+    "counter",
+    "has_next",
+    "index",
+    "is_even_item",
+    "is_first",
+    "is_last",
+    "is_odd_item",
+    "item_cycle",
+    "item_parity",
+    "item_parity_cap",
+];
+
+// sorted before the other built-ins
+fn completion_for_loop_builtin(prefix: &str) -> Vec<CompletionItem> {
+    let prefix = prefix.to_lowercase();
+    LOOP_BUILTINS
+        .iter()
+        .filter(|name| name.starts_with(&prefix))
+        .map(|name| CompletionItem {
+            label: name.to_string(),
+            label_details: Some(CompletionItemLabelDetails {
+                detail: None,
+                description: Some("loop variable".to_owned()),
+            }),
+            sort_text: Some(format!("0.{}", name)),
             kind: Some(CompletionItemKind::FIELD),
+            data: serde_json::to_value(CompletionData::Builtin {
+                name: name.to_string(),
+            })
+            .ok(),
             ..Default::default()
         })
         .collect()
 }
 
-pub fn completion_capability() -> CompletionOptions {
+// the names of " a=1 b" which are supplied already, none when a value is being typed
+fn supplied_names(typed: &str) -> Option<Vec<&str>> {
+    if !typed.starts_with(char::is_whitespace) || typed.contains('<') {
+        return None;
+    }
+    let words: Vec<&str> = typed.split_whitespace().collect();
+    if !typed.ends_with(char::is_whitespace) && words.last().is_some_and(|w| w.contains('=')) {
+        return None;
+    }
+    Some(
+        words
+            .iter()
+            .filter_map(|word| word.split_once('=').map(|(name, _)| name))
+            .collect(),
+    )
+}
+
+// the parameters of "<@foo a=1 |" which are not supplied yet
+fn completion_for_parameters(parameters: &[String], typed: &str) -> Option<Vec<CompletionItem>> {
+    let supplied = supplied_names(typed)?;
+    let items: Vec<CompletionItem> = parameters
+        .iter()
+        .map(|parameter| (parameter_name(parameter), parameter))
+        .filter(|(name, _)| !supplied.contains(name))
+        .map(|(name, parameter)| CompletionItem {
+            label: name.to_owned(),
+            kind: Some(CompletionItemKind::PROPERTY),
+            detail: Some(parameter.clone()),
+            insert_text: Some(format!("{}=", name)),
+            insert_text_format: Some(InsertTextFormat::SNIPPET),
+            ..Default::default()
+        })
+        .collect();
+    (!items.is_empty()).then_some(items)
+}
+
+// the attributes of "<#ftl encoding="UTF-8" |" which are not supplied yet, the hint of
+// the value is the placeholder of the snippet
+fn completion_for_ftl_attributes(typed: &str) -> Option<Vec<CompletionItem>> {
+    let supplied = supplied_names(typed)?;
+    let items: Vec<CompletionItem> = FTL_ATTRIBUTES
+        .iter()
+        .filter(|(name, _)| !supplied.contains(name))
+        .map(|(name, hint)| CompletionItem {
+            label: name.to_string(),
+            kind: Some(CompletionItemKind::PROPERTY),
+            detail: Some(format!("{}={}", name, hint)),
+            insert_text: Some(format!(
+                "{}=${{1:{}}}",
+                name,
+                hint.replace('\\', "\\\\")
+                    .replace('$', "\\$")
+                    .replace('}', "\\}")
+            )),
+            insert_text_format: Some(InsertTextFormat::SNIPPET),
+            ..Default::default()
+        })
+        .collect();
+    (!items.is_empty()).then_some(items)
+}
+
+// the macros defined by an imported template, "foo a b=1" in the detail
+fn completion_for_members(imported: &Analysis, namespace: &str) -> Vec<CompletionItem> {
+    let mut items = vec![];
+    imported.foreach_symbol(|name, symbols| {
+        if symbols[0].rule != Rule::MacroName {
+            return;
+        }
+        let detail = imported.get_macro_parameters(name).map(|parameters| {
+            std::iter::once(name)
+                .chain(parameters.iter().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join(" ")
+        });
+        items.push(CompletionItem {
+            label: name.to_owned(),
+            kind: Some(CompletionItemKind::MODULE),
+            detail,
+            label_details: Some(CompletionItemLabelDetails {
+                detail: None,
+                description: Some(namespace.to_owned()),
+            }),
+            ..Default::default()
+        });
+    });
+    items.sort_by(|a, b| a.label.cmp(&b.label));
+    items
+}
+
+fn resolve_documentation(data: CompletionData) -> Option<Documentation> {
+    match data {
+        CompletionData::Directive { file } => {
+            static_assets().directive_documentation.get(&file).cloned()
+        }
+        CompletionData::Builtin { name } => Some(Documentation::MarkupContent(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: match builtin_group(&name).and_then(|_| builtin_markdown(&name)) {
+                Some(markdown) => markdown,
+                None => format!(
+                    "The `?{}` built-in, see the [built-in reference]({}) for more info.",
+                    name, BUILTIN_REFERENCE
+                ),
+            },
+        })),
+    }
+}
+
+const DEFAULT_TRIGGER_CHARACTERS: [&str; 5] = [
+    "#", // '<#' --> trigger directive
+    "{", // '${' --> trigger interpolation
+    "?", // '?' --> trigger built-ins
+    "@", // "<@" --> trigger macro call
+    ".", // "${." --> trigger special variables, "<@lib." --> trigger namespace members
+];
+
+// the defaults and the configured ones, which must be single characters
+fn trigger_characters(config: &Config) -> Vec<String> {
+    let mut triggers: Vec<String> = DEFAULT_TRIGGER_CHARACTERS.map(str::to_owned).to_vec();
+    for trigger in &config.completion.trigger_characters {
+        if trigger.chars().count() != 1 {
+            tracing::warn!("invalid completion trigger character: {:?}", trigger);
+        } else if !triggers.contains(trigger) {
+            triggers.push(trigger.clone());
+        }
+    }
+    triggers
+}
+
+pub fn completion_capability(config: &Config) -> CompletionOptions {
     CompletionOptions {
-        resolve_provider: Some(false),
-        trigger_characters: Some(vec![
-            "#".to_string(), // '<#' --> trigger directive
-            "{".to_string(), // '${' --> trigger interpolation
-            "?".to_string(), // '?' --> trigger built-ins
-            "@".to_string(), // "<@" --> trigger macro call
-        ]),
+        resolve_provider: Some(true),
+        trigger_characters: Some(trigger_characters(config)),
         completion_item: Some(CompletionOptionsCompletionItem {
             label_details_support: Some(true),
         }),
@@ -141,6 +409,129 @@ pub fn completion_capability() -> CompletionOptions {
     }
 }
 
+impl Reactor {
+    // "lib" of "<@lib.|" or "[@lib.fo|"
+    fn member_namespace(&self, position: &Position) -> Option<String> {
+        let doc = self.get_document();
+        let line_start = doc.position_to_byte(&Position::new(position.line, 0));
+        let text = doc.get_ranged_text(line_start..doc.position_to_byte(position));
+        let is_name = |c: char| c.is_alphanumeric() || c == '_';
+        let head = text.trim_end_matches(is_name).strip_suffix('.')?;
+        let namespace = &head[head.trim_end_matches(is_name).len()..];
+        let opener = &head[..head.len() - namespace.len()];
+        (!namespace.is_empty() && (opener.ends_with("<@") || opener.ends_with("[@")))
+            .then(|| namespace.to_owned())
+    }
+
+    // "up" of "${name?up|}", none if the cursor is not after a '?'
+    fn builtin_prefix(&self, position: &Position) -> Option<String> {
+        let doc = self.get_document();
+        let line_start = doc.position_to_byte(&Position::new(position.line, 0));
+        let text = doc.get_ranged_text(line_start..doc.position_to_byte(position));
+        let head = text.trim_end_matches(|c: char| c.is_alphanumeric() || c == '_');
+        head.ends_with('?').then(|| text[head.len()..].to_owned())
+    }
+
+    // "x" of "<#list xs as x>${x?|}", the name before the '?' is a loop variable in scope
+    fn is_loop_variable_before(&self, position: &Position) -> bool {
+        let doc = self.get_document();
+        let line_start = doc.position_to_byte(&Position::new(position.line, 0));
+        let text = doc.get_ranged_text(line_start..doc.position_to_byte(position));
+        let is_name = |c: char| c.is_alphanumeric() || c == '_';
+        let Some(head) = text.trim_end_matches(is_name).strip_suffix('?') else {
+            return false;
+        };
+        let name = &head[head.trim_end_matches(is_name).len()..];
+        if name.is_empty() {
+            return false;
+        }
+        let name_byte = line_start + head.len() - name.len();
+        let point = utils::lsp_position_to_parser_point(doc, &doc.byte_to_position(name_byte));
+        let mut node_cursor = self.get_parser().get_node_at_point(point);
+        while let Some(node) = node_cursor {
+            if let Ok(Rule::ListClause) = Rule::from_str(node.kind()) {
+                let mut cursor = node.walk();
+                // the loop variable is not visible in the collection
+                if node
+                    .children_by_field_name("iterator", &mut cursor)
+                    .any(|iterator| {
+                        iterator.end_byte() < name_byte
+                            && doc.get_ranged_text(iterator.byte_range()) == name
+                    })
+                {
+                    return true;
+                }
+            }
+            node_cursor = node.parent();
+        }
+        false
+    }
+
+    // " encoding=\"UTF-8\" " of "<#ftl encoding=\"UTF-8\" |", the header is the first
+    // directive of the template and may be not closed yet
+    fn ftl_header_arguments(&self, position: &Position) -> Option<String> {
+        let doc = self.get_document();
+        let cursor = doc.position_to_byte(position);
+        // only the first directive is read, up to its end or the cursor
+        let mut chars = doc.rope.chars().peekable();
+        let mut start = 0;
+        while let Some(c) = chars.next_if(|c| c.is_whitespace()) {
+            start += c.len_utf8();
+        }
+        let keyword: String = chars.by_ref().take(5).collect();
+        if keyword != "<#ftl" && keyword != "[#ftl" {
+            return None;
+        }
+        start += keyword.len();
+        if cursor < start {
+            return None;
+        }
+        let mut end = start;
+        for c in chars {
+            if end >= cursor {
+                break;
+            }
+            if matches!(c, '>' | ']') {
+                return None;
+            }
+            end += c.len_utf8();
+        }
+        Some(doc.get_ranged_text(start..cursor))
+    }
+
+    // "${.|" or "<#assign x = .|", but not a member access like "${user.|"
+    fn is_special_variable_context(&self, position: &Position) -> bool {
+        let doc = self.get_document();
+        let line_start = doc.position_to_byte(&Position::new(position.line, 0));
+        let text = doc.get_ranged_text(line_start..doc.position_to_byte(position));
+        let head = text.trim_end_matches(' ');
+        if head
+            .chars()
+            .last()
+            .is_some_and(|c| c.is_alphanumeric() || "_.)]}\"'".contains(c))
+        {
+            return false;
+        }
+        // an interpolation or a directive tag which is still open
+        let in_interpolation = head.rfind("${") > head.rfind('}');
+        let in_directive = head.rfind("<#").max(head.rfind("<@")) > head.rfind('>');
+        in_interpolation || in_directive
+    }
+
+    // the kind of the innermost directive statement which contains the position
+    fn enclosing_directive(&self, position: &Position) -> Option<&'static str> {
+        let point = utils::lsp_position_to_parser_point(self.get_document(), position);
+        let mut node_cursor = self.get_parser().get_node_at_point(point);
+        while let Some(node) = node_cursor {
+            if node.kind().ends_with("_stmt") {
+                return Some(node.kind());
+            }
+            node_cursor = node.parent();
+        }
+        None
+    }
+}
+
 impl CompletionFeature for Reactor {
     fn list_macro_definitions(&self) -> Vec<CompletionItem> {
         let mut macro_definitions = vec![];
@@ -171,6 +562,22 @@ impl CompletionFeature for Reactor {
         &self,
         params: CompletionParams,
     ) -> JsonRpcResult<Option<CompletionResponse>> {
+        // triggered by '?' or typing after it, expect a built-in
+        if let Some(prefix) = self.builtin_prefix(&params.text_document_position.position) {
+            let mut items = vec![];
+            if self.is_loop_variable_before(&params.text_document_position.position) {
+                items = completion_for_loop_builtin(&prefix);
+            }
+            items.extend(completion_for_builtin(&prefix));
+            return Ok(Some(CompletionResponse::Array(items)));
+        }
+        // typing the attributes of the header, e.g. "<#ftl strip|"
+        if let Some(items) = self
+            .ftl_header_arguments(&params.text_document_position.position)
+            .and_then(|typed| completion_for_ftl_attributes(&typed))
+        {
+            return Ok(Some(CompletionResponse::Array(items)));
+        }
         if params
             .context
             .as_ref()
@@ -195,19 +602,22 @@ impl CompletionFeature for Reactor {
         let mut result: Option<CompletionResponse> = None;
 
         match trigger.as_str() {
-            "#" if prev_char == '<' => {
-                // triggered by '<#', expect a directive keyword
+            "#" if prev_char == '<' || prev_char == '[' => {
+                // triggered by '<#' or '[#', expect a directive keyword
+                let scope = self.enclosing_directive(&trigger_position);
                 result = Some(CompletionResponse::Array(
-                    STATIC_ASSETS.directive_completion.clone(),
+                    static_assets().directives_in_scope(scope),
                 ));
             }
-            "@" if prev_char == '<' => {
-                // triggered by '<@', expect a macro call
+            "@" if prev_char == '<' || prev_char == '[' => {
+                // triggered by '<@' or '[@', expect a macro call
                 result = Some(CompletionResponse::Array(self.list_macro_definitions()));
             }
-            "?" => {
-                // triggered by '?', expect a built-in
-                result = Some(CompletionResponse::Array(completion_for_builtin()));
+            "." if self.is_special_variable_context(&trigger_position) => {
+                // triggered by '.' in an expression, expect a special variable
+                result = Some(CompletionResponse::Array(
+                    static_assets().special_variable_completion.clone(),
+                ));
             }
             _ => {}
         }
@@ -215,13 +625,80 @@ impl CompletionFeature for Reactor {
     }
 }
 
+impl Workspace {
+    // inside a macro call, the parameters are resolved from the imported template if needed
+    pub(crate) async fn macro_parameter_completion(
+        &self,
+        reactors: &HashMap<Uri, Reactor>,
+        reactor: &Reactor,
+        position: &Position,
+    ) -> Option<Vec<CompletionItem>> {
+        let (call, typed) = reactor.open_macro_call(position)?;
+        let parameters = self
+            .resolve_macro_parameters(reactors, reactor.get_analysis(), call)
+            .await?;
+        completion_for_parameters(&parameters, &typed)
+    }
+
+    // the macros of the imported template after "<@lib."
+    pub(crate) async fn namespace_member_completion(
+        &self,
+        reactors: &HashMap<Uri, Reactor>,
+        reactor: &Reactor,
+        position: &Position,
+    ) -> Option<Vec<CompletionItem>> {
+        let namespace = reactor.member_namespace(position)?;
+        let import_uri = reactor.get_analysis().get_import_alias(&namespace)?;
+        Some(match reactors.get(import_uri) {
+            Some(imported) => completion_for_members(imported.get_analysis(), &namespace),
+            None => completion_for_members(&*self.imports.get(import_uri).await?, &namespace),
+        })
+    }
+}
+
+impl CompletionResolveFeature for Workspace {
+    async fn on_completion_resolve(
+        &self,
+        mut item: CompletionItem,
+    ) -> JsonRpcResult<CompletionItem> {
+        // the items without data (e.g. macros) are complete already
+        if item.documentation.is_none()
+            && let Some(data) = item.data.clone()
+            && let Ok(data) = serde_json::from_value::<CompletionData>(data)
+        {
+            item.documentation = resolve_documentation(data);
+        }
+        Ok(item)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::completion::{CompletionAsset, CompletionAssetItem};
+    use std::str::FromStr;
+
+    use serde_json::json;
+    use tower_lsp_server::ls_types::{
+        CompletionContext, CompletionItem, CompletionParams, CompletionResponse,
+        CompletionTriggerKind, DidOpenTextDocumentParams, Documentation, Position,
+        TextDocumentIdentifier, TextDocumentItem, TextDocumentPositionParams, Uri,
+    };
+
+    use crate::{
+        completion::{
+            CompletionAsset, CompletionAssetItem, LOOP_BUILTINS, completion_capability,
+            completion_for_builtin, completion_for_ftl_attributes,
+        },
+        config::Config,
+        reactor::Reactor,
+        server::{CompletionFeature, CompletionResolveFeature},
+        setting::FTL_ATTRIBUTES,
+        utils,
+        workspace::Workspace,
+    };
 
     #[test]
     fn test_asset_assign_directive() {
-        let item = CompletionAssetItem::from_embed("assign.toml");
+        let item = CompletionAssetItem::from_asset(None, "assign.toml");
         assert!(item.is_some());
         let item = item.unwrap();
         assert_eq!(item.category.as_str(), "directive");
@@ -231,7 +708,7 @@ mod tests {
 
     #[test]
     fn test_asset_assign_capture_directive() {
-        let item = CompletionAssetItem::from_embed("assign(capture).toml");
+        let item = CompletionAssetItem::from_asset(None, "assign(capture).toml");
         assert!(item.is_some());
         let item = item.unwrap();
         assert_eq!(item.category.as_str(), "directive");
@@ -249,5 +726,399 @@ mod tests {
     fn test_asset_directives() {
         let asset = CompletionAsset::new();
         assert!(!asset.directive_completion.is_empty());
+        assert_eq!(asset.special_variable_completion.len(), 10);
+    }
+
+    // typed after the trigger character, or invoked without one
+    fn completion_params(uri: Uri, position: Position, trigger: Option<&str>) -> CompletionParams {
+        CompletionParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: Some(CompletionContext {
+                trigger_kind: match trigger {
+                    Some(_) => CompletionTriggerKind::TRIGGER_CHARACTER,
+                    None => CompletionTriggerKind::INVOKED,
+                },
+                trigger_character: trigger.map(str::to_owned),
+            }),
+        }
+    }
+
+    fn response_items(response: Option<CompletionResponse>) -> Vec<CompletionItem> {
+        match response {
+            Some(CompletionResponse::Array(items)) => items,
+            _ => vec![],
+        }
+    }
+
+    async fn trigger_items(
+        reactor: &Reactor,
+        position: Position,
+        trigger: Option<&str>,
+    ) -> Vec<CompletionItem> {
+        let params = completion_params(reactor.get_document().uri(), position, trigger);
+        response_items(reactor.on_completion(params).await.unwrap())
+    }
+
+    async fn trigger_labels(
+        reactor: &Reactor,
+        position: Position,
+        trigger: Option<&str>,
+    ) -> Vec<String> {
+        trigger_items(reactor, position, trigger)
+            .await
+            .into_iter()
+            .map(|item| item.label)
+            .collect()
+    }
+
+    async fn directive_labels(reactor: &Reactor, position: Position) -> Vec<String> {
+        trigger_labels(reactor, position, Some("#")).await
+    }
+
+    #[tokio::test]
+    async fn test_directive_completion_scope() {
+        let uri = Uri::from_str("file:///tmp/completion.ftl").unwrap();
+        let source = "<#if a>\n<#\n</#if>\n<#switch s>\n<#case 1>\n<#\n</#switch>\n<#\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+
+        let in_if = directive_labels(&reactor, Position::new(1, 2)).await;
+        assert!(in_if.contains(&"elseif".to_owned()));
+        assert!(in_if.contains(&"else".to_owned()));
+        assert!(!in_if.contains(&"case".to_owned()));
+        assert!(in_if.contains(&"if".to_owned()));
+
+        let in_switch = directive_labels(&reactor, Position::new(5, 2)).await;
+        assert!(in_switch.contains(&"case".to_owned()));
+        assert!(in_switch.contains(&"default".to_owned()));
+        assert!(!in_switch.contains(&"elseif".to_owned()));
+
+        let top_level = directive_labels(&reactor, Position::new(7, 2)).await;
+        for label in ["elseif", "else", "case", "default"] {
+            assert!(!top_level.contains(&label.to_owned()), "{}", label);
+        }
+        assert!(top_level.contains(&"if".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn test_special_variable_completion() {
+        let uri = Uri::from_str("file:///tmp/completion.ftl").unwrap();
+        let source = "${.}\n<#assign x = .>\n${user.}\nHello.\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+
+        for position in [Position::new(0, 3), Position::new(1, 14)] {
+            let labels = trigger_labels(&reactor, position, Some(".")).await;
+            for label in ["now", "data_model", "locale", "version"] {
+                assert!(labels.contains(&label.to_owned()), "{}", label);
+            }
+        }
+        // a member access or plain text
+        assert!(
+            trigger_labels(&reactor, Position::new(2, 7), Some("."))
+                .await
+                .is_empty()
+        );
+        assert!(
+            trigger_labels(&reactor, Position::new(3, 6), Some("."))
+                .await
+                .is_empty()
+        );
+
+        let asset = CompletionAsset::new();
+        let now = asset
+            .special_variable_completion
+            .iter()
+            .find(|item| item.label == "now")
+            .unwrap();
+        let Some(Documentation::MarkupContent(content)) = &now.documentation else {
+            panic!("expect markdown documentation");
+        };
+        assert!(content.value.contains("current date-time"));
+    }
+
+    #[test]
+    fn test_commit_characters() {
+        let asset = CompletionAsset::new();
+        assert!(asset.directive_completion.iter().all(|(_, item)| {
+            item.commit_characters.as_deref() == Some(&[" ".to_owned(), ">".to_owned()][..])
+        }));
+        assert!(
+            completion_for_builtin("")
+                .iter()
+                .all(|item| item.commit_characters.is_none())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_completion_resolve() {
+        let workspace = Workspace::new();
+        let asset = CompletionAsset::new();
+        let (_, directive) = asset
+            .directive_completion
+            .iter()
+            .find(|(_, item)| item.label == "if")
+            .unwrap();
+        assert!(directive.documentation.is_none());
+        let resolved = workspace
+            .on_completion_resolve(directive.clone())
+            .await
+            .unwrap();
+        let Some(Documentation::MarkupContent(content)) = resolved.documentation else {
+            panic!("expect markdown documentation");
+        };
+        assert!(content.value.contains("The `<#if>` directive"));
+
+        let builtin = completion_for_builtin("").into_iter().next().unwrap();
+        assert!(builtin.documentation.is_none());
+        let resolved = workspace.on_completion_resolve(builtin).await.unwrap();
+        let Some(Documentation::MarkupContent(content)) = resolved.documentation else {
+            panic!("expect markdown documentation");
+        };
+        assert!(content.value.contains(&format!("`?{}`", resolved.label)));
+    }
+
+    #[tokio::test]
+    async fn test_introspection_builtins() {
+        let workspace = Workspace::new();
+        let items = completion_for_builtin("");
+        let introspection: Vec<&CompletionItem> = items
+            .iter()
+            .filter(|item| {
+                item.label_details
+                    .as_ref()
+                    .is_some_and(|ld| ld.description.as_deref() == Some("introspection"))
+            })
+            .collect();
+        for name in ["has_content", "is_string", "is_sequence", "is_hash"] {
+            assert!(introspection.iter().any(|item| item.label == name));
+        }
+        assert!(introspection.iter().all(|item| {
+            item.sort_text
+                .as_ref()
+                .is_some_and(|sort_text| sort_text.starts_with("introspection."))
+        }));
+        // a number built-in despite the prefix
+        let is_nan = items.iter().find(|item| item.label == "is_nan").unwrap();
+        assert!(is_nan.label_details.is_none());
+
+        let is_string = introspection
+            .iter()
+            .find(|item| item.label == "is_string")
+            .unwrap();
+        let resolved = workspace
+            .on_completion_resolve((*is_string).clone())
+            .await
+            .unwrap();
+        let Some(Documentation::MarkupContent(content)) = resolved.documentation else {
+            panic!("expect markdown documentation");
+        };
+        assert!(content.value.contains("# is_string"));
+    }
+
+    #[tokio::test]
+    async fn test_loop_builtins() {
+        let uri = Uri::from_str("file:///tmp/completion.ftl").unwrap();
+        let source = "<#list items as item>\n${item?}${item?has}${items?}\n</#list>\n${item?}\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let labels =
+            |line, character| trigger_labels(&reactor, Position::new(line, character), Some("?"));
+        let in_loop = labels(1, 7).await;
+        assert_eq!(in_loop[..LOOP_BUILTINS.len()], LOOP_BUILTINS);
+        assert!(in_loop.contains(&"upper_case".to_owned()));
+        assert_eq!(labels(1, 18).await[0], "has_next");
+        // the collection and the name after the loop are not loop variables
+        assert!(!labels(1, 27).await.contains(&"index".to_owned()));
+        assert!(!labels(3, 7).await.contains(&"index".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn test_builtin_prefix() {
+        let uri = Uri::from_str("file:///tmp/completion.ftl").unwrap();
+        let source = "${name?}\n${name?UP}\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let labels =
+            |line, character| trigger_labels(&reactor, Position::new(line, character), None);
+        assert_eq!(labels(0, 7).await.len(), completion_for_builtin("").len());
+        let filtered = labels(1, 9).await;
+        assert!(filtered.contains(&"upper_case".to_owned()));
+        assert!(filtered.iter().all(|label| label.starts_with("up")));
+    }
+
+    #[tokio::test]
+    async fn test_ftl_attribute_completion() {
+        let uri = Uri::from_str("file:///tmp/completion.ftl").unwrap();
+        let source = "<#ftl >\n${x}\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let items = |position| trigger_items(&reactor, position, None);
+        let attributes = items(Position::new(0, 6)).await;
+        let labels: Vec<&str> = attributes.iter().map(|item| item.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            vec![
+                "encoding",
+                "strip_whitespace",
+                "strip_text",
+                "strict_syntax",
+                "ns_prefixes",
+                "attributes",
+                "output_format",
+                "auto_esc",
+            ]
+        );
+        assert_eq!(
+            attributes[0].insert_text.as_deref(),
+            Some("encoding=${1:\"UTF-8\"}")
+        );
+        assert_eq!(
+            attributes[4].insert_text.as_deref(),
+            Some("ns_prefixes=${1:{\"D\": \"http://example.com/ns\"\\}}")
+        );
+        // not after the header
+        assert!(items(Position::new(1, 2)).await.is_empty());
+
+        // the supplied ones are not offered again, nor while typing a value
+        let source = "<#ftl encoding=\"UTF-8\" \n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let typed = completion_for_ftl_attributes(" encoding=\"UTF-8\" ").unwrap();
+        assert_eq!(typed.len(), FTL_ATTRIBUTES.len() - 1);
+        assert!(typed.iter().all(|item| item.label != "encoding"));
+        assert!(completion_for_ftl_attributes(" encoding=").is_none());
+        assert_eq!(
+            reactor.ftl_header_arguments(&Position::new(0, 23)),
+            Some(" encoding=\"UTF-8\" ".to_owned())
+        );
+        // only the first directive, which is still open at the position, is the header
+        for (source, position) in [
+            ("\n  [#ftl ]\n${x}\n", Position::new(2, 2)),
+            ("<#if x>\n<#ftl \n", Position::new(1, 6)),
+            ("<#ftl \n", Position::new(0, 3)),
+        ] {
+            let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+            assert!(
+                reactor.ftl_header_arguments(&position).is_none(),
+                "{:?}",
+                source
+            );
+        }
+        let reactor = Reactor::new(
+            &uri,
+            "\n  [#ftl en",
+            0,
+            Default::default(),
+            Default::default(),
+        );
+        assert_eq!(
+            reactor.ftl_header_arguments(&Position::new(1, 11)),
+            Some(" en".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_namespace_member_completion() {
+        let config = Config::from_initialization_options(Some(json!({
+            "completion": { "triggerCharacters": ["."] }
+        })));
+        let triggers = completion_capability(&config).trigger_characters.unwrap();
+        assert_eq!(triggers.iter().filter(|t| t.as_str() == ".").count(), 1);
+
+        let source = "<#import \"lib.ftl\" as lib>\n<@lib.\n[@lib.ba\n${lib.\n";
+        let dir = utils::fixture_dir(
+            "member_completion",
+            &[
+                (
+                    "lib.ftl",
+                    "<#macro foo>\n</#macro>\n<#macro bar title size=1>\n</#macro>\n",
+                ),
+                ("main.ftl", source),
+            ],
+        );
+        let uri = Uri::from_file_path(dir.join("main.ftl")).unwrap();
+        let workspace = Workspace::new();
+        workspace.set_config(config).await;
+        workspace
+            .on_did_open(&DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: uri.clone(),
+                    language_id: "ftl".to_owned(),
+                    version: 0,
+                    text: source.to_owned(),
+                },
+            })
+            .await;
+        let completion = |line, character| {
+            let workspace = &workspace;
+            let uri = uri.clone();
+            async move {
+                let params = completion_params(uri, Position::new(line, character), Some("."));
+                response_items(workspace.on_completion(params).await.unwrap())
+                    .into_iter()
+                    .map(|item| (item.label, item.detail.unwrap_or_default()))
+                    .collect::<Vec<_>>()
+            }
+        };
+        let members = vec![
+            ("bar".to_owned(), "bar title size=1".to_owned()),
+            ("foo".to_owned(), "foo".to_owned()),
+        ];
+        assert_eq!(completion(1, 6).await, members);
+        // the square bracket syntax, the client filters by the typed prefix
+        assert_eq!(completion(2, 8).await, members);
+        // not a macro call
+        assert!(completion(3, 6).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_macro_parameter_completion() {
+        let source = "<#import \"lib.ftl\" as lib>\n<#macro foo a b=1 c...>\n</#macro>\n<@foo a=1 />\n<@lib.bar />\n<@foo b= />\n";
+        let dir = utils::fixture_dir(
+            "parameter_completion",
+            &[
+                ("lib.ftl", "<#macro bar title>\n</#macro>\n"),
+                ("main.ftl", source),
+            ],
+        );
+        let uri = Uri::from_file_path(dir.join("main.ftl")).unwrap();
+        let workspace = Workspace::new();
+        workspace
+            .on_did_open(&DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: uri.clone(),
+                    language_id: "ftl".to_owned(),
+                    version: 0,
+                    text: source.to_owned(),
+                },
+            })
+            .await;
+        let completion = |line, character| {
+            let workspace = &workspace;
+            let uri = uri.clone();
+            async move {
+                let params = completion_params(uri, Position::new(line, character), None);
+                response_items(workspace.on_completion(params).await.unwrap())
+                    .into_iter()
+                    .map(|item| (item.label, item.insert_text.unwrap_or_default()))
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        // "a" is supplied already
+        assert_eq!(
+            completion(3, 10).await,
+            vec![
+                ("b".to_owned(), "b=".to_owned()),
+                ("c".to_owned(), "c=".to_owned())
+            ]
+        );
+        // the macro of an imported template
+        assert_eq!(
+            completion(4, 10).await,
+            vec![("title".to_owned(), "title=".to_owned())]
+        );
+        // a value is expected
+        assert!(completion(5, 8).await.is_empty());
     }
 }
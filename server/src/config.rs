@@ -0,0 +1,251 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Deserializer};
+use tower_lsp_server::ls_types::{DiagnosticSeverity, LSPAny};
+
+use crate::doc::PositionEncodingKind;
+
+// Server settings, read from `initializationOptions`
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Config {
+    // opt-in diagnostics, e.g. ["undefined_in_default"]
+    pub enabled_diagnostics: HashSet<String>,
+    // diagnostics which are never reported, e.g. ["undocumented_close_tag"]
+    pub disabled_diagnostics: HashSet<String>,
+    // e.g. {"ambiguous_string_literal": "hint"}, an invalid severity keeps the default one
+    #[serde(deserialize_with = "deserialize_severities")]
+    pub diagnostic_severity: HashMap<String, DiagnosticSeverity>,
+    // rewrites loader-relative import paths, applied in order
+    pub path_mappings: Vec<PathMapping>,
+    pub folding: FoldingConfig,
+    pub completion: CompletionConfig,
+    pub inlay_hints: InlayHintsConfig,
+    // capabilities which are not announced, e.g. ["formatting", "semanticTokens"]
+    pub disabled_features: HashSet<String>,
+    // analyze the templates under the root in the background after initialization
+    pub eager_index: bool,
+    // macros or namespaces provided by the runtime, e.g. ["spring", "renderWidget"]
+    pub known_macros: HashSet<String>,
+    // how many imports are followed to find a circular one
+    pub max_import_depth: Option<usize>,
+    // the variables of the data-model, e.g. ["user", "request"]
+    pub data_model_roots: HashSet<String>,
+    // hover shows the syntax node under the cursor instead, for extending the grammar
+    pub debug_hover: bool,
+    // "freemarker/reloadAssets" reads the "hover" and "completion" assets under it first,
+    // e.g. the "server/assets" of a checkout
+    pub assets_dir: Option<PathBuf>,
+    // the limit of "max_line_length", 120 by default
+    pub max_line_length: Option<usize>,
+    // the same as "unused_macro" in the enabled diagnostics
+    pub lint_unused_macros: bool,
+    // negotiated with the client, not an option
+    #[serde(skip)]
+    pub position_encoding: PositionEncodingKind,
+}
+
+const DEFAULT_MAX_IMPORT_DEPTH: usize = 8;
+const DEFAULT_MAX_LINE_LENGTH: usize = 120;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FoldingConfig {
+    // blocks nested deeper than this are not folded
+    pub max_depth: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CompletionConfig {
+    // added to the default ones, e.g. ["["] for the square bracket syntax
+    pub trigger_characters: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct InlayHintsConfig {
+    // "(optional)" or "(required)" after the parameters of a macro definition
+    pub macro_parameters: bool,
+}
+
+// e.g. {"prefix": "classpath:", "replacement": "/project/src/main/resources/"}
+// or {"appendExtension": ".ftl"}
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PathMapping {
+    pub prefix: Option<String>,
+    pub replacement: String,
+    // appended to the paths which have no extension
+    pub append_extension: Option<String>,
+}
+
+impl PathMapping {
+    fn apply(&self, path: String) -> String {
+        let mut path = match &self.prefix {
+            Some(prefix) => match path.strip_prefix(prefix.as_str()) {
+                Some(rest) => format!("{}{}", self.replacement, rest),
+                None => return path,
+            },
+            None => path,
+        };
+        if let Some(extension) = &self.append_extension
+            && Path::new(&path).extension().is_none()
+        {
+            path.push_str(extension);
+        }
+        path
+    }
+}
+
+fn deserialize_severities<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<String, DiagnosticSeverity>, D::Error> {
+    let severities = HashMap::<String, String>::deserialize(deserializer)?;
+    Ok(severities
+        .into_iter()
+        .filter_map(|(code, severity)| {
+            let severity = match severity.as_str() {
+                "error" => DiagnosticSeverity::ERROR,
+                "warning" => DiagnosticSeverity::WARNING,
+                "information" => DiagnosticSeverity::INFORMATION,
+                "hint" => DiagnosticSeverity::HINT,
+                _ => {
+                    tracing::warn!("invalid severity of the diagnostic {}: {}", code, severity);
+                    return None;
+                }
+            };
+            Some((code, severity))
+        })
+        .collect())
+}
+
+impl Config {
+    pub fn from_initialization_options(options: Option<LSPAny>) -> Self {
+        match options {
+            Some(options) => serde_json::from_value(options).unwrap_or_else(|e| {
+                tracing::warn!("invalid initialization options: {}", e);
+                Config::default()
+            }),
+            None => Config::default(),
+        }
+    }
+
+    pub fn map_import_path(&self, path: &str) -> String {
+        self.path_mappings
+            .iter()
+            .fold(path.to_owned(), |path, mapping| mapping.apply(path))
+    }
+
+    pub fn is_diagnostic_enabled(&self, code: &str) -> bool {
+        self.enabled_diagnostics.contains(code)
+    }
+
+    // unknown codes are ignored, they match no diagnostic
+    pub fn is_diagnostic_disabled(&self, code: &str) -> bool {
+        self.disabled_diagnostics.contains(code)
+    }
+
+    pub fn diagnostic_severity(&self, code: &str) -> Option<DiagnosticSeverity> {
+        self.diagnostic_severity.get(code).copied()
+    }
+
+    pub fn max_import_depth(&self) -> usize {
+        self.max_import_depth.unwrap_or(DEFAULT_MAX_IMPORT_DEPTH)
+    }
+
+    pub fn max_line_length(&self) -> usize {
+        self.max_line_length.unwrap_or(DEFAULT_MAX_LINE_LENGTH)
+    }
+
+    // "<@spring.bind/>" is known by the namespace "spring"
+    pub fn is_known_macro(&self, name: &str) -> bool {
+        self.known_macros.contains(name)
+    }
+
+    pub fn is_data_model_root(&self, name: &str) -> bool {
+        self.data_model_roots.contains(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use serde_json::json;
+    use tower_lsp_server::ls_types::Uri;
+
+    use crate::{config::Config, reactor::Reactor, utils};
+
+    #[test]
+    fn test_classpath_prefix_mapping() {
+        let dir = utils::fixture_dir(
+            "path_mappings",
+            &[
+                ("resources/templates/lib.ftl", "<#macro bar>\n</#macro>\n"),
+                ("main.ftl", ""),
+            ],
+        );
+        let config = Config::from_initialization_options(Some(json!({
+            "pathMappings": [{
+                "prefix": "classpath:",
+                "replacement": format!("{}/resources/", dir.display()),
+            }]
+        })));
+        assert_eq!(
+            config.map_import_path("classpath:templates/lib.ftl"),
+            format!("{}/resources/templates/lib.ftl", dir.display())
+        );
+        assert_eq!(config.map_import_path("lib.ftl"), "lib.ftl");
+
+        // the mapped import is resolved without errors
+        let uri = Uri::from_file_path(dir.join("main.ftl")).unwrap();
+        let source = "<#import \"classpath:templates/lib.ftl\" as lib>\n<@lib.bar/>\n";
+        let reactor = Reactor::new(&uri, source, 0, Arc::new(config), Default::default());
+        assert!(
+            reactor
+                .get_analysis()
+                .get_analyzed_full_diagnostics()
+                .full_document_diagnostic_report
+                .items
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_append_extension_mapping() {
+        let config = Config::from_initialization_options(Some(json!({
+            "pathMappings": [{ "appendExtension": ".ftl" }]
+        })));
+        assert_eq!(config.map_import_path("lib"), "lib.ftl");
+        assert_eq!(config.map_import_path("lib/common"), "lib/common.ftl");
+        assert_eq!(config.map_import_path("lib.ftl"), "lib.ftl");
+        assert_eq!(config.map_import_path("lib.ftlh"), "lib.ftlh");
+    }
+
+    #[test]
+    fn test_known_macros() {
+        let config = Config::from_initialization_options(Some(json!({
+            "knownMacros": ["spring", "renderWidget"]
+        })));
+        let uri = Uri::from_str("file:///tmp/known_macros.ftl").unwrap();
+        let source = "<@spring.bind \"user.name\"/>\n<@renderWidget id=1/>\n<@unknown/>\n";
+        let reactor = Reactor::new(&uri, source, 0, Arc::new(config), Default::default());
+        let lines: Vec<u32> = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items
+            .iter()
+            .map(|d| d.range.start.line)
+            .collect();
+        assert_eq!(lines, vec![2]);
+    }
+}
@@ -0,0 +1,212 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::{collections::HashMap, path::PathBuf};
+
+use tower_lsp_server::{
+    jsonrpc::Result as JsonRpcResult,
+    ls_types::{
+        AnnotatedTextEdit, ChangeAnnotation, DeleteFilesParams, DocumentChanges,
+        FileOperationFilter, FileOperationPattern, FileOperationRegistrationOptions, OneOf,
+        OptionalVersionedTextDocumentIdentifier, TextDocumentEdit, TextEdit, WorkspaceEdit,
+    },
+};
+
+use crate::{
+    rename::{canonicalize, file_path},
+    server::DeleteFilesFeature,
+    utils, window_log_warn,
+    workspace::Workspace,
+};
+
+const REMOVE_IMPORT_ANNOTATION: &str = "remove-dangling-import";
+
+pub fn will_delete_capability() -> FileOperationRegistrationOptions {
+    FileOperationRegistrationOptions {
+        filters: vec![FileOperationFilter {
+            pattern: FileOperationPattern {
+                glob: "**".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }],
+    }
+}
+
+impl DeleteFilesFeature for Workspace {
+    async fn on_will_delete_files(
+        &self,
+        params: DeleteFilesParams,
+    ) -> JsonRpcResult<Option<WorkspaceEdit>> {
+        let deletions: Vec<PathBuf> = params
+            .files
+            .iter()
+            .filter_map(|deletion| file_path(&deletion.uri))
+            .map(|path| canonicalize(&path))
+            .collect();
+        let read_guard = self.reactors.read().await;
+        let mut edits = Vec::new();
+        let mut warnings = Vec::new();
+        for (uri, reactor) in read_guard.iter() {
+            // a deleted document doesn't need to be fixed
+            if uri
+                .to_file_path()
+                .is_some_and(|path| deletions.iter().any(|d| canonicalize(&path).starts_with(d)))
+            {
+                continue;
+            }
+            // a deleted directory takes everything below it
+            let dangling: Vec<_> = reactor
+                .get_analysis()
+                .get_path_references()
+                .iter()
+                .filter(|reference| deletions.iter().any(|d| reference.target.starts_with(d)))
+                .collect();
+            if dangling.is_empty() {
+                continue;
+            }
+            warnings.extend(
+                dangling
+                    .iter()
+                    .map(|reference| format!("{} refers to \"{}\"", uri.as_str(), reference.text)),
+            );
+            let doc = reactor.get_document();
+            edits.push(TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier {
+                    uri: uri.clone(),
+                    version: Some(reactor.version),
+                },
+                edits: dangling
+                    .iter()
+                    .map(|reference| {
+                        // the other statements on the line of the directive are kept
+                        let range = &reference.directive_range;
+                        OneOf::Right(AnnotatedTextEdit {
+                            text_edit: TextEdit::new(
+                                utils::removal_range(
+                                    doc,
+                                    doc.position_to_byte(&range.start)
+                                        ..doc.position_to_byte(&range.end),
+                                ),
+                                String::new(),
+                            ),
+                            annotation_id: REMOVE_IMPORT_ANNOTATION.to_owned(),
+                        })
+                    })
+                    .collect(),
+            });
+        }
+        if edits.is_empty() {
+            return Ok(None);
+        }
+        window_log_warn!(format!(
+            "deleting templates which are still referenced: {}",
+            warnings.join(", ")
+        ));
+        // the removal is only offered, the user has to confirm it
+        Ok(Some(WorkspaceEdit {
+            document_changes: Some(DocumentChanges::Edits(edits)),
+            change_annotations: Some(HashMap::from([(
+                REMOVE_IMPORT_ANNOTATION.to_owned(),
+                ChangeAnnotation {
+                    label: "Remove imports of deleted templates".to_owned(),
+                    needs_confirmation: Some(true),
+                    description: Some(warnings.join("\n")),
+                },
+            )])),
+            ..Default::default()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::{
+        DeleteFilesParams, DidOpenTextDocumentParams, DocumentChanges, FileDelete, OneOf, Position,
+        Range, TextDocumentItem, Uri,
+    };
+
+    use crate::{server::DeleteFilesFeature, utils, workspace::Workspace};
+
+    #[tokio::test]
+    async fn test_will_delete_imported_file() {
+        let source = "<#import \"lib.ftl\" as lib>\nhello<#include 'lib.ftl'>\n<#import \"other.ftl\" as other>\n";
+        let dir = utils::fixture_dir(
+            "will_delete",
+            &[
+                ("lib.ftl", "<#macro bar>\n</#macro>\n"),
+                ("other.ftl", ""),
+                ("main.ftl", source),
+            ],
+        );
+        let main_uri = Uri::from_file_path(dir.join("main.ftl")).unwrap();
+        let workspace = Workspace::new();
+        workspace
+            .on_did_open(&DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: main_uri.clone(),
+                    language_id: "ftl".to_owned(),
+                    version: 3,
+                    text: source.to_owned(),
+                },
+            })
+            .await;
+
+        let edit = workspace
+            .on_will_delete_files(DeleteFilesParams {
+                files: vec![FileDelete {
+                    uri: Uri::from_file_path(dir.join("lib.ftl"))
+                        .unwrap()
+                        .to_string(),
+                }],
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        let Some(DocumentChanges::Edits(document_edits)) = edit.document_changes else {
+            panic!("expect text document edits");
+        };
+        assert_eq!(document_edits.len(), 1);
+        assert_eq!(document_edits[0].text_document.uri, main_uri);
+        assert_eq!(document_edits[0].text_document.version, Some(3));
+        let ranges: Vec<_> = document_edits[0]
+            .edits
+            .iter()
+            .map(|edit| match edit {
+                OneOf::Right(annotated) => annotated.text_edit.range,
+                OneOf::Left(_) => panic!("expect annotated edits"),
+            })
+            .collect();
+        // both the import and the include are removed, nothing else, "hello" stays along
+        // with its line break
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(
+            ranges[0],
+            Range::new(Position::new(0, 0), Position::new(1, 0))
+        );
+        assert_eq!(
+            ranges[1],
+            Range::new(Position::new(1, 5), Position::new(1, 25))
+        );
+        let annotations = edit.change_annotations.unwrap();
+        assert!(
+            annotations
+                .values()
+                .all(|a| a.needs_confirmation == Some(true))
+        );
+
+        // an unreferenced file is deleted silently
+        let edit = workspace
+            .on_will_delete_files(DeleteFilesParams {
+                files: vec![FileDelete {
+                    uri: Uri::from_file_path(dir.join("missing.ftl"))
+                        .unwrap()
+                        .to_string(),
+                }],
+            })
+            .await
+            .unwrap();
+        assert!(edit.is_none());
+    }
+}
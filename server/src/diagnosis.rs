@@ -2,124 +2,768 @@
 // Licensed under the BSD 3-Clause License.
 // SPDX-License-Identifier: BSD-3-Clause
 
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
+use serde::{Deserialize, Serialize};
 use tower_lsp_server::{
     jsonrpc,
     ls_types::{
-        CodeDescription, Diagnostic, DiagnosticOptions, DiagnosticServerCapabilities,
-        DiagnosticSeverity, DocumentDiagnosticParams, DocumentDiagnosticReport,
-        DocumentDiagnosticReportResult, NumberOrString,
+        CodeDescription, Diagnostic, DiagnosticOptions, DiagnosticRelatedInformation,
+        DiagnosticServerCapabilities, DiagnosticSeverity, DiagnosticTag, DocumentDiagnosticParams,
+        DocumentDiagnosticReport, DocumentDiagnosticReportResult, FullDocumentDiagnosticReport,
+        LSPAny, Location, NumberOrString, Position, Range, UnchangedDocumentDiagnosticReport, Uri,
+        WorkspaceDiagnosticParams, WorkspaceDiagnosticReport, WorkspaceDiagnosticReportResult,
+        WorkspaceDocumentDiagnosticReport, WorkspaceFullDocumentDiagnosticReport,
+        WorkspaceUnchangedDocumentDiagnosticReport,
     },
 };
 use tree_sitter::Node;
 use tree_sitter_freemarker::{
-    SEMANTICS, SYNTAX,
+    SEMANTICS, STYLE, SYNTAX,
     grammar::Rule,
     href::{
-        COMPARISION_EXPRESSION, DIRECTIVE_ASSIGN, DIRECTIVE_IMPORT, DIRECTIVE_LIST_BREAK,
-        TOPLEVEL_VARIABLE,
+        COMPARISION_EXPRESSION, DIRECTIVE_ASSIGN, DIRECTIVE_FTL, DIRECTIVE_FUNCTION,
+        DIRECTIVE_GLOBAL, DIRECTIVE_IMPORT, DIRECTIVE_INCLUDE, DIRECTIVE_LIST_BREAK,
+        DIRECTIVE_LOCAL, DIRECTIVE_MACRO, DIRECTIVE_REFERENCE, DIRECTIVE_SETTING,
+        DIRECTIVE_USER_DEFINED, MISSING_DEFAULT, STRING_INTERPOLATION, TOPLEVEL_VARIABLE,
+        WHITESPACE_STRIPPING,
     },
 };
 
 use crate::{
     analysis::{Analysis, AnalysisContext, DiagnosticAnalysis, Symbol},
+    config::Config,
     doc::TextDocument,
     reactor::Reactor,
-    server::DiagnosticFeature,
+    server::{DiagnosticFeature, WorkspaceDiagnosticFeature},
+    setting::is_known_ftl_attribute,
+    suppress::{is_disabled, is_suppressed, override_severity},
     utils,
+    workspace::Workspace,
 };
 
 pub fn diagnostic_capability() -> DiagnosticServerCapabilities {
     DiagnosticServerCapabilities::Options(DiagnosticOptions {
         identifier: None,
         inter_file_dependencies: true,
-        workspace_diagnostics: false,
+        workspace_diagnostics: true,
         work_done_progress_options: Default::default(),
     })
 }
 
+// for clients to group or filter the diagnostics
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    Syntax,
+    Semantics,
+    Style,
+}
+
+// the "data" of every diagnostic
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticData {
+    pub category: Category,
+}
+
+impl Category {
+    pub fn data(self) -> Option<LSPAny> {
+        serde_json::to_value(DiagnosticData { category: self }).ok()
+    }
+}
+
 pub struct Scenario {
     severity: DiagnosticSeverity,
+    category: Category,
     code: &'static str,
     source: &'static str,
     message: &'static str,
-    href: &'static str,
+    // the page of the manual, if there is one
+    href: Option<&'static str>,
 }
 
 impl Scenario {
     pub const UNDEFINED_MACRO: Scenario = Scenario {
         severity: DiagnosticSeverity::ERROR,
+        category: Category::Semantics,
         code: "undefined_macro",
         source: SEMANTICS,
         message: "Macro definition not found.",
-        href: DIRECTIVE_IMPORT,
+        href: Some(DIRECTIVE_IMPORT),
+    };
+
+    pub const INCLUDE_USED_AS_IMPORT: Scenario = Scenario {
+        severity: DiagnosticSeverity::ERROR,
+        category: Category::Semantics,
+        code: "include_used_as_import",
+        source: SEMANTICS,
+        message: "The namespace is introduced by <#include>, which doesn't create a namespace. Use <#import> instead.",
+        href: Some(DIRECTIVE_INCLUDE),
+    };
+
+    // opt-in
+    pub const UNDEFINED_IN_DEFAULT: Scenario = Scenario {
+        severity: DiagnosticSeverity::WARNING,
+        category: Category::Semantics,
+        code: "undefined_in_default",
+        source: SEMANTICS,
+        message: "The default value of the macro parameter refers to a name which is not defined at the top level of the template.",
+        href: Some(DIRECTIVE_MACRO),
+    };
+
+    // opt-in, the variable may well be always defined
+    pub const LIST_WITHOUT_DEFAULT: Scenario = Scenario {
+        severity: DiagnosticSeverity::HINT,
+        category: Category::Semantics,
+        code: "list_without_default",
+        source: SEMANTICS,
+        message: "Listing a missing variable is an error. Consider a default value like 'items![]' if it may be missing.",
+        href: Some(MISSING_DEFAULT),
+    };
+
+    // opt-in
+    pub const FORWARD_REFERENCE: Scenario = Scenario {
+        severity: DiagnosticSeverity::HINT,
+        category: Category::Semantics,
+        code: "forward_reference",
+        source: SEMANTICS,
+        message: "The function is called before its definition, which fails if the call is evaluated first.",
+        href: Some(DIRECTIVE_FUNCTION),
+    };
+
+    // opt-in, the data-model roots are configured
+    pub const GLOBAL_SHADOWS_DATAMODEL: Scenario = Scenario {
+        severity: DiagnosticSeverity::HINT,
+        category: Category::Semantics,
+        code: "global_shadows_datamodel",
+        source: SEMANTICS,
+        message: "The global variable hides the variable of the data-model with the same name.",
+        href: Some(DIRECTIVE_GLOBAL),
+    };
+
+    pub const UNUSED_IMPORT: Scenario = Scenario {
+        severity: DiagnosticSeverity::HINT,
+        category: Category::Semantics,
+        code: "unused_import",
+        source: SEMANTICS,
+        message: "The namespace of the import is never used.",
+        href: Some(DIRECTIVE_IMPORT),
+    };
+
+    // opt-in, the macros of a library are called by other templates
+    pub const UNUSED_MACRO: Scenario = Scenario {
+        severity: DiagnosticSeverity::HINT,
+        category: Category::Semantics,
+        code: "unused_macro",
+        source: SEMANTICS,
+        message: "The macro is never called in this template.",
+        href: Some(DIRECTIVE_MACRO),
+    };
+
+    pub const FUNCTION_WITHOUT_RETURN: Scenario = Scenario {
+        severity: DiagnosticSeverity::WARNING,
+        category: Category::Semantics,
+        code: "function_without_return",
+        source: SEMANTICS,
+        message: "The function has no <#return>, a call to it evaluates to a missing value.",
+        href: Some(DIRECTIVE_FUNCTION),
+    };
+
+    // opt-in, the branches may well cover every case
+    pub const CONDITIONAL_RETURN_ONLY: Scenario = Scenario {
+        severity: DiagnosticSeverity::WARNING,
+        category: Category::Semantics,
+        code: "conditional_return_only",
+        source: SEMANTICS,
+        message: "The function only returns inside a nested directive, a call to it may evaluate to a missing value. Consider a <#return> at the end of the function.",
+        href: Some(DIRECTIVE_FUNCTION),
+    };
+
+    pub const UNKNOWN_SETTING: Scenario = Scenario {
+        severity: DiagnosticSeverity::WARNING,
+        category: Category::Semantics,
+        code: "unknown_setting",
+        source: SEMANTICS,
+        message: "Unknown setting name.",
+        href: Some(DIRECTIVE_SETTING),
+    };
+
+    // FreeMarker refuses to parse the template
+    pub const UNKNOWN_FTL_ATTRIBUTE: Scenario = Scenario {
+        severity: DiagnosticSeverity::ERROR,
+        category: Category::Semantics,
+        code: "unknown_ftl_attribute",
+        source: SEMANTICS,
+        message: "Unknown attribute of the <#ftl> header.",
+        href: Some(DIRECTIVE_FTL),
     };
 
     const BACKSLASHED_IDENTIFIER: Scenario = Scenario {
         severity: DiagnosticSeverity::INFORMATION,
+        category: Category::Style,
         code: "identifier_has_backslash",
         source: SYNTAX,
         message: "Identifiers containing reserved characters require escaping with a backslash (\\), which can significantly reduce readability. Consider refactoring to avoid such identifiers.",
-        href: TOPLEVEL_VARIABLE,
+        href: Some(TOPLEVEL_VARIABLE),
     };
 
     const AMBIGUOUS_STRING_LITERAL: Scenario = Scenario {
         severity: DiagnosticSeverity::WARNING,
+        category: Category::Style,
         code: "ambiguous_string_literal",
         source: SYNTAX,
         message: "While using a string literal as an L-value is syntactically valid for <#assign> and <#local>, this practice is generally discouraged due to potential ambiguity and reduced maintainability.",
-        href: DIRECTIVE_ASSIGN,
+        href: Some(DIRECTIVE_ASSIGN),
     };
 
     const DEPRECATED_EQUAL_OPERATOR: Scenario = Scenario {
         severity: DiagnosticSeverity::WARNING,
+        category: Category::Style,
         code: "deprecated_equal_operator",
         source: SYNTAX,
         message: "For equality checks in comparisons, use '=='. The single '=' operator is deprecated for this purpose.",
-        href: COMPARISION_EXPRESSION,
+        href: Some(COMPARISION_EXPRESSION),
     };
 
     const UNDOCUMENTED_CLOSE_TAG: Scenario = Scenario {
         severity: DiagnosticSeverity::WARNING,
+        category: Category::Style,
         code: "undocumented_close_tag",
         source: SYNTAX,
         message: "For non-capture <#assign> directives, it is recommended to use '>' as the close tag. Using '/>' is undocumented and adds unnecessary characters.",
-        href: DIRECTIVE_ASSIGN,
+        href: Some(DIRECTIVE_ASSIGN),
     };
 
-    const DEPRECATED_LIST_BREAK: Scenario = Scenario {
+    pub const DEPRECATED_LIST_BREAK: Scenario = Scenario {
         severity: DiagnosticSeverity::WARNING,
+        category: Category::Style,
         code: "deprecated_list_break",
         source: SYNTAX,
         message: "<#break> is deprecated for most list-related use cases, as it can interfere with <#sep> and item?has_next. Instead, consider using sequence?take_while(predicate) to filter the sequence before iteration.",
-        href: DIRECTIVE_LIST_BREAK,
+        href: Some(DIRECTIVE_LIST_BREAK),
+    };
+
+    pub const LOCAL_OUTSIDE_MACRO: Scenario = Scenario {
+        severity: DiagnosticSeverity::ERROR,
+        category: Category::Semantics,
+        code: "local_outside_macro",
+        source: SEMANTICS,
+        message: "The <#local> directive can only be used within <#macro> or <#function> blocks.",
+        href: Some(DIRECTIVE_LOCAL),
     };
 
     const UNEXPECTED_BREAK_STMT: Scenario = Scenario {
         severity: DiagnosticSeverity::ERROR,
+        category: Category::Syntax,
         code: "unexpected_break_stmt",
         source: SYNTAX,
         message: "The <#break> directive can only be used within <#list> or <#switch> blocks.",
-        href: DIRECTIVE_LIST_BREAK,
+        href: Some(DIRECTIVE_LIST_BREAK),
+    };
+
+    // the message names the expression
+    const INTERPOLATION_IN_EXPRESSION: Scenario = Scenario {
+        severity: DiagnosticSeverity::ERROR,
+        category: Category::Syntax,
+        code: "interpolation_in_expression",
+        source: SYNTAX,
+        message: "Interpolation is not allowed in an expression.",
+        href: Some(STRING_INTERPOLATION),
+    };
+
+    // e.g. "b" of `<@m a x=1 b/>`
+    const POSITIONAL_AFTER_NAMED: Scenario = Scenario {
+        severity: DiagnosticSeverity::ERROR,
+        category: Category::Syntax,
+        code: "positional_after_named",
+        source: SYNTAX,
+        message: "A positional argument cannot follow a named argument.",
+        href: Some(DIRECTIVE_USER_DEFINED),
+    };
+
+    // opt-in
+    pub const TRAILING_WHITESPACE: Scenario = Scenario {
+        severity: DiagnosticSeverity::HINT,
+        category: Category::Style,
+        code: "trailing_whitespace",
+        source: SYNTAX,
+        message: "Trailing whitespace after the directive.",
+        href: Some(WHITESPACE_STRIPPING),
+    };
+
+    // opt-in, the message names the length and the limit
+    pub const MAX_LINE_LENGTH: Scenario = Scenario {
+        severity: DiagnosticSeverity::INFORMATION,
+        category: Category::Style,
+        code: "max_line_length",
+        source: STYLE,
+        message: "The line is too long.",
+        href: None,
+    };
+
+    // the message names the directive
+    const UNCLOSED_DIRECTIVE: Scenario = Scenario {
+        severity: DiagnosticSeverity::ERROR,
+        category: Category::Syntax,
+        code: "unclosed_directive",
+        source: SYNTAX,
+        message: "The directive is not closed.",
+        href: Some(DIRECTIVE_REFERENCE),
+    };
+
+    // the message names both directives
+    pub const MISMATCHED_CLOSE_TAG: Scenario = Scenario {
+        severity: DiagnosticSeverity::ERROR,
+        category: Category::Syntax,
+        code: "mismatched_close_tag",
+        source: SYNTAX,
+        message: "The close tag doesn't match the open directive.",
+        href: Some(DIRECTIVE_REFERENCE),
     };
 }
 
+impl Scenario {
+    pub const fn code(&self) -> &'static str {
+        self.code
+    }
+}
+
 impl From<Scenario> for Diagnostic {
     fn from(s: Scenario) -> Self {
         Diagnostic {
             severity: Some(s.severity),
             code: Some(NumberOrString::String(s.code.to_owned())),
-            code_description: Some(CodeDescription {
-                href: s.href.parse().unwrap(),
+            code_description: s.href.map(|href| CodeDescription {
+                href: href.parse().unwrap(),
             }),
             source: Some(s.source.to_owned()),
             message: s.message.to_owned(),
+            data: s.category.data(),
             ..Default::default()
         }
     }
 }
 
+// a missing "</#if>" is reported at its "<#if", which is far more helpful than the end of file
+fn unclosed_directive(missing: &Node, doc: &TextDocument) -> Option<Diagnostic> {
+    let name = missing.kind().strip_suffix("_close")?;
+    let opener = missing.parent()?.child(0)?;
+    if opener.kind().strip_suffix("_begin") != Some(name) {
+        return None;
+    }
+    Some(Diagnostic {
+        range: utils::parser_node_to_document_range(doc, &opener),
+        message: format!("Unclosed <#{name}>, expected </#{name}>."),
+        ..Scenario::UNCLOSED_DIRECTIVE.into()
+    })
+}
+
+// the directives which must be closed, besides the capture form of the assignments
+const BLOCK_DIRECTIVES: [&str; 13] = [
+    "attempt",
+    "autoesc",
+    "compress",
+    "escape",
+    "function",
+    "if",
+    "items",
+    "list",
+    "macro",
+    "noautoesc",
+    "noescape",
+    "outputformat",
+    "switch",
+];
+
+const CAPTURE_DIRECTIVES: [&str; 3] = ["assign", "global", "local"];
+
+struct DirectiveTag<'a> {
+    closing: bool,
+    name: &'a str,
+    // of the name, e.g. "if" of "<#if x>"
+    name_bytes: std::ops::Range<usize>,
+    // up to the end of the tag, e.g. " x" of "<#if x>"
+    arguments: &'a str,
+}
+
+fn is_variable_name(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_alphabetic() || c == '_' || c == '$')
+        && word
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '$' | '@'))
+}
+
+impl DirectiveTag<'_> {
+    // "<#assign x>" and "<#assign x in ns>" capture their body, "<#assign x = 1>" or
+    // "<#assign i++>" do not
+    fn is_block(&self) -> bool {
+        BLOCK_DIRECTIVES.contains(&self.name)
+            || (CAPTURE_DIRECTIVES.contains(&self.name)
+                && (self.closing
+                    || match self.arguments.split_whitespace().collect::<Vec<_>>()[..] {
+                        [name] => is_variable_name(name),
+                        [name, "in", namespace] => {
+                            is_variable_name(name) && is_variable_name(namespace)
+                        }
+                        _ => false,
+                    }))
+    }
+}
+
+// the offset of the character which closes an expression, the brackets nested in it and
+// the string literals, e.g. "${\"}\"}" or "<#if (a > b)>", are skipped
+fn expression_end(text: &str, from: usize, close: char) -> usize {
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+    for (offset, c) in text[from..].char_indices() {
+        if let Some(opening) = quote {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                _ if c == opening => quote = None,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            _ if c == close && depth == 0 => return from + offset,
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+// the tags of both syntaxes in order, the comments, the interpolations and the body of
+// <#noparse> are skipped
+fn directive_tags(text: &str) -> Vec<DirectiveTag<'_>> {
+    let mut tags = vec![];
+    let mut cursor = 0;
+    while let Some(offset) = text[cursor..].find(['<', '[', '$', '#']) {
+        let start = cursor + offset;
+        let interpolation_end = match &text[start..] {
+            rest if rest.starts_with("${") || rest.starts_with("#{") => {
+                Some(expression_end(text, start + 2, '}'))
+            }
+            rest if rest.starts_with("[=") => Some(expression_end(text, start + 2, ']')),
+            _ => None,
+        };
+        if let Some(end) = interpolation_end {
+            cursor = (end + 1).min(text.len());
+            continue;
+        }
+        if matches!(&text[start..start + 1], "$" | "#") {
+            cursor = start + 1;
+            continue;
+        }
+        let (open, close) = match &text[start..start + 1] {
+            "<" => ('<', '>'),
+            _ => ('[', ']'),
+        };
+        let rest = &text[start + 1..];
+        if rest.starts_with("#--") {
+            let comment_end = format!("--{}", close);
+            cursor = text[start..]
+                .find(&comment_end)
+                .map_or(text.len(), |end| start + end + comment_end.len());
+            continue;
+        }
+        let (closing, name_start) = match rest {
+            _ if rest.starts_with('#') => (false, start + 2),
+            _ if rest.starts_with("/#") => (true, start + 3),
+            _ => {
+                cursor = start + 1;
+                continue;
+            }
+        };
+        let name_end = text[name_start..]
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .map_or(text.len(), |end| name_start + end);
+        let tag_end = expression_end(text, name_end, close);
+        let name = &text[name_start..name_end];
+        tags.push(DirectiveTag {
+            closing,
+            name,
+            name_bytes: name_start..name_end,
+            arguments: &text[name_end..tag_end],
+        });
+        // a tag which is still being typed leaves the rest of the text to the scan
+        cursor = match tag_end < text.len() {
+            true => tag_end + 1,
+            false => name_end,
+        };
+        if !closing && name == "noparse" {
+            let noparse_end = format!("{}/#noparse", open);
+            cursor = text[name_end..]
+                .find(&noparse_end)
+                .map_or(text.len(), |end| name_end + end);
+        }
+    }
+    tags
+}
+
+// e.g. "</#list>" of "<#if x>...</#list>", which tree-sitter doesn't even parse as a close tag
+fn mismatched_close_tags(doc: &TextDocument) -> Vec<Diagnostic> {
+    let text = doc.to_string();
+    let mut diagnostics = vec![];
+    let mut opened: Vec<DirectiveTag> = vec![];
+    for tag in directive_tags(&text) {
+        if !tag.is_block() || (!tag.closing && tag.arguments.ends_with('/')) {
+            continue;
+        }
+        if !tag.closing {
+            opened.push(tag);
+            continue;
+        }
+        let Some(innermost) = opened.last() else {
+            continue;
+        };
+        if innermost.name == tag.name {
+            opened.pop();
+            continue;
+        }
+        let range = |bytes: &std::ops::Range<usize>| {
+            Range::new(
+                doc.byte_to_position(bytes.start),
+                doc.byte_to_position(bytes.end),
+            )
+        };
+        diagnostics.push(Diagnostic {
+            range: range(&tag.name_bytes),
+            message: format!(
+                "Mismatched </#{}>, expected </#{}>.",
+                tag.name, innermost.name
+            ),
+            related_information: Some(vec![DiagnosticRelatedInformation {
+                location: Location::new(doc.uri(), range(&innermost.name_bytes)),
+                message: format!("<#{}> is opened here", innermost.name),
+            }]),
+            ..Scenario::MISMATCHED_CLOSE_TAG.into()
+        });
+        // the close tag of an outer block closes the inner ones, otherwise it is misspelt
+        match opened.iter().rposition(|open| open.name == tag.name) {
+            Some(index) => opened.truncate(index),
+            None => {
+                opened.pop();
+            }
+        }
+    }
+    diagnostics
+}
+
+// the directives whose arguments are expressions, e.g. `<#list ${xs} as x>` should be `<#list xs as x>`
+const EXPRESSION_DIRECTIVES: [Rule; 9] = [
+    Rule::AssignBegin,
+    Rule::CaseBegin,
+    Rule::ElseifBegin,
+    Rule::IfBegin,
+    Rule::ListBegin,
+    Rule::LocalBegin,
+    Rule::MacroCallBegin,
+    Rule::ReturnBegin,
+    Rule::SwitchBegin,
+];
+
+// "$" is left as an ERROR, followed by the braces
+fn is_interpolation_prepend(node: &Node, doc: &TextDocument) -> bool {
+    node.is_error()
+        && doc.get_ranged_text(node.start_byte()..node.end_byte()) == "$"
+        && node.next_sibling().is_some_and(|next| {
+            doc.get_ranged_text(next.start_byte()..next.end_byte())
+                .starts_with('{')
+        })
+}
+
+fn in_expression_directive(node: &Node) -> bool {
+    let mut node_cursor = node.parent();
+    while let Some(ancestor) = node_cursor {
+        if let Some(begin) = ancestor.child(0)
+            && begin.kind().ends_with("_begin")
+        {
+            return Rule::from_str(begin.kind())
+                .is_ok_and(|rule| EXPRESSION_DIRECTIVES.contains(&rule));
+        }
+        node_cursor = ancestor.parent();
+    }
+    false
+}
+
+fn interpolation_in_expression(node: &Node, doc: &TextDocument) -> Option<Diagnostic> {
+    if !is_interpolation_prepend(node, doc) || !in_expression_directive(node) {
+        return None;
+    }
+    // the braces may be the first of the clause, e.g. `<#if ${x}>`
+    let mut braces = node.next_sibling()?;
+    while !matches!(braces.kind(), "object" | "{") {
+        braces = braces.child(0)?;
+    }
+    let mut end = braces.end_position();
+    let mut expression = doc.get_ranged_text(braces.start_byte()..braces.end_byte());
+    // "${xs}" of <#list> isn't even grouped
+    if expression == "{" {
+        let mut sibling_cursor = braces.next_sibling();
+        while let Some(sibling) = sibling_cursor {
+            expression.push_str(&doc.get_ranged_text(sibling.start_byte()..sibling.end_byte()));
+            end = sibling.end_position();
+            if sibling.kind() == "}" {
+                break;
+            }
+            sibling_cursor = sibling.next_sibling();
+        }
+    }
+    let expression = expression
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .trim();
+    let mut range = utils::parser_node_to_document_range(doc, node);
+    range.end = doc.point_to_position(end);
+    Some(Diagnostic {
+        range,
+        message: format!(
+            "Interpolation is not allowed in an expression, write '{expression}' instead of '${{{expression}}}'."
+        ),
+        ..Scenario::INTERPOLATION_IN_EXPRESSION.into()
+    })
+}
+
+// the ERROR inside of "${...}" is reported with the interpolation
+fn in_misplaced_interpolation(node: &Node, doc: &TextDocument) -> bool {
+    node.parent().is_some_and(|parent| {
+        parent.kind() == "object"
+            && parent.start_byte() > 0
+            && doc.get_ranged_text(parent.start_byte() - 1..parent.start_byte()) == "$"
+    })
+}
+
+fn contains_return(node: &Node) -> bool {
+    if let Ok(Rule::ReturnBegin) = Rule::from_str(node.kind()) {
+        return true;
+    }
+    let mut cursor = node.walk();
+    node.children(&mut cursor)
+        .any(|child| contains_return(&child))
+}
+
+// a <#return> directly in the body is always reached, so is an <#if> with an <#else> whose
+// branches all return, one in any other nested directive may be not
+fn has_unconditional_return(function_clause: &Node) -> bool {
+    let mut cursor = function_clause.walk();
+    function_clause
+        .children_by_field_name("body", &mut cursor)
+        .any(|stmt| always_returns(&stmt))
+}
+
+fn always_returns(stmt: &Node) -> bool {
+    let Some(directive) = stmt.named_child(0) else {
+        return false;
+    };
+    match Rule::from_str(directive.kind()) {
+        Ok(Rule::ReturnStmt) => true,
+        Ok(Rule::IfStmt) => {
+            let mut cursor = directive.walk();
+            let branches: Vec<Node> = directive
+                .named_children(&mut cursor)
+                .filter(|child| {
+                    matches!(
+                        Rule::from_str(child.kind()),
+                        Ok(Rule::IfClause | Rule::ElseifClause | Rule::ElseClause)
+                    )
+                })
+                .collect();
+            branches
+                .iter()
+                .any(|branch| Rule::from_str(branch.kind()) == Ok(Rule::ElseClause))
+                && branches.iter().all(|branch| {
+                    let mut cursor = branch.walk();
+                    branch
+                        .named_children(&mut cursor)
+                        .any(|child| always_returns(&child))
+                })
+        }
+        _ => false,
+    }
+}
+
+// the line ends with a tag like `<#if x>`, `</#list>`, `<@m/>` or their square bracket forms
+fn ends_with_directive(line: &str) -> bool {
+    let (open, close) = match line.chars().last() {
+        Some('>') => ('<', '>'),
+        Some(']') => ('[', ']'),
+        _ => return false,
+    };
+    line.rfind(open).is_some_and(|start| {
+        let tag = line[start + 1..].trim_end_matches(close);
+        ["#", "/#", "@", "/@"]
+            .iter()
+            .any(|prefix| tag.starts_with(prefix))
+    })
+}
+
+fn trailing_whitespace(doc: &TextDocument) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    for (index, line) in doc.rope.lines().enumerate() {
+        let line = line.to_string();
+        let content = line.trim_end_matches(['\n', '\r']);
+        let trimmed = content.trim_end_matches([' ', '\t']);
+        if trimmed.len() < content.len() && ends_with_directive(trimmed) {
+            diagnostics.push(Diagnostic {
+                range: Range::new(
+                    Position::new(
+                        index as u32,
+                        doc.byte_column_to_character(index, trimmed.len()),
+                    ),
+                    Position::new(
+                        index as u32,
+                        doc.byte_column_to_character(index, content.len()),
+                    ),
+                ),
+                tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                ..Scenario::TRAILING_WHITESPACE.into()
+            });
+        }
+    }
+    diagnostics
+}
+
+// long lines of text are left alone, only the lines with a tag or an interpolation count
+fn has_template_syntax(line: &str) -> bool {
+    [
+        "<#", "</#", "<@", "</@", "[#", "[/#", "[@", "[/@", "${", "#{", "[=",
+    ]
+    .iter()
+    .any(|marker| line.contains(marker))
+}
+
+fn long_lines(doc: &TextDocument, config: &Config) -> Vec<Diagnostic> {
+    let limit = config.max_line_length();
+    let mut diagnostics = vec![];
+    for (index, line) in doc.rope.lines().enumerate() {
+        let length = doc.line_len_in(index, config.position_encoding);
+        let line = line.to_string();
+        if length > limit && has_template_syntax(&line) {
+            let content = line.trim_end_matches(['\n', '\r']);
+            diagnostics.push(Diagnostic {
+                range: Range::new(
+                    Position::new(index as u32, doc.byte_column_to_character(index, 0)),
+                    Position::new(
+                        index as u32,
+                        doc.byte_column_to_character(index, content.len()),
+                    ),
+                ),
+                message: format!(
+                    "The line is {} characters long, longer than {}.",
+                    length, limit
+                ),
+                ..Scenario::MAX_LINE_LENGTH.into()
+            });
+        }
+    }
+    diagnostics
+}
+
 impl DiagnosticAnalysis for Analysis {
     fn analyze_diagnostic_report(
         &mut self,
@@ -128,32 +772,63 @@ impl DiagnosticAnalysis for Analysis {
         ctx: &mut AnalysisContext,
     ) {
         let node_kind = node.kind();
-        let range = utils::parser_node_to_document_range(node);
+        let range = utils::parser_node_to_document_range(doc, node);
         // TODO: maybe use tree-sitter query in the future
         if node.is_missing() {
-            // TODO : maybe use query in the future
-            self.add_diagnostic(Diagnostic {
-                range,
-                severity: Some(DiagnosticSeverity::ERROR),
-                source: Some(SYNTAX.to_owned()),
-                message: format!("Missing {} here", node_kind),
-                ..Default::default()
-            });
+            if let Some(diagnostic) = unclosed_directive(node, doc) {
+                self.add_diagnostic(diagnostic);
+            } else {
+                // TODO : maybe use query in the future
+                self.add_diagnostic(Diagnostic {
+                    range,
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    source: Some(SYNTAX.to_owned()),
+                    message: format!("Missing {} here", node_kind),
+                    data: Category::Syntax.data(),
+                    ..Default::default()
+                });
+            }
         }
 
         if node.is_error() {
-            let node_text = doc.get_ranged_text(node.start_byte()..node.end_byte());
-            self.add_diagnostic(Diagnostic {
-                range,
-                severity: Some(DiagnosticSeverity::ERROR),
-                source: Some(SYNTAX.to_owned()),
-                message: format!("ERROR: Unexpected '{}'.\n", node_text),
-                ..Default::default()
-            });
+            if let Some(diagnostic) = interpolation_in_expression(node, doc) {
+                self.add_diagnostic(diagnostic);
+            } else if !in_misplaced_interpolation(node, doc) {
+                let node_text = doc.get_ranged_text(node.start_byte()..node.end_byte());
+                self.add_diagnostic(Diagnostic {
+                    range,
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    source: Some(SYNTAX.to_owned()),
+                    message: format!("ERROR: Unexpected '{}'.\n", node_text),
+                    data: Category::Syntax.data(),
+                    ..Default::default()
+                });
+            }
         }
 
         if let Ok(rule) = Rule::from_str(node_kind) {
             match rule {
+                Rule::SourceFile => {
+                    for diagnostic in mismatched_close_tags(doc) {
+                        self.add_diagnostic(diagnostic);
+                    }
+                    if ctx
+                        .config
+                        .is_diagnostic_enabled(Scenario::TRAILING_WHITESPACE.code())
+                    {
+                        for diagnostic in trailing_whitespace(doc) {
+                            self.add_diagnostic(diagnostic);
+                        }
+                    }
+                    if ctx
+                        .config
+                        .is_diagnostic_enabled(Scenario::MAX_LINE_LENGTH.code())
+                    {
+                        for diagnostic in long_lines(doc, &ctx.config) {
+                            self.add_diagnostic(diagnostic);
+                        }
+                    }
+                }
                 Rule::Identifier => {
                     let node_text = doc.get_ranged_text(node.start_byte()..node.end_byte());
                     if node_text.contains("\\") {
@@ -163,6 +838,18 @@ impl DiagnosticAnalysis for Analysis {
                         });
                     }
                 }
+                Rule::FtlParameter => {
+                    if let Some(name) = node.child_by_field_name("name") {
+                        let name_text = doc.get_ranged_text(name.byte_range());
+                        if !is_known_ftl_attribute(&name_text) {
+                            self.add_diagnostic(Diagnostic {
+                                range: utils::parser_node_to_document_range(doc, &name),
+                                message: format!("Unknown <#ftl> attribute '{}'.", name_text),
+                                ..Scenario::UNKNOWN_FTL_ATTRIBUTE.into()
+                            });
+                        }
+                    }
+                }
                 Rule::AmbiguousStringLiteral => {
                     self.add_diagnostic(Diagnostic {
                         range,
@@ -181,26 +868,81 @@ impl DiagnosticAnalysis for Analysis {
                         ..Scenario::UNDOCUMENTED_CLOSE_TAG.into()
                     });
                 }
-                Rule::ListBegin | Rule::SwitchBegin => {
+                Rule::ListBegin | Rule::SwitchBegin | Rule::MacroBegin | Rule::FunctionBegin => {
                     ctx.scope.push(rule);
                 }
-                Rule::ListClose | Rule::SwitchClose => {
+                Rule::ListClose | Rule::SwitchClose | Rule::MacroClose | Rule::FunctionClose => {
                     ctx.scope.pop();
                 }
-                Rule::BreakStmt => match ctx.scope.last() {
-                    Some(scope_rule) => {
-                        if *scope_rule == Rule::ListBegin {
-                            self.add_diagnostic(Diagnostic {
-                                range,
-                                ..Scenario::DEPRECATED_LIST_BREAK.into()
-                            })
-                        }
+                Rule::LocalBegin
+                    if !ctx.scope.iter().any(|scope_rule| {
+                        matches!(scope_rule, Rule::MacroBegin | Rule::FunctionBegin)
+                    }) =>
+                {
+                    self.add_diagnostic(Diagnostic {
+                        range,
+                        ..Scenario::LOCAL_OUTSIDE_MACRO.into()
+                    });
+                }
+                Rule::ListClause => {
+                    if let Some(collection) = node.child_by_field_name("collection")
+                        && let Ok(Rule::Variable) = Rule::from_str(collection.kind())
+                        && ctx
+                            .config
+                            .is_diagnostic_enabled(Scenario::LIST_WITHOUT_DEFAULT.code())
+                    {
+                        self.add_diagnostic(Diagnostic {
+                            range: utils::parser_node_to_document_range(doc, &collection),
+                            ..Scenario::LIST_WITHOUT_DEFAULT.into()
+                        });
+                    }
+                }
+                Rule::FunctionClause => {
+                    let name_range = node.child_by_field_name("name").map_or(range, |name| {
+                        utils::parser_node_to_document_range(doc, &name)
+                    });
+                    if !contains_return(node) {
+                        self.add_diagnostic(Diagnostic {
+                            range: name_range,
+                            ..Scenario::FUNCTION_WITHOUT_RETURN.into()
+                        });
+                    } else if !has_unconditional_return(node)
+                        && ctx
+                            .config
+                            .is_diagnostic_enabled(Scenario::CONDITIONAL_RETURN_ONLY.code())
+                    {
+                        self.add_diagnostic(Diagnostic {
+                            range: name_range,
+                            ..Scenario::CONDITIONAL_RETURN_ONLY.into()
+                        });
                     }
-                    None => self.add_diagnostic(Diagnostic {
+                }
+                // the list or switch outside of a macro doesn't count
+                Rule::BreakStmt => match ctx.scope.last() {
+                    Some(Rule::ListBegin) => self.add_diagnostic(Diagnostic {
+                        range,
+                        ..Scenario::DEPRECATED_LIST_BREAK.into()
+                    }),
+                    Some(Rule::SwitchBegin) => {}
+                    _ => self.add_diagnostic(Diagnostic {
                         range,
                         ..Scenario::UNEXPECTED_BREAK_STMT.into()
                     }),
                 },
+                Rule::MacroCall => {
+                    let mut cursor = node.walk();
+                    let mut named = false;
+                    for argument in node.children_by_field_name("parameter", &mut cursor) {
+                        if argument.kind() == Rule::AssignExpression.to_string() {
+                            named = true;
+                        } else if named {
+                            self.add_diagnostic(Diagnostic {
+                                range: utils::parser_node_to_document_range(doc, &argument),
+                                ..Scenario::POSITIONAL_AFTER_NAMED.into()
+                            });
+                        }
+                    }
+                }
                 Rule::MacroNamespace => {
                     let node_text = doc.get_ranged_text(node.start_byte()..node.end_byte());
                     let macro_call = Symbol {
@@ -231,3 +973,658 @@ impl DiagnosticFeature for Reactor {
         ))
     }
 }
+
+// By position, then the most severe first: the order of the analysis would change with
+// the traversal, the clients and the tests expect a stable one
+pub fn sort_diagnostics(diagnostics: &mut [Diagnostic]) {
+    fn code(diagnostic: &Diagnostic) -> Option<String> {
+        diagnostic.code.as_ref().map(|code| match code {
+            NumberOrString::String(code) => code.clone(),
+            NumberOrString::Number(code) => code.to_string(),
+        })
+    }
+    diagnostics.sort_by(|a, b| {
+        (a.range.start, a.severity)
+            .cmp(&(b.range.start, b.severity))
+            .then_with(|| code(a).cmp(&code(b)))
+    });
+}
+
+impl Workspace {
+    // "<@ns.foo/>" is checked against the imported template, which only the workspace knows
+    pub(crate) async fn diagnose_imported_macros(
+        &self,
+        reactors: &HashMap<Uri, Reactor>,
+        analysis: &Analysis,
+    ) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        for call in analysis.get_macro_calls() {
+            let Some(member) = &call.member else {
+                continue;
+            };
+            let Some(import_uri) = analysis.get_import_alias(&call.namespace) else {
+                continue;
+            };
+            // an unreadable template is reported by the import already
+            let defined = match reactors.get(import_uri) {
+                Some(imported) => imported
+                    .get_analysis()
+                    .find_macro_definition(member)
+                    .is_some(),
+                None => match self.imports.get(import_uri).await {
+                    Some(imported) => imported.find_macro_definition(member).is_some(),
+                    None => continue,
+                },
+            };
+            if !defined {
+                diagnostics.push(Diagnostic {
+                    range: call.range,
+                    message: format!(
+                        "Macro definition '{}' not found in the namespace '{}'.",
+                        member, call.namespace
+                    ),
+                    ..Scenario::UNDEFINED_MACRO.into()
+                });
+            }
+        }
+        let config = self.get_config().await;
+        diagnostics.retain(|diagnostic| {
+            !is_suppressed(analysis.get_suppressions(), diagnostic)
+                && !is_disabled(&config, diagnostic)
+        });
+        for diagnostic in &mut diagnostics {
+            override_severity(&config, diagnostic);
+        }
+        diagnostics
+    }
+}
+
+impl WorkspaceDiagnosticFeature for Workspace {
+    async fn on_workspace_diagnostic(
+        &self,
+        params: WorkspaceDiagnosticParams,
+    ) -> jsonrpc::Result<WorkspaceDiagnosticReportResult> {
+        let read_guard = self.reactors.read().await;
+        let mut uris: Vec<&Uri> = read_guard.keys().collect();
+        uris.sort_by_key(|uri| uri.as_str());
+        // the diagnostics of the imported macros change with the imported templates
+        let generation = self.imports.validity().generation();
+        let mut items = vec![];
+        for uri in uris {
+            let reactor = &read_guard[uri];
+            // the version only advances when the document is edited
+            let result_id = format!("{}.{}", reactor.version, generation);
+            let version = Some(reactor.version as i64);
+            let unchanged = params
+                .previous_result_ids
+                .iter()
+                .any(|previous| &previous.uri == uri && previous.value == result_id);
+            if unchanged {
+                items.push(WorkspaceDocumentDiagnosticReport::Unchanged(
+                    WorkspaceUnchangedDocumentDiagnosticReport {
+                        uri: uri.clone(),
+                        version,
+                        unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+                            result_id,
+                        },
+                    },
+                ));
+                continue;
+            }
+            let mut diagnostics = reactor
+                .get_analysis()
+                .get_analyzed_full_diagnostics()
+                .full_document_diagnostic_report
+                .items;
+            diagnostics.extend(
+                self.diagnose_imported_macros(&read_guard, reactor.get_analysis())
+                    .await,
+            );
+            sort_diagnostics(&mut diagnostics);
+            items.push(WorkspaceDocumentDiagnosticReport::Full(
+                WorkspaceFullDocumentDiagnosticReport {
+                    uri: uri.clone(),
+                    version,
+                    full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                        result_id: Some(result_id),
+                        items: diagnostics,
+                    },
+                },
+            ));
+        }
+        Ok(WorkspaceDiagnosticReportResult::Report(
+            WorkspaceDiagnosticReport { items },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tower_lsp_server::ls_types::{
+        DiagnosticSeverity, DidOpenTextDocumentParams, DocumentDiagnosticParams,
+        DocumentDiagnosticReport, DocumentDiagnosticReportResult, NumberOrString, Position,
+        PreviousResultId, Range, TextDocumentIdentifier, TextDocumentItem, Uri,
+        WorkspaceDiagnosticParams, WorkspaceDiagnosticReportResult,
+        WorkspaceDocumentDiagnosticReport,
+    };
+
+    use crate::{
+        config::Config,
+        diagnosis::{Category, DiagnosticData},
+        doc::PositionEncodingKind,
+        reactor::Reactor,
+        server::WorkspaceDiagnosticFeature,
+        utils,
+        workspace::Workspace,
+    };
+
+    fn diagnostics_of(source: &str, config: Config) -> Vec<(u32, String)> {
+        let uri = Uri::from_str("file:///tmp/diagnosis.ftl").unwrap();
+        let reactor = Reactor::new(&uri, source, 0, config.into(), Default::default());
+        reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items
+            .into_iter()
+            .filter_map(|d| match d.code {
+                Some(NumberOrString::String(code)) => Some((d.range.start.line, code)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diagnostics_order() {
+        // reported by the traversal, the symbol analysis and the import checks
+        let source = "<#function f>\n</#function>\n<@undefined/>\n<#import \"missing.ftl\" as m>\n<#function g>\n</#function>\n";
+        let uri = Uri::from_str("file:///tmp/diagnosis.ftl").unwrap();
+        let reactor = Reactor::new(
+            &uri,
+            source,
+            0,
+            Config::default().into(),
+            Default::default(),
+        );
+        let items: Vec<(u32, u32, String)> = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items
+            .into_iter()
+            .filter_map(|d| match d.code {
+                Some(NumberOrString::String(code)) => {
+                    Some((d.range.start.line, d.range.start.character, code))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            items,
+            vec![
+                (0, 11, "function_without_return".to_owned()),
+                (2, 2, "undefined_macro".to_owned()),
+                (3, 9, "path_uncanonical".to_owned()),
+                (3, 26, "unused_import".to_owned()),
+                (4, 11, "function_without_return".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_local_outside_macro() {
+        let source = "<#macro m>\n<#local x = 1>\n<#list xs as y><#local z = 2></#list>\n</#macro>\n<#local w = 3>\n<#function f><#local v = 1><#return v></#function>\n<#list xs as x><#local u = 4></#list>\n";
+        assert_eq!(
+            diagnostics_of(source, Config::default())
+                .into_iter()
+                .filter(|(_, code)| code == "local_outside_macro")
+                .collect::<Vec<_>>(),
+            vec![
+                (4, "local_outside_macro".to_owned()),
+                (6, "local_outside_macro".to_owned()),
+            ]
+        );
+        // the list outside of the macro doesn't allow a break
+        let source = "<#list xs as x><#macro m><#break></#macro></#list>\n";
+        assert_eq!(
+            diagnostics_of(source, Config::default()),
+            vec![(0, "unexpected_break_stmt".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_function_without_return() {
+        let source = "<#function a>\n<#return 1>\n</#function>\n<#function b>\n</#function>\n<#function c x>\n<#if x><#return 1></#if>\n</#function>\n";
+        assert_eq!(
+            diagnostics_of(source, Config::default()),
+            vec![(3, "function_without_return".to_owned())]
+        );
+        let config = Config {
+            enabled_diagnostics: ["conditional_return_only".to_owned()].into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            diagnostics_of(source, config.clone()),
+            vec![
+                (3, "function_without_return".to_owned()),
+                (5, "conditional_return_only".to_owned()),
+            ]
+        );
+        // every branch of the <#if> returns, or the <#return> follows other statements
+        let source = "<#function f x><#if x><#return 1><#else><#return 2></#if></#function>\n<#function g x>\n<#if x><#return 1><#elseif !x><#return 2><#else>\n<#return 3>\n</#if>\n</#function>\n<#function h x>\n<#assign y = x>\n<#if x></#if>\n<#return y>\n</#function>\n";
+        assert_eq!(diagnostics_of(source, config.clone()), vec![]);
+        // without an <#else> or with a branch that doesn't return
+        let source = "<#function f x><#if x><#return 1></#if></#function>\n<#function g x><#if x><#return 1><#else><#assign y = 2></#if></#function>\n";
+        assert_eq!(
+            diagnostics_of(source, config),
+            vec![
+                (0, "conditional_return_only".to_owned()),
+                (1, "conditional_return_only".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_positional_after_named() {
+        let source = "<@m a x=1 b \"c\"/>\n<@m a b/>\n<@m x=1 y=2/>\n";
+        assert_eq!(
+            diagnostics_of(source, Config::default())
+                .into_iter()
+                .filter(|(_, code)| code == "positional_after_named")
+                .count(),
+            2
+        );
+        let uri = Uri::from_str("file:///tmp/diagnosis.ftl").unwrap();
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let ranges: Vec<Range> = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items
+            .into_iter()
+            .filter(|d| d.code == Some(NumberOrString::String("positional_after_named".to_owned())))
+            .map(|d| d.range)
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                Range::new(Position::new(0, 10), Position::new(0, 11)),
+                Range::new(Position::new(0, 12), Position::new(0, 15)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unclosed_directive() {
+        let uri = Uri::from_str("file:///tmp/unclosed.ftl").unwrap();
+        let source = "<#assign a = 1>\n<#list xs as x>\n<#if x>${x}</#if>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let items = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items;
+        let unclosed = items
+            .iter()
+            .find(|d| d.code == Some(NumberOrString::String("unclosed_directive".to_owned())))
+            .unwrap();
+        assert_eq!(unclosed.message, "Unclosed <#list>, expected </#list>.");
+        assert_eq!(
+            unclosed.range,
+            Range::new(Position::new(1, 0), Position::new(1, 6))
+        );
+        assert!(!items.iter().any(|d| d.message.starts_with("Missing")));
+
+        // no close tag is missing
+        assert!(diagnostics_of("<#if a>\n</#if>\n", Config::default()).is_empty());
+    }
+
+    #[test]
+    fn test_mismatched_close_tag() {
+        let uri = Uri::from_str("file:///tmp/mismatched.ftl").unwrap();
+        let source = "<#list xs as x>\n  <#if x>\n    ${x}\n  </#list>\n</#list>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let items = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items;
+        let mismatched: Vec<_> = items
+            .iter()
+            .filter(|d| d.code == Some(NumberOrString::String("mismatched_close_tag".to_owned())))
+            .collect();
+        assert_eq!(mismatched.len(), 1);
+        assert_eq!(
+            mismatched[0].message,
+            "Mismatched </#list>, expected </#if>."
+        );
+        assert_eq!(mismatched[0].severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(
+            mismatched[0].range,
+            Range::new(Position::new(3, 5), Position::new(3, 9))
+        );
+        let related = &mismatched[0].related_information.as_ref().unwrap()[0];
+        assert_eq!(
+            related.location.range,
+            Range::new(Position::new(1, 4), Position::new(1, 6))
+        );
+
+        // nested, captured, self-closed, incremented, commented, quoted, interpolated and
+        // unparsed tags are in order
+        let source = "<#macro m>\n<#assign x>[#if a][/#if]</#assign>\n<#assign y = 1/>\n<#if a><#assign i++></#if>\n<#local n-->\n<#assign z in ns>z</#assign>\n<#-- <#if> -->\n<#noparse></#list></#noparse>\n<#if true>${\"</#list>\"}</#if>\n<#assign s = \"</#if>\">\n<#if (a > b)>[=\"[/#list]\"]</#if>\n</#macro>\n";
+        assert!(
+            !diagnostics_of(source, Config::default())
+                .iter()
+                .any(|(_, code)| code == "mismatched_close_tag")
+        );
+    }
+
+    #[test]
+    fn test_interpolation_in_expression() {
+        let uri = Uri::from_str("file:///tmp/interpolation.ftl").unwrap();
+        let source = "<#if ${x}>a</#if>\n<#list ${xs} as i>${i}</#list>\n<#assign y = ${x}>\n<#assign s = \"a${x}\">\n${x}\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let items = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items;
+        let misplaced: Vec<(Range, String)> = items
+            .iter()
+            .filter(|d| {
+                d.code
+                    == Some(NumberOrString::String(
+                        "interpolation_in_expression".to_owned(),
+                    ))
+            })
+            .map(|d| (d.range, d.message.clone()))
+            .collect();
+        assert_eq!(
+            misplaced,
+            vec![
+                (
+                    Range::new(Position::new(0, 5), Position::new(0, 9)),
+                    "Interpolation is not allowed in an expression, write 'x' instead of '${x}'."
+                        .to_owned()
+                ),
+                (
+                    Range::new(Position::new(1, 7), Position::new(1, 12)),
+                    "Interpolation is not allowed in an expression, write 'xs' instead of '${xs}'."
+                        .to_owned()
+                ),
+                (
+                    Range::new(Position::new(2, 13), Position::new(2, 17)),
+                    "Interpolation is not allowed in an expression, write 'x' instead of '${x}'."
+                        .to_owned()
+                ),
+            ]
+        );
+        // the content of the braces is not reported on its own
+        assert!(!items.iter().any(|d| d.message.contains("Unexpected 'x'")));
+    }
+
+    #[tokio::test]
+    async fn test_import_without_macros() {
+        let source = "<#import \"globals.ftl\" as g>\n<#import \"empty.ftl\" as e>\n<#import \"lib.ftl\" as lib>\n<@g.anything/>\n<@e.anything/>\n<@lib.foo/>\n<@lib.bar/>\n<#-- lsp-disable-next-line undefined_macro -->\n<@g.ignored/>\n";
+        let dir = utils::fixture_dir(
+            "import_without_macros",
+            &[
+                ("globals.ftl", "<#global theme = \"dark\">\n"),
+                ("empty.ftl", ""),
+                ("lib.ftl", "<#macro foo>\n</#macro>\n"),
+                ("main.ftl", source),
+            ],
+        );
+        let uri = Uri::from_file_path(dir.join("main.ftl")).unwrap();
+        let workspace = Workspace::new();
+        workspace
+            .on_did_open(&DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: uri.clone(),
+                    language_id: "ftl".to_owned(),
+                    version: 0,
+                    text: source.to_owned(),
+                },
+            })
+            .await;
+        let Ok(DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Full(report))) =
+            workspace
+                .on_diagnostic(DocumentDiagnosticParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    identifier: None,
+                    previous_result_id: None,
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                })
+                .await
+        else {
+            panic!("expect a full report");
+        };
+        let codes: Vec<(u32, String)> = report
+            .full_document_diagnostic_report
+            .items
+            .into_iter()
+            .filter_map(|d| match d.code {
+                Some(NumberOrString::String(code)) => Some((d.range.start.line, code)),
+                _ => None,
+            })
+            .collect();
+        // the imports are fine, the members are not
+        assert_eq!(
+            codes,
+            vec![
+                (3, "undefined_macro".to_owned()),
+                (4, "undefined_macro".to_owned()),
+                (6, "undefined_macro".to_owned()),
+            ]
+        );
+        // the namespace is empty rather than unknown
+        let empty = Uri::from_file_path(dir.join("empty.ftl")).unwrap();
+        let analysis = workspace.imports.get(&empty).await.unwrap();
+        assert!(analysis.get_macro_parameters("anything").is_none());
+        assert!(analysis.find_macro_definition("anything").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_workspace_diagnostic() {
+        let workspace = Workspace::new();
+        let first = Uri::from_str("file:///tmp/first.ftl").unwrap();
+        let second = Uri::from_str("file:///tmp/second.ftl").unwrap();
+        for (uri, version) in [(&second, 3), (&first, 1)] {
+            workspace
+                .on_did_open(&DidOpenTextDocumentParams {
+                    text_document: TextDocumentItem {
+                        uri: uri.clone(),
+                        language_id: "ftl".to_owned(),
+                        version,
+                        text: "<@undefined/>\n".to_owned(),
+                    },
+                })
+                .await;
+        }
+        let report = |previous_result_ids| async {
+            let Ok(WorkspaceDiagnosticReportResult::Report(report)) = workspace
+                .on_workspace_diagnostic(WorkspaceDiagnosticParams {
+                    identifier: None,
+                    previous_result_ids,
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                })
+                .await
+            else {
+                panic!("expect a report");
+            };
+            report.items
+        };
+        let items = report(vec![]).await;
+        assert_eq!(items.len(), 2);
+        let WorkspaceDocumentDiagnosticReport::Full(full) = &items[0] else {
+            panic!("expect a full report");
+        };
+        assert_eq!(full.uri, first);
+        assert_eq!(full.version, Some(1));
+        assert_eq!(
+            full.full_document_diagnostic_report.result_id.as_deref(),
+            Some("1.0")
+        );
+        assert!(!full.full_document_diagnostic_report.items.is_empty());
+        // only the document whose version advanced is reported again
+        let items = report(vec![
+            PreviousResultId {
+                uri: first.clone(),
+                value: "1.0".to_owned(),
+            },
+            PreviousResultId {
+                uri: second.clone(),
+                value: "2.0".to_owned(),
+            },
+        ])
+        .await;
+        assert!(matches!(
+            &items[0],
+            WorkspaceDocumentDiagnosticReport::Unchanged(unchanged) if unchanged.uri == first
+        ));
+        assert!(matches!(
+            &items[1],
+            WorkspaceDocumentDiagnosticReport::Full(full) if full.uri == second
+        ));
+        // a changed template may define the macro, every document is reported again
+        workspace
+            .imports
+            .invalidate(&Uri::from_str("file:///tmp/undefined.ftl").unwrap())
+            .await;
+        let items = report(vec![
+            PreviousResultId {
+                uri: first.clone(),
+                value: "1.0".to_owned(),
+            },
+            PreviousResultId {
+                uri: second.clone(),
+                value: "3.0".to_owned(),
+            },
+        ])
+        .await;
+        assert!(
+            items
+                .iter()
+                .all(|item| matches!(item, WorkspaceDocumentDiagnosticReport::Full(_)))
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_categories() {
+        let uri = Uri::from_str("file:///tmp/category.ftl").unwrap();
+        let source =
+            "<#if a = 1></#if>\n<@foo/>\n<#break>\n<#macro m></#macro>\n<#macro m></#macro>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let categories: Vec<(String, Category)> = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items
+            .into_iter()
+            .map(|d| {
+                let Some(NumberOrString::String(code)) = d.code else {
+                    panic!("expect a string code");
+                };
+                let data: DiagnosticData = serde_json::from_value(d.data.unwrap()).unwrap();
+                (code, data.category)
+            })
+            .collect();
+        for (code, category) in [
+            ("deprecated_equal_operator", Category::Style),
+            ("undefined_macro", Category::Semantics),
+            ("unexpected_break_stmt", Category::Syntax),
+            ("duplicated_symbol", Category::Semantics),
+        ] {
+            assert!(
+                categories.contains(&(code.to_owned(), category)),
+                "{}: {:?}",
+                code,
+                categories
+            );
+        }
+    }
+
+    #[test]
+    fn test_html_without_false_errors() {
+        let uri = Uri::from_str("file:///tmp/diagnosis.ftl").unwrap();
+        // "${name}" of the JavaScript template literal is interpolated by FreeMarker as well,
+        // it needs a <#noparse> to be kept as is
+        let source = r##"<html>
+<head>
+<script>
+  const greeting = `hello ${name}`;
+  if (a < b && c > d) {}
+</script>
+<style>
+  a > b { color: red; }
+</style>
+</head>
+<body>
+<pre>
+  use <# for directives and <@ for macro calls
+</pre>
+<#if user??>
+  <p class="${cls}">Hi ${user.name}, <a href="#top">top</a></p>
+  <p>price < 10 & 1 <2</p>
+</#if>
+<p>a <@ b</p>
+</body>
+</html>
+"##;
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let items = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items;
+        assert!(items.is_empty(), "{:?}", items);
+
+        // a name makes it a tag again
+        let items = diagnostics_of("<p>a <@b</p>\n", Default::default());
+        assert!(!items.is_empty());
+    }
+
+    #[test]
+    fn test_max_line_length() {
+        let directive = format!("<#assign x = \"{}\">", "a".repeat(120));
+        let html = format!("<p>{}</p>", "a".repeat(120));
+        let source = format!("{}\n{}\n<#if x>\n</#if>\n", directive, html);
+        assert!(diagnostics_of(&source, Default::default()).is_empty());
+
+        // the long line of HTML is left alone
+        let config = Config {
+            enabled_diagnostics: ["max_line_length".to_owned()].into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            diagnostics_of(&source, config),
+            vec![(0, "max_line_length".to_owned())]
+        );
+
+        // "é" is 2 bytes but a single UTF-16 code unit
+        let source = "${\"éééé\"}\n";
+        let mut config = Config::from_initialization_options(Some(serde_json::json!({
+            "enabledDiagnostics": ["max_line_length"],
+            "maxLineLength": 10,
+        })));
+        assert!(diagnostics_of(source, config.clone()).is_empty());
+        config.position_encoding = PositionEncodingKind::UTF8;
+        assert_eq!(
+            diagnostics_of(source, config),
+            vec![(0, "max_line_length".to_owned())]
+        );
+
+        // a style rule, the manual has no page about it
+        let diagnostic: tower_lsp_server::ls_types::Diagnostic =
+            super::Scenario::MAX_LINE_LENGTH.into();
+        assert_eq!(
+            diagnostic.source.as_deref(),
+            Some(tree_sitter_freemarker::STYLE)
+        );
+        assert!(diagnostic.code_description.is_none());
+    }
+}
@@ -8,13 +8,22 @@ use std::{ops::Range, path::PathBuf};
 
 use ropey::{Rope, RopeSlice};
 use thiserror::Error;
-use tower_lsp_server::ls_types::{Position, TextDocumentContentChangeEvent, Uri};
+use tower_lsp_server::ls_types::{self, Position, TextDocumentContentChangeEvent, Uri};
 use tree_sitter::{InputEdit, Point};
 
+pub const BOM: char = '\u{feff}';
+
 #[derive(Debug)]
 pub struct TextDocument {
     uri: Uri,
     pub rope: Rope,
+    // the BOM is not a part of the rope, it would shift the offsets of the first line
+    has_bom: bool,
+    // of the positions exchanged with the client, the columns of the parser are bytes
+    position_encoding: PositionEncodingKind,
+    // the bytes copied out of the rope, the tests check that they grow linearly
+    #[cfg(test)]
+    copied_bytes: std::sync::atomic::AtomicUsize,
 }
 
 #[derive(Error, Debug)]
@@ -25,20 +34,57 @@ pub enum DocumentError {
     LineIndexOutOfBounds(usize),
 }
 
-#[derive(Clone, Debug, Copy)]
+#[derive(Clone, Debug, Copy, Default)]
 /// We redeclare this enum here because the `lsp_types` crate exports a Cow
 /// type that is unconvenient to deal with.
 pub enum PositionEncodingKind {
     UTF8,
-    #[allow(dead_code)]
+    #[default]
     UTF16,
-    #[allow(dead_code)]
     UTF32,
 }
 
+impl PositionEncodingKind {
+    /// The first encoding offered by the client which is supported, the offers
+    /// are in the order of the client preference. UTF-16 is the default of the
+    /// protocol when the client offers nothing.
+    pub fn negotiate(offered: Option<&Vec<ls_types::PositionEncodingKind>>) -> Self {
+        offered
+            .into_iter()
+            .flatten()
+            .find_map(|kind| match kind.as_str() {
+                "utf-8" => Some(PositionEncodingKind::UTF8),
+                "utf-16" => Some(PositionEncodingKind::UTF16),
+                "utf-32" => Some(PositionEncodingKind::UTF32),
+                _ => None,
+            })
+            .unwrap_or(PositionEncodingKind::UTF16)
+    }
+
+    // the code units of a BOM in the encoding
+    pub fn bom_width(&self) -> u32 {
+        match self {
+            PositionEncodingKind::UTF8 => BOM.len_utf8() as u32,
+            PositionEncodingKind::UTF16 => BOM.len_utf16() as u32,
+            PositionEncodingKind::UTF32 => 1,
+        }
+    }
+}
+
+impl From<PositionEncodingKind> for ls_types::PositionEncodingKind {
+    fn from(kind: PositionEncodingKind) -> Self {
+        match kind {
+            PositionEncodingKind::UTF8 => ls_types::PositionEncodingKind::UTF8,
+            PositionEncodingKind::UTF16 => ls_types::PositionEncodingKind::UTF16,
+            PositionEncodingKind::UTF32 => ls_types::PositionEncodingKind::UTF32,
+        }
+    }
+}
+
 impl std::fmt::Display for TextDocument {
     #[inline]
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.count_copy(self.rope.len_bytes());
         self.rope.fmt(f)
     }
 }
@@ -47,19 +93,44 @@ impl TextDocument {
     /// Creates a new document from the given text and language id. It creates
     /// a rope, parser and syntax tree from the text.
     pub fn new(uri: &Uri, text: &str) -> Self {
+        let stripped = text.strip_prefix(BOM);
         TextDocument {
             uri: uri.clone(),
-            rope: Rope::from_str(text),
+            rope: Rope::from_str(stripped.unwrap_or(text)),
+            has_bom: stripped.is_some(),
+            position_encoding: PositionEncodingKind::default(),
+            #[cfg(test)]
+            copied_bytes: Default::default(),
         }
     }
 
-    pub fn uri(&self) -> Uri {
-        self.uri.clone()
+    #[inline]
+    fn count_copy(&self, _bytes: usize) {
+        #[cfg(test)]
+        self.copied_bytes
+            .fetch_add(_bytes, std::sync::atomic::Ordering::Relaxed);
     }
 
-    pub fn canonical_uri(&self) -> PathBuf {
-        let filepath = self.uri.to_file_path().unwrap();
-        filepath.canonicalize().unwrap()
+    #[cfg(test)]
+    pub fn copied_bytes(&self) -> usize {
+        self.copied_bytes.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    pub fn with_position_encoding(mut self, position_encoding: PositionEncodingKind) -> Self {
+        self.position_encoding = position_encoding;
+        self
+    }
+
+    pub fn position_encoding(&self) -> PositionEncodingKind {
+        self.position_encoding
+    }
+
+    pub fn has_bom(&self) -> bool {
+        self.has_bom
+    }
+
+    pub fn uri(&self) -> Uri {
+        self.uri.clone()
     }
 
     pub fn dir(&self) -> PathBuf {
@@ -68,35 +139,116 @@ impl TextDocument {
         parent.to_path_buf()
     }
 
-    pub fn line_count(&self) -> usize {
-        self.rope.len_lines()
-    }
-
     pub fn enumerate_lines<F>(&self, mut func: F)
     where
         F: FnMut(usize, &str),
     {
         for (index, line_rope) in self.rope.lines().enumerate() {
+            self.count_copy(line_rope.len_bytes());
             let line = line_rope.to_string().trim_end().to_string();
             func(index, &line)
         }
     }
 
+    // only the slice is copied, it is called for most of the nodes
     pub fn get_ranged_text(&self, range: Range<usize>) -> String {
-        let source = self.rope.to_string();
-        source[range.start..range.end].to_owned()
+        self.count_copy(range.len());
+        self.rope.byte_slice(range).to_string()
+    }
+
+    // the columns of the client count the BOM on the first line
+    fn bom_offset(&self, line: usize, position_encoding: PositionEncodingKind) -> u32 {
+        match self.has_bom && line == 0 {
+            true => position_encoding.bom_width(),
+            false => 0,
+        }
+    }
+
+    // a byte column of the parser as a column of the negotiated encoding
+    pub fn byte_column_to_character(&self, line: usize, column: usize) -> u32 {
+        let offset = self.bom_offset(line, self.position_encoding);
+        // every encoding counts the same columns in an ASCII document, it is called for
+        // most of the nodes so the line lookup is skipped
+        if self.rope.len_bytes() == self.rope.len_chars() {
+            return column as u32 + offset;
+        }
+        let Some(line) = self.rope.get_line(line) else {
+            return column as u32 + offset;
+        };
+        let column = column.min(line.len_bytes());
+        let character = match self.position_encoding {
+            PositionEncodingKind::UTF8 => column,
+            PositionEncodingKind::UTF16 => line.char_to_utf16_cu(line.byte_to_char(column)),
+            PositionEncodingKind::UTF32 => line.byte_to_char(column),
+        };
+        character as u32 + offset
+    }
+
+    // a column of the negotiated encoding as a byte column, clamped to the line without
+    // its line break
+    pub fn character_to_byte_column(&self, line: usize, character: u32) -> usize {
+        let character = character.saturating_sub(self.bom_offset(line, self.position_encoding));
+        let Some(line) = self.rope.get_line(line) else {
+            return 0;
+        };
+        let breaks = line
+            .chars_at(line.len_chars())
+            .reversed()
+            .take_while(|c| matches!(c, '\n' | '\r'))
+            .count();
+        let line = line.slice(..line.len_chars() - breaks);
+        let character = character as usize;
+        let char_idx = match self.position_encoding {
+            PositionEncodingKind::UTF8 => line.byte_to_char(character.min(line.len_bytes())),
+            PositionEncodingKind::UTF16 => {
+                line.utf16_cu_to_char(character.min(line.len_utf16_cu()))
+            }
+            PositionEncodingKind::UTF32 => character.min(line.len_chars()),
+        };
+        line.char_to_byte(char_idx)
+    }
+
+    pub fn point_to_position(&self, point: Point) -> Position {
+        Position::new(
+            point.row as u32,
+            self.byte_column_to_character(point.row, point.column),
+        )
+    }
+
+    pub fn position_to_point(&self, position: &Position) -> Point {
+        Point {
+            row: position.line as usize,
+            column: self.character_to_byte_column(position.line as usize, position.character),
+        }
+    }
+
+    pub fn byte_to_position(&self, byte: usize) -> Position {
+        let byte = byte.min(self.rope.len_bytes());
+        let line = self.rope.byte_to_line(byte);
+        self.point_to_position(Point {
+            row: line,
+            column: byte - self.rope.line_to_byte(line),
+        })
+    }
+
+    pub fn position_to_byte(&self, position: &Position) -> usize {
+        let line = (position.line as usize).min(self.rope.len_lines() - 1);
+        let column = self.character_to_byte_column(line, position.character);
+        (self.rope.line_to_byte(line) + column).min(self.rope.len_bytes())
     }
 
     pub fn get_line_text(&self, index: usize) -> String {
         let line = self.rope.line(index);
+        self.count_copy(line.len_bytes());
         line.to_string()
     }
 
     pub fn get_prev_char_at(&self, position: &Position) -> Option<char> {
-        if let Some(line) = self.rope.get_line(position.line as usize)
-            && position.character > 0
+        let point = self.position_to_point(position);
+        if let Some(line) = self.rope.get_line(point.row)
+            && point.column > 0
         {
-            return line.get_char(position.character as usize - 1);
+            return line.get_char(line.byte_to_char(point.column) - 1);
         }
         None
     }
@@ -108,6 +260,20 @@ impl TextDocument {
         }
     }
 
+    // in the code units of the encoding, without the line break
+    pub fn line_len_in(&self, id: usize, position_encoding: PositionEncodingKind) -> usize {
+        let Some(line) = self.rope.get_line(id) else {
+            return 0;
+        };
+        let line = line.to_string();
+        let content = line.trim_end_matches(['\n', '\r']);
+        match position_encoding {
+            PositionEncodingKind::UTF8 => content.len(),
+            PositionEncodingKind::UTF16 => content.encode_utf16().count(),
+            PositionEncodingKind::UTF32 => content.chars().count(),
+        }
+    }
+
     /// Apply a change to the document.
     pub fn apply_content_change(
         &mut self,
@@ -115,7 +281,12 @@ impl TextDocument {
         position_encoding: PositionEncodingKind,
     ) -> Result<Option<InputEdit>, DocumentError> {
         match change.range {
-            Some(range) => {
+            Some(mut range) => {
+                for position in [&mut range.start, &mut range.end] {
+                    position.character = position
+                        .character
+                        .saturating_sub(self.bom_offset(position.line as usize, position_encoding));
+                }
                 assert!(
                     range.start.line < range.end.line
                         || (range.start.line == range.end.line
@@ -204,19 +375,13 @@ impl TextDocument {
                 // change starts/ends. Required for tree-sitter.
                 let change_start_line_byte_idx = match position_encoding {
                     PositionEncodingKind::UTF8 => change_start_line_cu_idx,
-                    PositionEncodingKind::UTF16 => {
-                        change_start_line.char_to_utf16_cu(change_start_line_char_idx)
-                    }
-                    PositionEncodingKind::UTF32 => change_start_line_char_idx,
+                    _ => change_start_line.char_to_byte(change_start_line_char_idx),
                 };
                 let change_end_line_byte_idx = match same_line && same_character {
                     true => change_start_line_byte_idx,
                     false => match position_encoding {
                         PositionEncodingKind::UTF8 => change_end_line_cu_idx,
-                        PositionEncodingKind::UTF16 => {
-                            change_end_line.char_to_utf16_cu(change_end_line_char_idx)
-                        }
-                        PositionEncodingKind::UTF32 => change_end_line_char_idx,
+                        _ => change_end_line.char_to_byte(change_end_line_char_idx),
                     },
                 };
 
@@ -229,7 +394,8 @@ impl TextDocument {
                 let change_new_end_line_idx = self
                     .rope
                     .byte_to_line(change_start_doc_byte_idx + change.text.len());
-                let change_new_end_line_byte_idx = change_start_doc_byte_idx + change.text.len();
+                let change_new_end_line_byte_idx = change_start_doc_byte_idx + change.text.len()
+                    - self.rope.line_to_byte(change_new_end_line_idx);
 
                 // 7. Construct the tree-sitter edit. We stay mindful that
                 // tree-sitter Point::column is a byte offset.
@@ -256,7 +422,9 @@ impl TextDocument {
                 return Ok(Some(edit));
             }
             None => {
-                self.rope = Rope::from_str(&change.text);
+                let stripped = change.text.strip_prefix(BOM);
+                self.rope = Rope::from_str(stripped.unwrap_or(&change.text));
+                self.has_bom = stripped.is_some();
                 //self.tree = self.parser.parse(&change.text, None);
             }
         }
@@ -264,3 +432,123 @@ impl TextDocument {
         Ok(None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tower_lsp_server::ls_types::{self, Position, Range, TextDocumentContentChangeEvent, Uri};
+
+    use super::{PositionEncodingKind, TextDocument};
+
+    #[test]
+    fn test_negotiate_position_encoding() {
+        let offered = vec![
+            ls_types::PositionEncodingKind::new("utf-7"),
+            ls_types::PositionEncodingKind::UTF8,
+            ls_types::PositionEncodingKind::UTF16,
+        ];
+        assert!(matches!(
+            PositionEncodingKind::negotiate(Some(&offered)),
+            PositionEncodingKind::UTF8
+        ));
+        assert!(matches!(
+            PositionEncodingKind::negotiate(None),
+            PositionEncodingKind::UTF16
+        ));
+    }
+
+    #[test]
+    fn test_utf16_content_change() {
+        let uri = Uri::from_str("file:///tmp/doc.ftl").unwrap();
+        let mut doc = TextDocument::new(&uri, "${\u{1F600}é}x\n");
+        // "x" is at the UTF-16 column 6 but at the byte column 9
+        let change = TextDocumentContentChangeEvent {
+            range: Some(Range::new(Position::new(0, 6), Position::new(0, 7))),
+            range_length: None,
+            text: "y".to_owned(),
+        };
+        let edit = doc
+            .apply_content_change(&change, PositionEncodingKind::UTF16)
+            .unwrap()
+            .unwrap();
+        assert_eq!(doc.to_string(), "${\u{1F600}é}y\n");
+        assert_eq!(edit.start_byte, 9);
+        assert_eq!(edit.start_position.column, 9);
+        assert_eq!(edit.new_end_position.column, 10);
+    }
+
+    #[test]
+    fn test_strip_bom() {
+        let uri = Uri::from_str("file:///tmp/doc.ftl").unwrap();
+        let mut doc = TextDocument::new(&uri, "\u{feff}<#if x>\n");
+        assert!(doc.has_bom());
+        assert_eq!(doc.to_string(), "<#if x>\n");
+        // a full text change may add or drop it
+        let change = TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: "${x}\n".to_owned(),
+        };
+        doc.apply_content_change(&change, PositionEncodingKind::UTF16)
+            .unwrap();
+        assert!(!doc.has_bom());
+        assert_eq!(doc.to_string(), "${x}\n");
+    }
+
+    #[test]
+    fn test_convert_columns() {
+        let uri = Uri::from_str("file:///tmp/doc.ftl").unwrap();
+        let source = "x\n<p>\u{1F600}é${y}</p>\n";
+        // "y" is at the byte column 11
+        let doc = TextDocument::new(&uri, source);
+        assert_eq!(doc.byte_column_to_character(1, 11), 8);
+        assert_eq!(doc.character_to_byte_column(1, 8), 11);
+        assert_eq!(doc.byte_to_position(13), Position::new(1, 8));
+        assert_eq!(doc.position_to_byte(&Position::new(1, 8)), 13);
+
+        let doc =
+            TextDocument::new(&uri, source).with_position_encoding(PositionEncodingKind::UTF32);
+        assert_eq!(doc.byte_column_to_character(1, 11), 7);
+        assert_eq!(doc.position_to_byte(&Position::new(1, 7)), 13);
+
+        let doc =
+            TextDocument::new(&uri, source).with_position_encoding(PositionEncodingKind::UTF8);
+        assert_eq!(doc.byte_column_to_character(1, 11), 11);
+        // past the end of the line
+        assert_eq!(doc.position_to_byte(&Position::new(0, 5)), 1);
+    }
+
+    #[test]
+    fn test_bom_content_change() {
+        let uri = Uri::from_str("file:///tmp/doc.ftl").unwrap();
+        for (position_encoding, width) in [
+            (PositionEncodingKind::UTF8, 3),
+            (PositionEncodingKind::UTF16, 1),
+            (PositionEncodingKind::UTF32, 1),
+        ] {
+            let mut doc = TextDocument::new(&uri, "\u{feff}${x}\n${x}\n")
+                .with_position_encoding(position_encoding);
+            // "x" of the first line is at the column 2 of the client, after the BOM
+            let change = TextDocumentContentChangeEvent {
+                range: Some(Range::new(
+                    Position::new(0, 2 + width),
+                    Position::new(0, 3 + width),
+                )),
+                range_length: None,
+                text: "y".to_owned(),
+            };
+            let edit = doc
+                .apply_content_change(&change, position_encoding)
+                .unwrap()
+                .unwrap();
+            assert_eq!(doc.to_string(), "${y}\n${x}\n");
+            assert_eq!(edit.start_byte, 2);
+            assert_eq!(edit.start_position.column, 2);
+            assert_eq!(doc.byte_to_position(2), Position::new(0, 2 + width));
+            assert_eq!(doc.position_to_byte(&Position::new(0, 2 + width)), 2);
+            // the other lines are left alone
+            assert_eq!(doc.byte_to_position(7), Position::new(1, 2));
+        }
+    }
+}
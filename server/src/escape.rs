@@ -0,0 +1,137 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use tower_lsp_server::ls_types::{Position, Range};
+use tree_sitter::Node;
+
+use crate::{
+    analysis::{Analysis, AnalysisContext, EscapeAnalysis},
+    doc::TextDocument,
+    utils::{self, ErrorNodeDirective},
+};
+
+const ESCAPE: &str = "escape";
+const NOESCAPE: &str = "noescape";
+
+#[derive(Clone, Debug)]
+pub struct Escape {
+    // "x?html" of `<#escape x as x?html>`, none inside <#noescape>
+    pub expression: Option<String>,
+    // from the start tag to the end tag
+    pub range: Range,
+}
+
+enum EscapeTag {
+    Begin(Option<String>),
+    End,
+}
+
+fn parse_escape_tag(directive: &ErrorNodeDirective) -> Option<EscapeTag> {
+    let rest = directive.rest.as_str();
+    if !rest.starts_with(|c: char| c == '>' || c.is_whitespace()) {
+        return None;
+    }
+    match (directive.name.as_str(), directive.closing) {
+        (ESCAPE | NOESCAPE, true) => return Some(EscapeTag::End),
+        (NOESCAPE, false) => return Some(EscapeTag::Begin(None)),
+        (ESCAPE, false) => (),
+        _ => return None,
+    }
+    let expression = rest[..rest.find('>').unwrap_or(rest.len())]
+        .split_once(" as ")
+        .map(|(_, expression)| expression.trim().to_owned())
+        .filter(|expression| !expression.is_empty());
+    Some(EscapeTag::Begin(expression))
+}
+
+// the innermost escaping block which contains the position
+pub fn find_escape<'a>(escapes: &'a [Escape], position: &Position) -> Option<&'a Escape> {
+    escapes
+        .iter()
+        .filter(|escape| escape.range.start <= *position && *position <= escape.range.end)
+        .max_by_key(|escape| escape.range.start)
+}
+
+impl EscapeAnalysis for Analysis {
+    fn analyze_escape(
+        &mut self,
+        node: &Node,
+        doc: &TextDocument,
+        ctx: &mut AnalysisContext,
+    ) -> bool {
+        let Some(directive) = utils::error_node_directive(node, doc) else {
+            return false;
+        };
+        match parse_escape_tag(&directive) {
+            Some(EscapeTag::Begin(expression)) => {
+                ctx.escape_stack
+                    .push((expression, doc.byte_to_position(node.start_byte())));
+                true
+            }
+            Some(EscapeTag::End) => {
+                let end = doc.byte_to_position(
+                    directive.rest_byte
+                        + directive
+                            .rest
+                            .find('>')
+                            .map_or(directive.rest.len(), |i| i + 1),
+                );
+                if let Some((expression, start)) = ctx.escape_stack.pop() {
+                    self.add_escape(Escape {
+                        expression,
+                        range: Range { start, end },
+                    });
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn close_escapes(&mut self, doc: &TextDocument, ctx: &mut AnalysisContext) {
+        // unclosed blocks last until the end of the document
+        let end = doc.byte_to_position(doc.to_string().len());
+        while let Some((expression, start)) = ctx.escape_stack.pop() {
+            self.add_escape(Escape {
+                expression,
+                range: Range { start, end },
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tower_lsp_server::ls_types::{Position, Uri};
+
+    use crate::{escape::find_escape, reactor::Reactor};
+
+    #[test]
+    fn test_nested_escapes() {
+        let uri = Uri::from_str("file:///tmp/escape.ftl").unwrap();
+        let source = "${a}\n<#escape x as x?html>\n${b}\n<#escape y as y?url>\n${c}\n</#escape>\n<#noescape>${d}</#noescape>\n</#escape>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let escapes = reactor.get_analysis().get_escapes();
+        assert_eq!(escapes.len(), 3);
+        let expression_at = |line, character| {
+            find_escape(escapes, &Position::new(line, character))
+                .and_then(|escape| escape.expression.clone())
+        };
+        assert_eq!(expression_at(0, 2), None);
+        assert_eq!(expression_at(2, 2).as_deref(), Some("x?html"));
+        assert_eq!(expression_at(4, 2).as_deref(), Some("y?url"));
+        assert_eq!(expression_at(6, 13), None);
+        // the escape directives are not syntax errors
+        assert!(
+            reactor
+                .get_analysis()
+                .get_analyzed_full_diagnostics()
+                .full_document_diagnostic_report
+                .items
+                .is_empty()
+        );
+    }
+}
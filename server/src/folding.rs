@@ -4,7 +4,7 @@
 
 use std::str::FromStr;
 
-use tower_lsp_server::ls_types::{FoldingRange, FoldingRangeProviderCapability};
+use tower_lsp_server::ls_types::{FoldingRange, FoldingRangeKind, FoldingRangeProviderCapability};
 use tree_sitter::Node;
 use tree_sitter_freemarker::grammar::Rule;
 
@@ -18,34 +18,100 @@ pub fn folding_capability() -> FoldingRangeProviderCapability {
     FoldingRangeProviderCapability::Simple(true)
 }
 
-impl FoldingAnalysis for Analysis {
-    fn analyze_folding_ranges(&mut self, node: &Node, ctx: &mut AnalysisContext) {
-        if node.is_error() || node.is_missing() {
-            // not sure if it is proper
-            return;
-        }
-        if let Ok(
-            Rule::Comment
+fn is_foldable(node: &Node) -> bool {
+    matches!(
+        Rule::from_str(node.kind()),
+        Ok(Rule::Comment
             | Rule::AssignClause
             | Rule::CaseClause
             | Rule::DefaultClause
             | Rule::ElseClause
+            | Rule::FtlStmt
             | Rule::FunctionClause
             | Rule::IfClause
             | Rule::ListClause
             | Rule::LocalClause
             | Rule::MacroClause
             | Rule::OnClause
-            | Rule::SwitchClause,
-        ) = Rule::from_str(node.kind())
-        {
-            // node kind with "_clause" requires indent increasing
+            | Rule::SwitchClause)
+    )
+}
+
+// a single tag which spans lines, e.g. `<@table ... />`, `${...}` or `<#assign x = {...}>`,
+// the directives with a body are folded by their clauses
+fn is_multiline_tag(node: &Node) -> bool {
+    if node.start_position().row == node.end_position().row {
+        return false;
+    }
+    match Rule::from_str(node.kind()) {
+        Ok(Rule::MacroCall | Rule::Interpolation) => true,
+        Ok(Rule::Directive) => node.named_child(0).is_some_and(|stmt| {
+            let mut cursor = stmt.walk();
+            !is_foldable(&stmt)
+                && !stmt
+                    .named_children(&mut cursor)
+                    .any(|child| is_foldable(&child))
+        }),
+        _ => false,
+    }
+}
+
+// 1 for the outermost foldable blocks
+fn folding_depth(node: &Node) -> usize {
+    let mut depth = 1;
+    let mut node_cursor = node.parent();
+    while let Some(parent) = node_cursor {
+        if is_foldable(&parent) {
+            depth += 1;
+        }
+        node_cursor = parent.parent();
+    }
+    depth
+}
+
+impl FoldingAnalysis for Analysis {
+    fn analyze_folding_ranges(&mut self, node: &Node, ctx: &mut AnalysisContext) {
+        if node.is_error() || node.is_missing() {
+            // not sure if it is proper
+            return;
+        }
+        if is_foldable(node) || is_multiline_tag(node) {
+            if ctx
+                .config
+                .folding
+                .max_depth
+                .is_some_and(|max_depth| folding_depth(node) > max_depth)
+            {
+                return;
+            }
+            let start_line = node.start_position().row as u32;
+            let (end_line, kind) = match Rule::from_str(node.kind()) {
+                // a comment or the ftl header ends with its own last line
+                Ok(Rule::Comment) => (
+                    node.end_position().row as u32,
+                    Some(FoldingRangeKind::Comment),
+                ),
+                Ok(Rule::FtlStmt) => (
+                    node.end_position().row as u32,
+                    Some(FoldingRangeKind::Region),
+                ),
+                // node kind with "_clause" requires indent increasing, it ends where the next
+                // tag begins, e.g. the body of a "<#case>" ends on the line before the next case,
+                // the last line of a tag stays visible likewise
+                _ => ((node.end_position().row as u32).saturating_sub(1), None),
+            };
+            // e.g. a single-line comment or the empty body of "<#case 1><#case 2>", nothing
+            // to fold
+            if end_line <= start_line {
+                return;
+            }
             let id = node.id();
             if !ctx.ranges_set.contains(&id) {
                 ctx.ranges_set.insert(id);
                 self.add_folding_range(FoldingRange {
-                    start_line: node.start_position().row as u32,
-                    end_line: node.end_position().row as u32 - 1,
+                    start_line,
+                    end_line,
+                    kind,
                     ..Default::default()
                 });
             }
@@ -61,3 +127,117 @@ impl FoldingFeature for Reactor {
         Ok(Some(self.get_analysis().get_analyzed_folding_ranges()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use serde_json::json;
+    use tower_lsp_server::ls_types::{FoldingRangeKind, Uri};
+
+    use crate::{config::Config, reactor::Reactor};
+
+    #[test]
+    fn test_folding_max_depth() {
+        let uri = Uri::from_str("file:///tmp/folding.ftl").unwrap();
+        let source = "<#if a>\n<#if b>\n<#if c>\n<#if d>\n<#if e>\nx\n</#if>\n</#if>\n</#if>\n</#if>\n</#if>\n";
+        let unlimited = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        assert_eq!(
+            unlimited.get_analysis().get_analyzed_folding_ranges().len(),
+            5
+        );
+
+        let config = Config::from_initialization_options(Some(json!({
+            "folding": { "maxDepth": 2 }
+        })));
+        let reactor = Reactor::new(&uri, source, 0, Arc::new(config), Default::default());
+        let mut start_lines: Vec<_> = reactor
+            .get_analysis()
+            .get_analyzed_folding_ranges()
+            .iter()
+            .map(|range| range.start_line)
+            .collect();
+        start_lines.sort();
+        assert_eq!(start_lines, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_folding_capture_assign() {
+        let uri = Uri::from_str("file:///tmp/folding.ftl").unwrap();
+        let source = "<#assign html>\n<p>${x}</p>\n<#if y>\nz\n</#if>\n</#assign>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let mut ranges: Vec<_> = reactor
+            .get_analysis()
+            .get_analyzed_folding_ranges()
+            .iter()
+            .map(|range| (range.start_line, range.end_line))
+            .collect();
+        ranges.sort();
+        assert_eq!(ranges, vec![(0, 4), (2, 3)]);
+    }
+
+    #[test]
+    fn test_folding_switch_cases() {
+        let uri = Uri::from_str("file:///tmp/folding.ftl").unwrap();
+        let source = "<#switch x>\n<#case 1>\none\n<#break>\n<#case 2>\ntwo\n<#case 3><#case 4>\nthree\n</#switch>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let mut ranges: Vec<_> = reactor
+            .get_analysis()
+            .get_analyzed_folding_ranges()
+            .iter()
+            .map(|range| (range.start_line, range.end_line))
+            .collect();
+        ranges.sort();
+        // the switch and each case up to the line before the next one, "<#case 3>" is empty
+        assert_eq!(ranges, vec![(0, 7), (1, 3), (4, 5), (6, 7)]);
+    }
+
+    #[test]
+    fn test_folding_comments_and_ftl_header() {
+        let uri = Uri::from_str("file:///tmp/folding.ftl").unwrap();
+        let source = "<#ftl\n  encoding=\"UTF-8\"\n  strip_whitespace=true>\n<#-- one -->\n<#--\n  two\n-->\n<#if x>\ny\n</#if>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let mut ranges: Vec<_> = reactor
+            .get_analysis()
+            .get_analyzed_folding_ranges()
+            .iter()
+            .map(|range| (range.start_line, range.end_line, range.kind.clone()))
+            .collect();
+        ranges.sort_by_key(|range| range.0);
+        // the single-line comment is not folded
+        assert_eq!(
+            ranges,
+            vec![
+                (0, 2, Some(FoldingRangeKind::Region)),
+                (4, 6, Some(FoldingRangeKind::Comment)),
+                (7, 8, None),
+            ]
+        );
+
+        // a single-line header is not folded either
+        let source = "<#ftl encoding=\"UTF-8\">\n<#-- one -->\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        assert!(
+            reactor
+                .get_analysis()
+                .get_analyzed_folding_ranges()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_folding_multiline_tags() {
+        let uri = Uri::from_str("file:///tmp/folding.ftl").unwrap();
+        let source = "<@table\n  columns=[\"a\", \"b\"]\n  rows=rows/>\n${\n  x +\n  y\n}\n<#assign x = {\n  \"a\": 1,\n  \"b\": 2\n}>\n<#if x>\n<@t a=1\n  b=2/>\n</#if>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let mut ranges: Vec<_> = reactor
+            .get_analysis()
+            .get_analyzed_folding_ranges()
+            .iter()
+            .map(|range| (range.start_line, range.end_line))
+            .collect();
+        ranges.sort();
+        // a tag on two lines has nothing to fold, the <#if> is folded once by its clause
+        assert_eq!(ranges, vec![(0, 1), (3, 5), (7, 9), (11, 13)]);
+    }
+}
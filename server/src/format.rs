@@ -5,12 +5,18 @@
 use tower_lsp_server::{
     jsonrpc::Result as JsonRpcResult,
     ls_types::{
-        DocumentFormattingOptions, DocumentFormattingParams, OneOf, Position, Range, TextEdit,
+        DocumentFormattingOptions, DocumentFormattingParams, DocumentOnTypeFormattingOptions,
+        DocumentOnTypeFormattingParams, DocumentRangeFormattingOptions,
+        DocumentRangeFormattingParams, FormattingOptions, OneOf, Position, Range, TextEdit,
     },
 };
 use tree_sitter::Point;
 
-use crate::{reactor::Reactor, server::FormatFeature, window_log_info};
+use crate::{
+    reactor::Reactor,
+    server::{FormatFeature, OnTypeFormatFeature, RangeFormatFeature},
+    window_log_info,
+};
 
 #[derive(Clone, Copy)]
 struct FormatState {
@@ -79,10 +85,107 @@ fn update_state(
     state
 }
 
+// 4 whitespaces per level when the client doesn't tell
+pub fn default_formatting_options() -> FormattingOptions {
+    FormattingOptions {
+        tab_size: 4,
+        insert_spaces: true,
+        ..Default::default()
+    }
+}
+
+// The preset is a column offset of the top level directive, it stays as whitespaces with tabs,
+// otherwise a tab stop would depend on the width of the preset.
+fn indentation(preset: usize, indent: usize, options: &FormattingOptions) -> String {
+    match options.insert_spaces {
+        true => " ".repeat(preset + indent * options.tab_size as usize),
+        false => " ".repeat(preset) + &"\t".repeat(indent),
+    }
+}
+
 pub fn formatting_capability() -> OneOf<bool, DocumentFormattingOptions> {
     OneOf::Left(true)
 }
 
+pub fn range_formatting_capability() -> OneOf<bool, DocumentRangeFormattingOptions> {
+    OneOf::Left(true)
+}
+
+// the end of a tag, e.g. "</#if>"
+pub fn on_type_formatting_capability() -> DocumentOnTypeFormattingOptions {
+    DocumentOnTypeFormattingOptions {
+        first_trigger_character: ">".to_owned(),
+        more_trigger_character: None,
+    }
+}
+
+fn format_source(reactor: &Reactor, options: &FormattingOptions) -> Vec<String> {
+    let mut state = FormatState {
+        preset: None,
+        indent: 0,
+        has_directive: false,
+    };
+    let mut formatted = vec![];
+    reactor.get_document().enumerate_lines(|index, line| {
+        state = update_state(reactor, index, line, state);
+        let preset = state.preset.unwrap_or_default();
+        if state.has_directive {
+            formatted.push(indentation(preset, state.indent, options) + line.trim());
+        } else {
+            formatted.push(line.to_owned());
+        }
+        state = reset_state(state);
+    });
+    formatted
+}
+
+// only the lines which are changed by formatting get edited
+pub fn format_edits(reactor: &Reactor, options: &FormattingOptions) -> Vec<TextEdit> {
+    let doc = reactor.get_document();
+    format_source(reactor, options)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, formatted)| {
+            let line = doc.get_line_text(index);
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line == formatted {
+                return None;
+            }
+            // the text of the client still starts with the BOM, the edit keeps it
+            let range = Range {
+                start: Position {
+                    line: index as u32,
+                    character: doc.byte_column_to_character(index, 0),
+                },
+                end: Position {
+                    line: index as u32,
+                    character: doc.byte_column_to_character(index, line.len()),
+                },
+            };
+            Some(TextEdit::new(range, formatted))
+        })
+        .collect()
+}
+
+// The indentation state is seeded from the top of the document rather than from the
+// selection, so a range starting in the middle of a block gets the same indentation
+// as the whole document formatting.
+pub fn format_range_edits(
+    reactor: &Reactor,
+    range: &Range,
+    options: &FormattingOptions,
+) -> Vec<TextEdit> {
+    // a selection ending at the start of a line doesn't select that line
+    let end_line = match range.end.character == 0 && range.end.line > range.start.line {
+        true => range.end.line - 1,
+        false => range.end.line,
+    };
+    format_edits(reactor, options)
+        .into_iter()
+        .filter(|edit| range.start.line <= edit.range.start.line && edit.range.end.line <= end_line)
+        .collect()
+}
+
 impl FormatFeature for Reactor {
     async fn on_formatting(
         &self,
@@ -90,40 +193,199 @@ impl FormatFeature for Reactor {
     ) -> JsonRpcResult<Option<Vec<TextEdit>>> {
         let uri = params.text_document.uri;
         window_log_info!(format!("on_formatting: {}", uri.to_string()));
-        let mut state = FormatState {
-            preset: None,
-            indent: 0,
-            has_directive: false,
+        Ok(Some(format_edits(self, &params.options)))
+    }
+}
+
+impl RangeFormatFeature for Reactor {
+    async fn on_range_formatting(
+        &self,
+        params: DocumentRangeFormattingParams,
+    ) -> JsonRpcResult<Option<Vec<TextEdit>>> {
+        let uri = params.text_document.uri;
+        window_log_info!(format!("on_range_formatting: {}", uri.to_string()));
+        Ok(Some(format_range_edits(
+            self,
+            &params.range,
+            &params.options,
+        )))
+    }
+}
+
+impl OnTypeFormatFeature for Reactor {
+    async fn on_type_formatting(
+        &self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> JsonRpcResult<Option<Vec<TextEdit>>> {
+        // A close tag isn't within the "*_clause" of its opener, so it is dedented by the
+        // same state machine as the whole document formatting.
+        let line = params.text_document_position.position.line;
+        let range = Range::new(Position::new(line, 0), Position::new(line, 0));
+        let edits = format_range_edits(self, &range, &params.options);
+        Ok((!edits.is_empty()).then_some(edits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tower_lsp_server::ls_types::{
+        DocumentOnTypeFormattingParams, DocumentRangeFormattingParams, FormattingOptions, Position,
+        Range, TextDocumentIdentifier, TextDocumentPositionParams, Uri,
+    };
+
+    use crate::{
+        format::{default_formatting_options, format_edits},
+        reactor::Reactor,
+        server::{OnTypeFormatFeature, RangeFormatFeature},
+    };
+
+    fn format_with(source: &str, options: &FormattingOptions) -> String {
+        let uri = Uri::from_str("file:///tmp/format.ftl").unwrap();
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let mut lines: Vec<String> = source.split('\n').map(str::to_owned).collect();
+        for edit in format_edits(&reactor, options) {
+            // the UTF-16 columns, an edit of the first line starts after the BOM
+            let line = &mut lines[edit.range.start.line as usize];
+            let kept: Vec<u16> = line
+                .encode_utf16()
+                .take(edit.range.start.character as usize)
+                .collect();
+            *line = String::from_utf16(&kept).unwrap() + &edit.new_text;
+        }
+        lines.join("\n")
+    }
+
+    fn format_once(source: &str) -> String {
+        format_with(source, &default_formatting_options())
+    }
+
+    #[test]
+    fn test_format_idempotent() {
+        let fixtures = [
+            "<#if x>\nfoo\n<#if y>\n    bar\n</#if>\n</#if>\n",
+            "  <#if x>\n<#list xs as x>\n${x}\n</#list>\n</#if>\n<#assign y = 1>\n",
+            "    <#assign y = 1>\n<#if x>\n  <#assign z = 2>\n</#if>\n",
+            "<#macro foo>\n<#if x>\n<#else>\n</#if>\n</#macro>\n  <#assign a = 1>\n<#assign b = 2>\n",
+            "<#-- <#if x> -->\n  <#if y>\n<#-- comment -->\n  </#if>\n",
+            "\t<#if x>\n\t\t<#if y>\n</#if>\n\t</#if>\n",
+            "<#if x><#if y>\n</#if>\n</#if>\n    <#if z>\n</#if>\n",
+            "  <#assign\n b = 2>\n<#switch s>\n<#case 1>\n<#break>\n</#switch>\n",
+        ];
+        for source in fixtures {
+            let formatted = format_once(source);
+            assert_eq!(format_once(&formatted), formatted, "source: {:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_format_keeps_bom() {
+        assert_eq!(
+            format_once("\u{feff}<#if x>  \n<#if y>\n</#if>\n</#if>\n"),
+            "\u{feff}<#if x>\n    <#if y>\n    </#if>\n</#if>\n"
+        );
+        let uri = Uri::from_str("file:///tmp/format.ftl").unwrap();
+        let reactor = Reactor::new(
+            &uri,
+            "\u{feff}<#if x>  \n</#if>\n",
+            0,
+            Default::default(),
+            Default::default(),
+        );
+        let edits = format_edits(&reactor, &default_formatting_options());
+        assert_eq!(
+            edits[0].range,
+            Range::new(Position::new(0, 1), Position::new(0, 10))
+        );
+        assert_eq!(edits[0].new_text, "<#if x>");
+    }
+
+    #[test]
+    fn test_format_edits_changed_lines_only() {
+        let uri = Uri::from_str("file:///tmp/format.ftl").unwrap();
+        let source = "<#if x>\nfoo\n<#if y>\n    bar\n</#if>\n</#if>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let edits = format_edits(&reactor, &default_formatting_options());
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[0].range.start.line, 2);
+        assert_eq!(edits[0].range.end.character, 7);
+        assert_eq!(edits[0].new_text, "    <#if y>");
+        assert_eq!(edits[1].range.start.line, 4);
+        assert_eq!(edits[1].new_text, "    </#if>");
+    }
+
+    #[test]
+    fn test_format_indent_options() {
+        let source = "  <#list xs as x>\n<#if x>\n</#if>\n</#list>\n";
+        let two_spaces = FormattingOptions {
+            tab_size: 2,
+            insert_spaces: true,
+            ..Default::default()
         };
-        let mut formatted = String::from("");
-        let mut last_length = 0;
-        let line_count = self.get_document().line_count();
-        self.get_document().enumerate_lines(|index, line| {
-            last_length = line.len();
-            state = update_state(self, index, line, state);
-            let preset = state.preset.unwrap_or_default();
-            if state.has_directive {
-                // todo: make indent step become a configuration
-                // currently use 4 whitespaces as the indent step by default
-                formatted += &(" ".repeat(preset + state.indent * 4) + line.trim());
-            } else {
-                formatted += line;
-            }
-            if index < line_count - 1 {
-                formatted += "\n";
-            }
-            state = reset_state(state);
-        });
-        let range = Range {
-            start: Position {
-                line: 0,
-                character: 0,
-            },
-            end: Position {
-                line: line_count as u32,
-                character: last_length as u32,
-            },
+        assert_eq!(
+            format_with(source, &two_spaces),
+            "  <#list xs as x>\n    <#if x>\n    </#if>\n  </#list>\n"
+        );
+        let tabs = FormattingOptions {
+            tab_size: 4,
+            insert_spaces: false,
+            ..Default::default()
+        };
+        // the preset of the top level directive stays as whitespaces
+        assert_eq!(
+            format_with(source, &tabs),
+            "  <#list xs as x>\n  \t<#if x>\n  \t</#if>\n  </#list>\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_range_formatting() {
+        let uri = Uri::from_str("file:///tmp/format.ftl").unwrap();
+        let source = "<#if x>\n<#if y>\n<#list xs as x>\n</#list>\n</#if>\n</#if>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        // the selection starts in the middle of the outer <#if>
+        let edits = reactor
+            .on_range_formatting(DocumentRangeFormattingParams {
+                text_document: TextDocumentIdentifier { uri },
+                range: Range::new(Position::new(2, 3), Position::new(4, 0)),
+                options: default_formatting_options(),
+                work_done_progress_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[0].range.start.line, 2);
+        assert_eq!(edits[0].new_text, "        <#list xs as x>");
+        assert_eq!(edits[1].range.start.line, 3);
+        assert_eq!(edits[1].new_text, "        </#list>");
+    }
+
+    #[tokio::test]
+    async fn test_on_type_formatting() {
+        let uri = Uri::from_str("file:///tmp/format.ftl").unwrap();
+        let source = "<#list xs as x>\n<#if x>\n${x}\n        </#if>\n</#list>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let on_type = |line, character| {
+            reactor.on_type_formatting(DocumentOnTypeFormattingParams {
+                text_document_position: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    position: Position::new(line, character),
+                },
+                ch: ">".to_owned(),
+                options: default_formatting_options(),
+            })
         };
-        Ok(Some(vec![TextEdit::new(range, formatted)]))
+        // the close tag is dedented to the depth of its opener
+        let edits = on_type(3, 14).await.unwrap().unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(
+            edits[0].range,
+            Range::new(Position::new(3, 0), Position::new(3, 14))
+        );
+        assert_eq!(edits[0].new_text, "    </#if>");
+        // the other lines are left to the whole document formatting
+        assert_eq!(on_type(4, 8).await.unwrap(), None);
     }
 }
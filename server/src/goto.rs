@@ -2,29 +2,89 @@
 // Licensed under the BSD 3-Clause License.
 // SPDX-License-Identifier: BSD-3-Clause
 
-use std::str::FromStr;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    str::FromStr,
+};
 
 use tower_lsp_server::{
     jsonrpc::Result as JsonRpcResult,
     ls_types::{
-        DefinitionOptions, GotoDefinitionParams, GotoDefinitionResponse, Location, OneOf, Range,
+        DefinitionOptions, GotoDefinitionParams, GotoDefinitionResponse, Location, OneOf, Position,
+        Range, Uri,
     },
 };
 use tree_sitter_freemarker::grammar::Rule;
 
-use crate::{reactor::Reactor, server::GotoFeature, utils};
+use crate::{
+    analysis::{Analysis, Symbol},
+    reactor::Reactor,
+    server::GotoFeature,
+    setting::find_format_setting,
+    utils,
+    workspace::Workspace,
+};
 
 pub fn definition_capability() -> OneOf<bool, DefinitionOptions> {
     OneOf::Left(true)
 }
 
+// the macro defined by the template, and the templates it imports to look into next
+fn macro_definition_or_imports(analysis: &Analysis, name: &str) -> (Option<Symbol>, Vec<Uri>) {
+    let mut imports: Vec<Uri> = analysis.get_valid_imports().cloned().collect();
+    imports.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    (analysis.find_macro_definition(name), imports)
+}
+
+impl Workspace {
+    // "<@lib.foo" jumps to the definition of "foo", which may be in a template imported by "lib",
+    // the imported templates are parsed by the import cache once
+    pub(crate) async fn goto_imported_macro(
+        &self,
+        reactors: &HashMap<Uri, Reactor>,
+        reactor: &Reactor,
+        position: &Position,
+    ) -> Option<Location> {
+        let analysis = reactor.get_analysis();
+        let call = analysis
+            .get_macro_calls()
+            .iter()
+            .find(|call| call.range.start <= *position && *position <= call.range.end)?;
+        let member = call.member.as_ref()?;
+        let import_uri = analysis.get_import_alias(&call.namespace)?;
+        // imports may well be cyclic
+        let mut visited = HashSet::new();
+        let mut pending = VecDeque::from([import_uri.clone()]);
+        while let Some(uri) = pending.pop_front() {
+            if !visited.insert(uri.clone()) {
+                continue;
+            }
+            let (definition, imports) = match reactors.get(&uri) {
+                Some(imported) => macro_definition_or_imports(imported.get_analysis(), member),
+                None => match self.imports.get(&uri).await {
+                    Some(imported) => macro_definition_or_imports(&imported, member),
+                    None => continue,
+                },
+            };
+            if let Some(definition) = definition {
+                return Some(Location::new(uri, definition.range));
+            }
+            pending.extend(imports);
+        }
+        // the top of the imported template is still closer than the import
+        Some(Location::new(import_uri.clone(), Range::default()))
+    }
+}
+
 impl GotoFeature for Reactor {
     async fn on_goto_definition(
         &self,
         params: GotoDefinitionParams,
     ) -> JsonRpcResult<Option<GotoDefinitionResponse>> {
-        let point =
-            utils::lsp_position_to_parser_point(&params.text_document_position_params.position);
+        let point = utils::lsp_position_to_parser_point(
+            self.get_document(),
+            &params.text_document_position_params.position,
+        );
         if let Some(node) = self.get_parser().get_node_at_point(point)
             && let Ok(rule) = Rule::from_str(node.kind())
         {
@@ -57,9 +117,151 @@ impl GotoFeature for Reactor {
                     }
                     Ok(None)
                 }
+                Rule::BuiltinName => {
+                    let doc = self.get_document();
+                    let builtin_name = doc.get_ranged_text(node.start_byte()..node.end_byte());
+                    // `?string` without a format argument follows the format settings
+                    let has_argument = doc
+                        .rope
+                        .byte_slice(node.end_byte()..)
+                        .chars()
+                        .find(|c| !c.is_whitespace())
+                        == Some('(');
+                    if builtin_name != "string" || has_argument {
+                        return Ok(None);
+                    }
+                    let position = params.text_document_position_params.position;
+                    Ok(
+                        find_format_setting(self.get_analysis().get_settings(), &position).map(
+                            |setting| {
+                                GotoDefinitionResponse::Scalar(Location {
+                                    uri: doc.uri(),
+                                    range: setting.range,
+                                })
+                            },
+                        ),
+                    )
+                }
                 _ => Ok(None),
             };
         }
         Ok(None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tower_lsp_server::ls_types::{
+        DidOpenTextDocumentParams, GotoDefinitionParams, GotoDefinitionResponse, Location,
+        Position, Range, TextDocumentIdentifier, TextDocumentItem, TextDocumentPositionParams, Uri,
+    };
+
+    use crate::{reactor::Reactor, server::GotoFeature, utils, workspace::Workspace};
+
+    async fn goto(reactor: &Reactor, position: Position) -> Option<GotoDefinitionResponse> {
+        reactor
+            .on_goto_definition(GotoDefinitionParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: reactor.get_document().uri(),
+                    },
+                    position,
+                },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_goto_format_setting() {
+        let uri = Uri::from_str("file:///tmp/goto.ftl").unwrap();
+        let source = "${a?string}\n<#setting locale=\"en_US\">\n<#setting number_format=\"0.##\">\n${x?string}\n${y?upper_case}\n${z?string(\"0.0\")}\n${z?string (\"0.0\")}\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let Some(GotoDefinitionResponse::Scalar(location)) =
+            goto(&reactor, Position::new(3, 6)).await
+        else {
+            panic!("expect the location of the setting");
+        };
+        assert_eq!(location.range.start, Position::new(2, 0));
+        // no setting precedes it
+        assert!(goto(&reactor, Position::new(0, 6)).await.is_none());
+        // not setting dependent
+        assert!(goto(&reactor, Position::new(4, 6)).await.is_none());
+        // the format is given, with or without a space before it
+        assert!(goto(&reactor, Position::new(5, 6)).await.is_none());
+        assert!(goto(&reactor, Position::new(6, 6)).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_goto_chained_import() {
+        let source = "<#import \"lib.ftl\" as lib>\n<@lib.foo/>\n<@lib.bar/>\n<@lib.baz/>\n";
+        let dir = utils::fixture_dir(
+            "goto_chained_import",
+            &[
+                (
+                    "lib.ftl",
+                    "<#import \"deep.ftl\" as deep>\n<#import \"main.ftl\" as main>\n<#macro bar>\n</#macro>\n",
+                ),
+                ("deep.ftl", "<#-- deep -->\n<#macro foo>\n</#macro>\n"),
+                ("main.ftl", source),
+            ],
+        );
+        let uri = Uri::from_file_path(dir.join("main.ftl")).unwrap();
+        let workspace = Workspace::new();
+        workspace
+            .on_did_open(&DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: uri.clone(),
+                    language_id: "ftl".to_owned(),
+                    version: 0,
+                    text: source.to_owned(),
+                },
+            })
+            .await;
+        let goto = async |position: Position| {
+            workspace
+                .on_goto_definition(GotoDefinitionParams {
+                    text_document_position_params: TextDocumentPositionParams {
+                        text_document: TextDocumentIdentifier { uri: uri.clone() },
+                        position,
+                    },
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                })
+                .await
+                .unwrap()
+        };
+        let location = |file: &str, line: u32, start: u32, end: u32| {
+            GotoDefinitionResponse::Scalar(Location::new(
+                Uri::from_file_path(dir.join(file)).unwrap(),
+                Range::new(Position::new(line, start), Position::new(line, end)),
+            ))
+        };
+
+        // two imports deep, also from the namespace
+        assert_eq!(
+            goto(Position::new(1, 7)).await,
+            Some(location("deep.ftl", 1, 8, 11))
+        );
+        assert_eq!(
+            goto(Position::new(1, 3)).await,
+            Some(location("deep.ftl", 1, 8, 11))
+        );
+        assert_eq!(
+            goto(Position::new(2, 7)).await,
+            Some(location("lib.ftl", 2, 8, 11))
+        );
+        // not defined anywhere, in spite of the cyclic import
+        assert_eq!(
+            goto(Position::new(3, 7)).await,
+            Some(location("lib.ftl", 0, 0, 0))
+        );
+        // the imported templates are parsed once, and kept for the next requests
+        let deep = Uri::from_file_path(dir.join("deep.ftl")).unwrap();
+        assert!(workspace.imports.contains(&deep).await);
+    }
+}
@@ -0,0 +1,324 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+
+use tower_lsp_server::{
+    jsonrpc::Result as JsonRpcResult,
+    ls_types::{
+        CallHierarchyIncomingCall, CallHierarchyIncomingCallsParams, CallHierarchyItem,
+        CallHierarchyOutgoingCall, CallHierarchyOutgoingCallsParams, CallHierarchyPrepareParams,
+        CallHierarchyServerCapability, Position, Range, SymbolKind, Uri,
+    },
+};
+use tree_sitter_freemarker::grammar::Rule;
+
+use crate::{
+    analysis::{Analysis, MacroCall, Symbol},
+    reactor::Reactor,
+    server::CallHierarchyFeature,
+    utils,
+    workspace::{GET_REACTOR_EXPECT, Workspace},
+};
+
+pub fn call_hierarchy_capability() -> CallHierarchyServerCapability {
+    CallHierarchyServerCapability::Simple(true)
+}
+
+fn template_name(uri: &Uri) -> String {
+    uri.to_file_path()
+        .and_then(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| uri.to_string())
+}
+
+fn macro_item(uri: &Uri, name: &str, definition: &Symbol) -> CallHierarchyItem {
+    CallHierarchyItem {
+        name: name.to_owned(),
+        kind: SymbolKind::FUNCTION,
+        tags: None,
+        detail: Some(template_name(uri)),
+        uri: uri.clone(),
+        range: definition.range,
+        selection_range: definition.range,
+        data: None,
+    }
+}
+
+// top-level macro calls are made by the template itself
+fn template_item(uri: &Uri) -> CallHierarchyItem {
+    CallHierarchyItem {
+        name: template_name(uri),
+        kind: SymbolKind::FILE,
+        tags: None,
+        detail: None,
+        uri: uri.clone(),
+        range: Range::default(),
+        selection_range: Range::default(),
+        data: None,
+    }
+}
+
+fn item_caller(item: &CallHierarchyItem) -> Option<String> {
+    match item.kind == SymbolKind::FILE {
+        true => None,
+        false => Some(item.name.clone()),
+    }
+}
+
+// resolves the (template, macro name) which a macro call targets
+fn resolve_macro_call(analysis: &Analysis, uri: &Uri, call: &MacroCall) -> Option<(Uri, String)> {
+    match &call.member {
+        Some(member) => analysis
+            .get_import_alias(&call.namespace)
+            .map(|import_uri| (import_uri.clone(), member.clone())),
+        None => Some((uri.clone(), call.namespace.clone())),
+    }
+}
+
+fn contains(range: &Range, position: &Position) -> bool {
+    range.start <= *position && *position <= range.end
+}
+
+// call ranges grouped by caller or callee
+type CallGroups<K> = Vec<(K, Vec<Range>)>;
+
+fn push_range<K: PartialEq>(groups: &mut CallGroups<K>, key: K, range: Range) {
+    match groups.iter_mut().find(|(k, _)| *k == key) {
+        Some((_, ranges)) => ranges.push(range),
+        None => groups.push((key, vec![range])),
+    }
+}
+
+impl Workspace {
+    async fn find_macro_item(
+        &self,
+        reactors: &HashMap<Uri, Reactor>,
+        uri: &Uri,
+        name: &str,
+    ) -> Option<CallHierarchyItem> {
+        let definition = match reactors.get(uri) {
+            Some(reactor) => reactor.get_analysis().find_macro_definition(name),
+            None => self.imports.get(uri).await?.find_macro_definition(name),
+        };
+        definition.map(|definition| macro_item(uri, name, &definition))
+    }
+}
+
+impl CallHierarchyFeature for Workspace {
+    async fn on_prepare_call_hierarchy(
+        &self,
+        params: CallHierarchyPrepareParams,
+    ) -> JsonRpcResult<Option<Vec<CallHierarchyItem>>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+        let read_guard = self.reactors.read().await;
+        let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
+        let point = utils::lsp_position_to_parser_point(reactor.get_document(), &position);
+        let Some(node) = reactor.get_parser().get_node_at_point(point) else {
+            return Ok(None);
+        };
+        let target = match Rule::from_str(node.kind()) {
+            Ok(Rule::MacroName) => {
+                let name = reactor
+                    .get_document()
+                    .get_ranged_text(node.start_byte()..node.end_byte());
+                Some((uri.clone(), name))
+            }
+            Ok(Rule::MacroNamespace | Rule::MacroSpecs | Rule::Identifier) => reactor
+                .get_analysis()
+                .get_macro_calls()
+                .iter()
+                .find(|call| contains(&call.range, &position))
+                .and_then(|call| resolve_macro_call(reactor.get_analysis(), uri, call)),
+            _ => None,
+        };
+        let Some((target_uri, name)) = target else {
+            return Ok(None);
+        };
+        Ok(self
+            .find_macro_item(&read_guard, &target_uri, &name)
+            .await
+            .map(|item| vec![item]))
+    }
+
+    async fn on_incoming_calls(
+        &self,
+        params: CallHierarchyIncomingCallsParams,
+    ) -> JsonRpcResult<Option<Vec<CallHierarchyIncomingCall>>> {
+        let item = params.item;
+        let read_guard = self.reactors.read().await;
+        // the defining template might not be opened
+        let cached: Option<Arc<Analysis>> = match read_guard.contains_key(&item.uri) {
+            true => None,
+            false => self.imports.get(&item.uri).await,
+        };
+        let sources: Vec<(&Uri, &Analysis)> = read_guard
+            .iter()
+            .map(|(uri, reactor)| (uri, reactor.get_analysis()))
+            .chain(cached.iter().map(|analysis| (&item.uri, analysis.as_ref())))
+            .collect();
+        let mut groups: CallGroups<(Uri, Option<String>)> = vec![];
+        for (uri, analysis) in &sources {
+            for call in analysis.get_macro_calls() {
+                if resolve_macro_call(analysis, uri, call)
+                    .is_some_and(|(target_uri, name)| target_uri == item.uri && name == item.name)
+                {
+                    push_range(
+                        &mut groups,
+                        ((*uri).clone(), call.caller.clone()),
+                        call.range,
+                    );
+                }
+            }
+        }
+        let mut incoming_calls = vec![];
+        for ((uri, caller), from_ranges) in groups {
+            let from = match caller {
+                Some(caller) => sources
+                    .iter()
+                    .find(|(source_uri, _)| **source_uri == uri)
+                    .and_then(|(_, analysis)| analysis.find_macro_definition(&caller))
+                    .map(|definition| macro_item(&uri, &caller, &definition)),
+                None => Some(template_item(&uri)),
+            };
+            if let Some(from) = from {
+                incoming_calls.push(CallHierarchyIncomingCall { from, from_ranges });
+            }
+        }
+        Ok(Some(incoming_calls))
+    }
+
+    async fn on_outgoing_calls(
+        &self,
+        params: CallHierarchyOutgoingCallsParams,
+    ) -> JsonRpcResult<Option<Vec<CallHierarchyOutgoingCall>>> {
+        let item = params.item;
+        let caller = item_caller(&item);
+        let read_guard = self.reactors.read().await;
+        let cached: Arc<Analysis>;
+        let analysis = match read_guard.get(&item.uri) {
+            Some(reactor) => reactor.get_analysis(),
+            None => match self.imports.get(&item.uri).await {
+                Some(analysis) => {
+                    cached = analysis;
+                    cached.as_ref()
+                }
+                None => return Ok(None),
+            },
+        };
+        let mut groups: CallGroups<(Uri, String)> = vec![];
+        for call in analysis.get_macro_calls() {
+            if call.caller == caller
+                && let Some(target) = resolve_macro_call(analysis, &item.uri, call)
+            {
+                push_range(&mut groups, target, call.range);
+            }
+        }
+        let mut outgoing_calls = vec![];
+        for ((uri, name), from_ranges) in groups {
+            // calls to undefined macros are reported by diagnostics
+            if let Some(to) = self.find_macro_item(&read_guard, &uri, &name).await {
+                outgoing_calls.push(CallHierarchyOutgoingCall { to, from_ranges });
+            }
+        }
+        Ok(Some(outgoing_calls))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::{
+        CallHierarchyIncomingCallsParams, CallHierarchyOutgoingCallsParams,
+        CallHierarchyPrepareParams, DidOpenTextDocumentParams, Position, SymbolKind,
+        TextDocumentIdentifier, TextDocumentItem, TextDocumentPositionParams, Uri,
+    };
+
+    use crate::{server::CallHierarchyFeature, utils, workspace::Workspace};
+
+    #[tokio::test]
+    async fn test_cross_file_call_hierarchy() {
+        let dir = utils::fixture_dir(
+            "call_hierarchy",
+            &[
+                ("lib.ftl", "<#macro bar>\n</#macro>\n"),
+                (
+                    "main.ftl",
+                    "<#import \"lib.ftl\" as lib>\n<#macro foo>\n<@lib.bar/>\n</#macro>\n<@lib.bar/>\n",
+                ),
+            ],
+        );
+        let main_uri = Uri::from_file_path(dir.join("main.ftl")).unwrap();
+        let lib_uri = Uri::from_file_path(dir.join("lib.ftl")).unwrap();
+        let workspace = Workspace::new();
+        workspace
+            .on_did_open(&DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: main_uri.clone(),
+                    language_id: "ftl".to_owned(),
+                    version: 0,
+                    text: std::fs::read_to_string(dir.join("main.ftl")).unwrap(),
+                },
+            })
+            .await;
+
+        // prepare on the definition of "foo"
+        let items = workspace
+            .on_prepare_call_hierarchy(CallHierarchyPrepareParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: main_uri.clone(),
+                    },
+                    position: Position::new(1, 9),
+                },
+                work_done_progress_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "foo");
+
+        // "foo" calls "bar" of the imported template
+        let outgoing = workspace
+            .on_outgoing_calls(CallHierarchyOutgoingCallsParams {
+                item: items[0].clone(),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(outgoing.len(), 1);
+        assert_eq!(outgoing[0].to.name, "bar");
+        assert_eq!(outgoing[0].to.uri, lib_uri);
+        assert_eq!(outgoing[0].to.selection_range.start, Position::new(0, 8));
+
+        // "bar" is called by "foo" and by the importing template itself
+        let incoming = workspace
+            .on_incoming_calls(CallHierarchyIncomingCallsParams {
+                item: outgoing[0].to.clone(),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(incoming.len(), 2);
+        assert!(incoming.iter().all(|call| call.from.uri == main_uri));
+        assert!(
+            incoming
+                .iter()
+                .any(|call| call.from.name == "foo" && call.from_ranges[0].start.line == 2)
+        );
+        assert!(
+            incoming
+                .iter()
+                .any(|call| call.from.kind == SymbolKind::FILE
+                    && call.from_ranges[0].start.line == 4)
+        );
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::str::FromStr;
+
+use tower_lsp_server::{
+    jsonrpc::Result as JsonRpcResult,
+    ls_types::{
+        DocumentHighlight, DocumentHighlightKind, DocumentHighlightOptions,
+        DocumentHighlightParams, OneOf, Range,
+    },
+};
+use tree_sitter::Node;
+use tree_sitter_freemarker::grammar::Rule;
+
+use crate::{doc::TextDocument, reactor::Reactor, server::DocumentHighlightFeature, utils};
+
+pub fn document_highlight_capability() -> OneOf<bool, DocumentHighlightOptions> {
+    OneOf::Left(true)
+}
+
+fn collect_identifiers(node: &Node, doc: &TextDocument, name: &str, ranges: &mut Vec<Range>) {
+    if let Ok(Rule::Identifier) = Rule::from_str(node.kind())
+        && doc.get_ranged_text(node.start_byte()..node.end_byte()) == name
+    {
+        ranges.push(utils::parser_node_to_document_range(doc, node));
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_identifiers(&child, doc, name, ranges);
+    }
+}
+
+impl DocumentHighlightFeature for Reactor {
+    async fn on_document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> JsonRpcResult<Option<Vec<DocumentHighlight>>> {
+        let position = params.text_document_position_params.position;
+        // macros are written by their definition and read by their calls
+        if let Some((_, calls)) = self.find_namespace_references(&position, false)
+            && let Some((_, ranges)) = self.find_namespace_references(&position, true)
+        {
+            return Ok(Some(
+                ranges
+                    .into_iter()
+                    .map(|range| DocumentHighlight {
+                        kind: match calls.contains(&range) {
+                            true => Some(DocumentHighlightKind::READ),
+                            false => Some(DocumentHighlightKind::WRITE),
+                        },
+                        range,
+                    })
+                    .collect(),
+            ));
+        }
+
+        let point = utils::lsp_position_to_parser_point(self.get_document(), &position);
+        let Some(mut node) = self.get_parser().get_node_at_point(point) else {
+            return Ok(None);
+        };
+        match Rule::from_str(node.kind()) {
+            Ok(Rule::Identifier) => {}
+            Ok(Rule::Variable) => match node.child_by_field_name("name") {
+                Some(name) => node = name,
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        }
+        let doc = self.get_document();
+        let name = doc.get_ranged_text(node.start_byte()..node.end_byte());
+        let mut ranges = vec![];
+        if let Some(ast) = self.get_parser().get_ast() {
+            collect_identifiers(&ast.root_node(), doc, &name, &mut ranges);
+        }
+        Ok(Some(
+            ranges
+                .into_iter()
+                .map(|range| DocumentHighlight {
+                    range,
+                    kind: Some(DocumentHighlightKind::TEXT),
+                })
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use tower_lsp_server::ls_types::{
+        DocumentHighlightKind, DocumentHighlightParams, Position, TextDocumentIdentifier,
+        TextDocumentPositionParams, Uri,
+    };
+
+    use crate::{
+        config::Config, doc::PositionEncodingKind, reactor::Reactor,
+        server::DocumentHighlightFeature,
+    };
+
+    async fn highlights(
+        reactor: &Reactor,
+        position: Position,
+    ) -> Vec<(Position, Option<DocumentHighlightKind>)> {
+        reactor
+            .on_document_highlight(DocumentHighlightParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: reactor.get_document().uri(),
+                    },
+                    position,
+                },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|highlight| (highlight.range.start, highlight.kind))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_document_highlight() {
+        let uri = Uri::from_str("file:///tmp/highlight.ftl").unwrap();
+        let source = "<#macro foo>\n${x}\n</#macro>\n<@foo/>\n${x + y}\n<#if x>${x}</#if>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+
+        let text = Some(DocumentHighlightKind::TEXT);
+        assert_eq!(
+            highlights(&reactor, Position::new(4, 2)).await,
+            vec![
+                (Position::new(1, 2), text),
+                (Position::new(4, 2), text),
+                (Position::new(5, 5), text),
+                (Position::new(5, 9), text),
+            ]
+        );
+        assert_eq!(
+            highlights(&reactor, Position::new(3, 3)).await,
+            vec![
+                (Position::new(0, 8), Some(DocumentHighlightKind::WRITE)),
+                (Position::new(3, 2), Some(DocumentHighlightKind::READ)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_macro_definition_and_calls() {
+        let uri = Uri::from_str("file:///tmp/highlight.ftl").unwrap();
+        let source = "<#macro foo>\nx\n</#macro>\n<@foo/>\n<p><@foo/></p>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+
+        let expected = vec![
+            (Position::new(0, 8), Some(DocumentHighlightKind::WRITE)),
+            (Position::new(3, 2), Some(DocumentHighlightKind::READ)),
+            (Position::new(4, 5), Some(DocumentHighlightKind::READ)),
+        ];
+        // from the definition or any of the calls
+        for position in [
+            Position::new(0, 9),
+            Position::new(3, 3),
+            Position::new(4, 6),
+        ] {
+            let mut kinds = highlights(&reactor, position).await;
+            kinds.sort_by_key(|(start, _)| *start);
+            assert_eq!(kinds, expected, "{:?}", position);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_non_ascii_line() {
+        let uri = Uri::from_str("file:///tmp/highlight.ftl").unwrap();
+        let source = "<p>日本${x}</p>${x}\n";
+        let text = Some(DocumentHighlightKind::TEXT);
+
+        // "日本" is 2 code units in UTF-16 but 6 bytes
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        assert_eq!(
+            highlights(&reactor, Position::new(0, 7)).await,
+            vec![(Position::new(0, 7), text), (Position::new(0, 15), text)]
+        );
+
+        let config = Config {
+            position_encoding: PositionEncodingKind::UTF8,
+            ..Default::default()
+        };
+        let reactor = Reactor::new(&uri, source, 0, Arc::new(config), Default::default());
+        assert_eq!(
+            highlights(&reactor, Position::new(0, 11)).await,
+            vec![(Position::new(0, 11), text), (Position::new(0, 19), text)]
+        );
+    }
+}
@@ -3,34 +3,57 @@
 // SPDX-License-Identifier: BSD-3-Clause
 
 use once_cell::sync::Lazy;
-use rust_embed::{Embed, EmbeddedFile};
+use rust_embed::Embed;
 use serde::Deserialize;
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{HashMap, VecDeque},
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex, RwLock},
+};
 use tower_lsp_server::{
     jsonrpc,
     ls_types::{
         Hover, HoverContents, HoverParams, HoverProviderCapability, MarkedString, MarkupContent,
-        MarkupKind,
+        MarkupKind, Position,
+    },
+};
+use tree_sitter::Node;
+use tree_sitter_freemarker::{
+    grammar::Rule,
+    href::{
+        DIRECTIVE_ASSIGN, DIRECTIVE_FTL, DIRECTIVE_FUNCTION, DIRECTIVE_IF, DIRECTIVE_IMPORT,
+        DIRECTIVE_LIST, DIRECTIVE_LIST_BREAK, DIRECTIVE_LIST_SEP, DIRECTIVE_LOCAL, DIRECTIVE_MACRO,
+        DIRECTIVE_REFERENCE, DIRECTIVE_SWITCH,
     },
 };
-use tree_sitter_freemarker::grammar::Rule;
 
 //use crate::symbol::MacroNamespace;
-use crate::{reactor::Reactor, server::HoverFeature, utils};
+use crate::{
+    assets, doc::TextDocument, escape::find_escape, reactor::Reactor, server::HoverFeature, utils,
+};
 
 #[derive(Embed)]
 #[folder = "assets/hover/"]
 #[include = "built-ins/*"]
 #[include = "types/*"]
+#[include = "settings/*"]
+#[include = "directives/*"]
 struct HoverAssetPath;
 
+const IDENTIFIER_PLACEHOLDER: &str = "{{identifier}}";
+const NODE_TEXT_PLACEHOLDER: &str = "{{node_text}}";
+const HREF_PLACEHOLDER: &str = "{{href}}";
+
 #[derive(Debug, Default, Deserialize)]
 struct HoverAssetItem {
-    // static markdown text
     identifier: String,
     category: String,
+    // the group of a built-in in the completion, e.g. "introspection" of "?is_string"
+    group: Option<String>,
+    // markdown text, "{{identifier}}" is replaced by the identifier when loaded and
+    // "{{node_text}}" by the text of the hovered node, "{{href}}" of a directive by its reference
     markdown: Option<String>,
-    // TODO: dynamic text "rendering"
 }
 
 impl HoverAssetItem {
@@ -50,88 +73,375 @@ impl HoverAssetItem {
     }
 
     #[tracing::instrument(skip_all)]
-    fn from_embed(file: EmbeddedFile) -> Option<HoverAssetItem> {
-        HoverAssetItem::from_bytes(file.data.as_ref())
+    fn from_asset(dir: Option<&Path>, file: &str) -> Option<HoverAssetItem> {
+        match assets::asset_bytes::<HoverAssetPath>(dir, "hover", file) {
+            Some(bytes) => HoverAssetItem::from_bytes(bytes.as_ref()),
+            None => {
+                tracing::error!("hover asset not found: {}", file);
+                None
+            }
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 struct HoverAsset {
     built_in: HashMap<String, Hover>,
+    built_in_groups: HashMap<String, String>,
     types: HashMap<String, Hover>,
-    // TODO: other hovers
+    settings: HashMap<String, Hover>,
+    directives: HashMap<String, Hover>,
+}
+
+fn directive_href(name: &str) -> &'static str {
+    match name {
+        "assign" => DIRECTIVE_ASSIGN,
+        "break" => DIRECTIVE_LIST_BREAK,
+        "case" | "default" | "on" | "switch" => DIRECTIVE_SWITCH,
+        "else" | "elseif" | "if" => DIRECTIVE_IF,
+        "ftl" => DIRECTIVE_FTL,
+        "function" | "return" => DIRECTIVE_FUNCTION,
+        "import" => DIRECTIVE_IMPORT,
+        "list" => DIRECTIVE_LIST,
+        "local" => DIRECTIVE_LOCAL,
+        "macro" => DIRECTIVE_MACRO,
+        "sep" => DIRECTIVE_LIST_SEP,
+        _ => DIRECTIVE_REFERENCE,
+    }
+}
+
+// "if" of `<#if`, `</#if>` and "break" of `<#break>`
+fn directive_name(rule: Rule) -> &'static str {
+    let kind: &'static str = rule.into();
+    kind.trim_end_matches("_begin")
+        .trim_end_matches("_close")
+        .trim_end_matches("_stmt")
 }
 
 fn insert_to_hover_map(item: HoverAssetItem, hovers: &mut HashMap<String, Hover>) {
+    let value = item
+        .markdown
+        .unwrap_or_default()
+        .replace(IDENTIFIER_PLACEHOLDER, &item.identifier);
     hovers.insert(
         item.identifier,
         Hover {
             contents: HoverContents::Markup(MarkupContent {
                 kind: MarkupKind::Markdown,
-                value: item.markdown.unwrap_or_default(),
+                value,
             }),
             range: None,
         },
     );
 }
 
+fn render_hover(hover: &Hover, node: &Node, node_text: &str, doc: &TextDocument) -> Hover {
+    let contents = match &hover.contents {
+        HoverContents::Markup(markup) if markup.value.contains(NODE_TEXT_PLACEHOLDER) => {
+            HoverContents::Markup(MarkupContent {
+                kind: markup.kind.clone(),
+                value: markup.value.replace(NODE_TEXT_PLACEHOLDER, node_text),
+            })
+        }
+        contents => contents.clone(),
+    };
+    Hover {
+        contents,
+        range: Some(utils::parser_node_to_document_range(doc, node)),
+    }
+}
+
 impl HoverAsset {
     fn new() -> Self {
+        HoverAsset::load(None)
+    }
+
+    fn load(dir: Option<&Path>) -> Self {
         let mut built_in: HashMap<String, Hover> = HashMap::new();
+        let mut built_in_groups: HashMap<String, String> = HashMap::new();
         let mut types: HashMap<String, Hover> = HashMap::new();
-        HoverAssetPath::iter().for_each(|file| {
-            if let Some(embedded_file) = HoverAssetPath::get(&file)
-                && let Some(item) = HoverAssetItem::from_embed(embedded_file)
-            {
-                match item.category.as_str() {
-                    "built-in" => insert_to_hover_map(item, &mut built_in),
-                    "types" => insert_to_hover_map(item, &mut types),
-                    _ => {}
+        let mut settings: HashMap<String, Hover> = HashMap::new();
+        let mut directives: HashMap<String, Hover> = HashMap::new();
+        assets::asset_files::<HoverAssetPath>(dir, "hover")
+            .iter()
+            .for_each(|file| {
+                if let Some(item) = HoverAssetItem::from_asset(dir, file) {
+                    match item.category.as_str() {
+                        "built-in" => {
+                            if let Some(group) = &item.group {
+                                built_in_groups.insert(item.identifier.clone(), group.clone());
+                            }
+                            insert_to_hover_map(item, &mut built_in)
+                        }
+                        "types" => insert_to_hover_map(item, &mut types),
+                        "setting" => insert_to_hover_map(item, &mut settings),
+                        "directive" => {
+                            let href = directive_href(&item.identifier);
+                            let item = HoverAssetItem {
+                                markdown: item
+                                    .markdown
+                                    .map(|markdown| markdown.replace(HREF_PLACEHOLDER, href)),
+                                ..item
+                            };
+                            insert_to_hover_map(item, &mut directives)
+                        }
+                        _ => {}
+                    }
                 }
-            }
-        });
-        HoverAsset { built_in, types }
+            });
+        HoverAsset {
+            built_in,
+            built_in_groups,
+            types,
+            settings,
+            directives,
+        }
     }
 }
 
-static STATIC_ASSETS: Lazy<HoverAsset> = Lazy::new(HoverAsset::new);
+// replaced as a whole by "freemarker/reloadAssets"
+static STATIC_ASSETS: Lazy<RwLock<Arc<HoverAsset>>> =
+    Lazy::new(|| RwLock::new(Arc::new(HoverAsset::new())));
+
+fn static_assets() -> Arc<HoverAsset> {
+    STATIC_ASSETS.read().unwrap().clone()
+}
+
+pub fn reload_assets(dir: Option<&Path>) {
+    *STATIC_ASSETS.write().unwrap() = Arc::new(HoverAsset::load(dir));
+}
+
+pub fn builtin_group(name: &str) -> Option<String> {
+    static_assets().built_in_groups.get(name).cloned()
+}
+
+// the markdown of the hover, reused as the documentation of the completion
+pub fn builtin_markdown(name: &str) -> Option<String> {
+    match &static_assets().built_in.get(name)?.contents {
+        HoverContents::Markup(markup) => Some(markup.value.clone()),
+        _ => None,
+    }
+}
 
 pub fn hover_capability() -> HoverProviderCapability {
     HoverProviderCapability::Simple(true)
 }
 
+const HOVER_CACHE_CAPACITY: usize = 16;
+
+// Hovers are pure functions of the node, so the last few of them are kept
+// until the document version changes
+// (document version, [(node id, hover)])
+type CachedHovers = (i32, VecDeque<(usize, Option<Hover>)>);
+
+#[derive(Debug, Default)]
+pub struct HoverCache {
+    entries: Mutex<CachedHovers>,
+}
+
+impl HoverCache {
+    pub fn get(&self, version: i32, node_id: usize) -> Option<Option<Hover>> {
+        let entries = self.entries.lock().unwrap();
+        if entries.0 != version {
+            return None;
+        }
+        entries
+            .1
+            .iter()
+            .find(|(id, _)| *id == node_id)
+            .map(|(_, hover)| hover.clone())
+    }
+
+    pub fn insert(&self, version: i32, node_id: usize, hover: Option<Hover>) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.0 != version {
+            *entries = (version, VecDeque::new());
+        }
+        entries.1.retain(|(id, _)| *id != node_id);
+        if entries.1.len() == HOVER_CACHE_CAPACITY {
+            entries.1.pop_front();
+        }
+        entries.1.push_back((node_id, hover));
+    }
+
+    // the assets are reloaded
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().1.clear();
+    }
+}
+
+// e.g. "Ok(Identifier) identifier 2..3" of "x" in "${x}"
+fn debug_hover(node: &Node, doc: &TextDocument) -> Hover {
+    Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::PlainText,
+            value: format!(
+                "{:?} {} {}..{}",
+                Rule::from_str(node.kind()),
+                node.kind(),
+                node.start_byte(),
+                node.end_byte()
+            ),
+        }),
+        range: Some(utils::parser_node_to_document_range(doc, node)),
+    }
+}
+
 impl HoverFeature for Reactor {
     async fn on_hover(&self, params: HoverParams) -> jsonrpc::Result<Option<Hover>> {
-        let point =
-            utils::lsp_position_to_parser_point(&params.text_document_position_params.position);
-        if let Some(node) = self.get_parser().get_node_at_point(point)
-            && let Ok(rule) = Rule::from_str(node.kind())
-        {
+        let point = utils::lsp_position_to_parser_point(
+            self.get_document(),
+            &params.text_document_position_params.position,
+        );
+        if let Some(node) = self.get_parser().get_node_at_point(point) {
+            if self.get_config().debug_hover {
+                return Ok(Some(debug_hover(&node, self.get_document())));
+            }
+            if let Some(hover) = self.hover_setting(&params.text_document_position_params.position)
+            {
+                return Ok(hover);
+            }
+            let cache = self.get_hover_cache();
+            if let Some(hover) = cache.get(self.version, node.id()) {
+                return Ok(hover);
+            }
+            let hover = self.note_escaping(&node, self.hover_node(&node, &params)?);
+            cache.insert(self.version, node.id(), hover.clone());
+            return Ok(hover);
+        }
+        Ok(None)
+    }
+}
+
+impl Reactor {
+    // interpolations inside <#escape> are escaped implicitly
+    fn note_escaping(&self, node: &Node, hover: Option<Hover>) -> Option<Hover> {
+        let mut node_cursor = Some(*node);
+        while let Some(current) = node_cursor {
+            if let Ok(Rule::Interpolation) = Rule::from_str(current.kind()) {
+                break;
+            }
+            node_cursor = current.parent();
+        }
+        let Some(interpolation) = node_cursor else {
+            return hover;
+        };
+        let range = utils::parser_node_to_document_range(self.get_document(), &interpolation);
+        let Some(expression) = find_escape(self.get_analysis().get_escapes(), &range.start)
+            .and_then(|escape| escape.expression.as_ref())
+        else {
+            return hover;
+        };
+        let note = format!("Escaped as `{}` by the enclosing `<#escape>`", expression);
+        Some(match hover {
+            Some(Hover {
+                contents: HoverContents::Scalar(marked),
+                range,
+            }) => Hover {
+                contents: HoverContents::Array(vec![marked, MarkedString::String(note)]),
+                range,
+            },
+            Some(Hover {
+                contents: HoverContents::Array(mut marked),
+                range,
+            }) => {
+                marked.push(MarkedString::String(note));
+                Hover {
+                    contents: HoverContents::Array(marked),
+                    range,
+                }
+            }
+            Some(Hover {
+                contents: HoverContents::Markup(mut markup),
+                range,
+            }) => {
+                markup.value.push_str(&format!("\n\n{}", note));
+                Hover {
+                    contents: HoverContents::Markup(markup),
+                    range,
+                }
+            }
+            None => Hover {
+                contents: HoverContents::Scalar(MarkedString::String(note)),
+                range: Some(range),
+            },
+        })
+    }
+
+    // settings are not parsed by the grammar, several of them may share an ERROR node so
+    // they are looked up by the position rather than cached by the node
+    fn hover_setting(&self, position: &Position) -> Option<Option<Hover>> {
+        let setting = self.get_analysis().get_settings().iter().find(|setting| {
+            setting.name_range.start <= *position && *position <= setting.name_range.end
+        })?;
+        Some(
+            static_assets()
+                .settings
+                .get(&setting.name)
+                .map(|hover| Hover {
+                    contents: hover.contents.clone(),
+                    range: Some(setting.name_range),
+                }),
+        )
+    }
+
+    fn hover_node(&self, node: &Node, params: &HoverParams) -> jsonrpc::Result<Option<Hover>> {
+        if let Ok(rule) = Rule::from_str(node.kind()) {
             return match rule {
                 Rule::Number | Rule::StringLiteral | Rule::BooleanTrue | Rule::BooleanFalse => {
                     let rule_str = match matches!(rule, Rule::BooleanTrue | Rule::BooleanFalse) {
                         true => "boolean",
                         false => &rule.to_string(),
                     };
-                    if let Some(hover) = STATIC_ASSETS.types.get(rule_str) {
-                        return Ok(Some(Hover {
-                            contents: hover.contents.clone(),
-                            range: Some(utils::parser_node_to_document_range(&node)),
-                        }));
-                    }
-                    return Ok(None);
+                    let node_text = self
+                        .get_document()
+                        .get_ranged_text(node.start_byte()..node.end_byte());
+                    return Ok(static_assets()
+                        .types
+                        .get(rule_str)
+                        .map(|hover| render_hover(hover, node, &node_text, self.get_document())));
                 }
                 Rule::BuiltinName => {
                     let node_text = self
                         .get_document()
                         .get_ranged_text(node.start_byte()..node.end_byte());
-                    if let Some(hover) = STATIC_ASSETS.built_in.get(&node_text) {
-                        return Ok(Some(Hover {
-                            contents: hover.contents.clone(),
-                            range: Some(utils::parser_node_to_document_range(&node)),
-                        }));
-                    }
-                    return Ok(None);
+                    return Ok(static_assets()
+                        .built_in
+                        .get(&node_text)
+                        .map(|hover| render_hover(hover, node, &node_text, self.get_document())));
+                }
+                Rule::AssignBegin
+                | Rule::AssignClose
+                | Rule::BreakStmt
+                | Rule::CaseBegin
+                | Rule::DefaultBegin
+                | Rule::ElseBegin
+                | Rule::ElseifBegin
+                | Rule::FtlBegin
+                | Rule::FunctionBegin
+                | Rule::FunctionClose
+                | Rule::IfBegin
+                | Rule::IfClose
+                | Rule::ImportBegin
+                | Rule::ListBegin
+                | Rule::ListClose
+                | Rule::LocalBegin
+                | Rule::LocalClose
+                | Rule::MacroBegin
+                | Rule::MacroClose
+                | Rule::OnBegin
+                | Rule::ReturnBegin
+                | Rule::SepBegin
+                | Rule::SepClose
+                | Rule::SwitchBegin
+                | Rule::SwitchClose => {
+                    let node_text = self
+                        .get_document()
+                        .get_ranged_text(node.start_byte()..node.end_byte());
+                    return Ok(static_assets()
+                        .directives
+                        .get(directive_name(rule))
+                        .map(|hover| render_hover(hover, node, &node_text, self.get_document())));
                 }
                 Rule::MacroNamespace => {
                     let node_text = self
@@ -147,12 +457,44 @@ impl HoverFeature for Reactor {
                                 contents: HoverContents::Scalar(MarkedString::LanguageString(
                                     utils::ftl_to_rust(definition_line.trim()),
                                 )),
-                                range: Some(utils::parser_node_to_document_range(&node)),
+                                range: Some(utils::parser_node_to_document_range(
+                                    self.get_document(),
+                                    node,
+                                )),
                             }));
                         }
                         _ => Ok(None),
                     }
                 }
+                Rule::Identifier => {
+                    if node
+                        .parent()
+                        .is_none_or(|parent| parent.kind() != Rule::Variable.to_string())
+                    {
+                        return Ok(None);
+                    }
+                    let node_text = self
+                        .get_document()
+                        .get_ranged_text(node.start_byte()..node.end_byte());
+                    let position = params.text_document_position_params.position;
+                    match self.get_analysis().find_assignment(&node_text, &position) {
+                        Some(sym) => {
+                            let assignment_line = self
+                                .get_document()
+                                .get_line_text(sym.range.start.line as usize);
+                            Ok(Some(Hover {
+                                contents: HoverContents::Scalar(MarkedString::LanguageString(
+                                    utils::ftl_to_rust(assignment_line.trim()),
+                                )),
+                                range: Some(utils::parser_node_to_document_range(
+                                    self.get_document(),
+                                    node,
+                                )),
+                            }))
+                        }
+                        None => Ok(None),
+                    }
+                }
                 _ => Ok(None),
             };
         }
@@ -162,7 +504,165 @@ impl HoverFeature for Reactor {
 
 #[cfg(test)]
 mod tests {
-    use crate::hover::{HoverAsset, HoverAssetItem, HoverAssetPath};
+    use std::{str::FromStr, sync::Arc};
+
+    use tower_lsp_server::ls_types::{
+        Hover, HoverContents, HoverParams, MarkedString, MarkupKind, Position, Range,
+        TextDocumentIdentifier, TextDocumentPositionParams, Uri,
+    };
+
+    use crate::{
+        config::Config,
+        hover::{HoverAsset, HoverAssetItem, builtin_markdown, reload_assets, static_assets},
+        reactor::Reactor,
+        server::HoverFeature,
+        setting::SETTING_NAMES,
+        utils,
+    };
+    use tree_sitter_freemarker::href::{DIRECTIVE_IF, DIRECTIVE_LIST};
+
+    async fn hover_at(reactor: &Reactor, position: Position) -> Option<Hover> {
+        reactor
+            .on_hover(HoverParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: reactor.get_document().uri(),
+                    },
+                    position,
+                },
+                work_done_progress_params: Default::default(),
+            })
+            .await
+            .unwrap()
+    }
+
+    // the code of an assignment, or the markdown of a directive, a built-in or a setting
+    async fn hover_text(reactor: &Reactor, position: Position) -> Option<String> {
+        match hover_at(reactor, position).await?.contents {
+            HoverContents::Scalar(MarkedString::LanguageString(s)) => Some(s.value),
+            HoverContents::Markup(markup) => Some(markup.value),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hover_cache() {
+        let uri = Uri::from_str("file:///tmp/hover.ftl").unwrap();
+        let source = "<#assign x = 1>\n${x}\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let position = Position::new(1, 2);
+        assert_eq!(
+            hover_text(&reactor, position).await.as_deref(),
+            Some("assign x = 1")
+        );
+        let node = reactor
+            .get_parser()
+            .get_node_at_point(utils::lsp_position_to_parser_point(
+                reactor.get_document(),
+                &position,
+            ))
+            .unwrap();
+        let cache = reactor.get_hover_cache();
+        assert!(cache.get(0, node.id()).is_some());
+        // the second hover is served by the cache
+        cache.insert(0, node.id(), None);
+        assert_eq!(hover_text(&reactor, position).await, None);
+        // another version is not
+        assert!(cache.get(1, node.id()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_debug_hover() {
+        let uri = Uri::from_str("file:///tmp/hover.ftl").unwrap();
+        let source = "<#assign x = 1>\n${x}\n";
+        let config = Config {
+            debug_hover: true,
+            ..Default::default()
+        };
+        let reactor = Reactor::new(&uri, source, 0, Arc::new(config), Default::default());
+        let hover = hover_at(&reactor, Position::new(1, 2)).await.unwrap();
+        let HoverContents::Markup(markup) = hover.contents else {
+            panic!("expect plain text");
+        };
+        assert_eq!(markup.kind, MarkupKind::PlainText);
+        assert_eq!(markup.value, "Ok(Identifier) identifier 18..19");
+        assert_eq!(
+            hover.range,
+            Some(Range::new(Position::new(1, 2), Position::new(1, 3)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hover_nearest_assignment() {
+        let uri = Uri::from_str("file:///tmp/hover.ftl").unwrap();
+        let source = "${x}\n<#assign x = 1>\n${x}\n<#assign x = 2>\n${x}\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        // not assigned yet
+        assert_eq!(hover_text(&reactor, Position::new(0, 2)).await, None);
+        assert_eq!(
+            hover_text(&reactor, Position::new(2, 2)).await.as_deref(),
+            Some("assign x = 1")
+        );
+        assert_eq!(
+            hover_text(&reactor, Position::new(4, 2)).await.as_deref(),
+            Some("assign x = 2")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hover_local_assignment() {
+        let uri = Uri::from_str("file:///tmp/hover.ftl").unwrap();
+        let source =
+            "<#macro m>\n<#local y = 2>\n${y}\n</#macro>\n<#macro n>\n${y}\n</#macro>\n${y}\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        assert_eq!(
+            hover_text(&reactor, Position::new(2, 2)).await.as_deref(),
+            Some("local y = 2")
+        );
+        // out of the macro which declares it
+        assert_eq!(hover_text(&reactor, Position::new(5, 2)).await, None);
+        assert_eq!(hover_text(&reactor, Position::new(7, 2)).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_hover_escaped_interpolation() {
+        let uri = Uri::from_str("file:///tmp/hover_escape.ftl").unwrap();
+        let source = "<#escape x as x?html>\n<#assign y = 1>\n${y}\n<#escape x as x?url>\n${z}\n</#escape>\n<#noescape>${z}</#noescape>\n</#escape>\n${z}\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let hover_contents = async |position| {
+            hover_at(&reactor, position)
+                .await
+                .map(|hover| hover.contents)
+        };
+        // the assignment comes first, then the escaping
+        let Some(HoverContents::Array(marked)) = hover_contents(Position::new(2, 2)).await else {
+            panic!("expect the assignment and the escaping");
+        };
+        assert_eq!(
+            marked[1],
+            MarkedString::String("Escaped as `x?html` by the enclosing `<#escape>`".to_owned())
+        );
+        // the innermost block wins
+        let Some(HoverContents::Scalar(MarkedString::String(note))) =
+            hover_contents(Position::new(4, 0)).await
+        else {
+            panic!("expect the escaping");
+        };
+        assert!(note.contains("x?url"));
+        // no escaping inside <#noescape> or after the block
+        assert_eq!(hover_contents(Position::new(6, 13)).await, None);
+        assert_eq!(hover_contents(Position::new(8, 2)).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_hover_node_text_placeholder() {
+        let uri = Uri::from_str("file:///tmp/hover_number.ftl").unwrap();
+        let source = "${42.5}\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let markdown = hover_text(&reactor, Position::new(0, 3)).await.unwrap();
+        assert!(markdown.contains("# Number `42.5`"));
+        assert!(!markdown.contains("{{"));
+    }
 
     #[test]
     fn test_asset_builtin_from_str() {
@@ -182,8 +682,7 @@ markdown = """baz"""
 
     #[test]
     fn test_asset_builtin_from_file() {
-        let embedded_file = HoverAssetPath::get("built-ins/c.toml").expect("must success");
-        if let Some(item) = HoverAssetItem::from_embed(embedded_file) {
+        if let Some(item) = HoverAssetItem::from_asset(None, "built-ins/c.toml") {
             assert_eq!(item.identifier, "c".to_string());
             assert_eq!(item.category, "built-in".to_string());
             assert!(item.markdown.is_some());
@@ -195,4 +694,92 @@ markdown = """baz"""
         let asset = HoverAsset::new();
         assert!(!asset.built_in.is_empty());
     }
+
+    #[test]
+    fn test_reload_assets() {
+        let dir = utils::fixture_dir(
+            "reload_assets",
+            &[(
+                "hover/built-ins/reload_probe.toml",
+                "identifier = \"reload_probe\"\ncategory = \"built-in\"\nmarkdown = \"# {{identifier}}\"\n",
+            )],
+        );
+        let before = static_assets();
+        assert!(builtin_markdown("reload_probe").is_none());
+        reload_assets(Some(&dir));
+        assert!(!Arc::ptr_eq(&before, &static_assets()));
+        assert_eq!(
+            builtin_markdown("reload_probe").as_deref(),
+            Some("# reload_probe")
+        );
+        // the embedded ones again
+        reload_assets(None);
+        assert!(builtin_markdown("reload_probe").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_hover_directive() {
+        let uri = Uri::from_str("file:///tmp/hover_directive.ftl").unwrap();
+        let source = "<#list xs as x>\n<#if x>${x}<#else>-</#if>\n</#list>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let markdown = async |position| hover_text(&reactor, position).await;
+        let list = markdown(Position::new(0, 3)).await.unwrap();
+        assert!(list.contains("# <#list>"));
+        assert!(list.contains(DIRECTIVE_LIST));
+        let if_close = markdown(Position::new(1, 21)).await.unwrap();
+        assert!(if_close.contains("# <#if>"));
+        assert!(if_close.contains(DIRECTIVE_IF));
+        let r#else = markdown(Position::new(1, 13)).await.unwrap();
+        assert!(r#else.contains("# <#else>"));
+        assert!(!r#else.contains("{{"));
+    }
+
+    #[test]
+    fn test_asset_settings() {
+        let asset = HoverAsset::new();
+        for name in SETTING_NAMES {
+            assert!(asset.settings.contains_key(*name), "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hover_setting() {
+        let uri = Uri::from_str("file:///tmp/hover.ftl").unwrap();
+        let source = "<#setting number_format=\"0.##\">\n<#setting foo=1>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let hover = hover_at(&reactor, Position::new(0, 12)).await.unwrap();
+        assert_eq!(
+            hover.range,
+            Some(Range::new(Position::new(0, 10), Position::new(0, 23)))
+        );
+        let HoverContents::Markup(markup) = hover.contents else {
+            panic!("expect markdown");
+        };
+        assert!(markup.value.starts_with("# number_format"));
+        // unknown settings have no hover
+        assert_eq!(hover_text(&reactor, Position::new(1, 11)).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_hover_settings_in_one_node() {
+        let uri = Uri::from_str("file:///tmp/hover.ftl").unwrap();
+        let source = "<#setting locale=\"en_US\">\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let node_at = |position| {
+            reactor
+                .get_parser()
+                .get_node_at_point(utils::lsp_position_to_parser_point(
+                    reactor.get_document(),
+                    &position,
+                ))
+                .map(|node| node.id())
+        };
+        // "setting" and "locale" are in the same ERROR node
+        let (keyword, name) = (Position::new(0, 3), Position::new(0, 11));
+        assert_eq!(node_at(keyword), node_at(name));
+        assert!(hover_at(&reactor, keyword).await.is_none());
+        // the hover of the keyword is not served for the name
+        let locale = hover_text(&reactor, name).await.unwrap();
+        assert!(locale.starts_with("# locale"));
+    }
 }
@@ -0,0 +1,809 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tower_lsp_server::{
+    jsonrpc,
+    ls_types::{Range, TextDocumentIdentifier, Uri},
+};
+use tree_sitter::Node;
+use tree_sitter_freemarker::grammar::Rule;
+
+use crate::{
+    analysis::{Analysis, analyze_text},
+    config::Config,
+    doc::TextDocument,
+    parser::TextParser,
+    progress::{ProgressSink, ProgressTracker},
+    server::ResolveImportFeature,
+    utils,
+    workspace::Workspace,
+};
+
+pub const RESOLVE_IMPORT: &str = "freemarker/resolveImport";
+
+pub const EAGER_INDEX_TOKEN: &str = "freemarker/eagerIndex";
+// a huge root (e.g. the home directory) must not keep the server busy
+const EAGER_INDEX_LIMIT: usize = 2000;
+const TEMPLATE_EXTENSIONS: [&str; 3] = ["ftl", "ftlh", "ftlx"];
+
+// What an import path resolves to on the filesystem
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportTarget {
+    pub canonical_path: PathBuf,
+    pub is_file: bool,
+    pub modified: Option<SystemTime>,
+}
+
+pub trait FileStat: Debug + Send + Sync {
+    fn stat(&self, path: &Path) -> io::Result<ImportTarget>;
+}
+
+#[derive(Debug)]
+struct DiskFileStat;
+
+impl FileStat for DiskFileStat {
+    fn stat(&self, path: &Path) -> io::Result<ImportTarget> {
+        let canonical_path = path.canonicalize()?;
+        let metadata = canonical_path.metadata()?;
+        Ok(ImportTarget {
+            canonical_path,
+            is_file: metadata.is_file(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+// An <#import> of a template read from the disk
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportEdge {
+    pub target: PathBuf,
+    // the quoted path in the importing template
+    pub range: Range,
+}
+
+// Import paths are resolved on every analysis (i.e. every keystroke), so the
+// results are kept until the watched files change
+#[derive(Debug)]
+pub struct ImportValidity {
+    stat: Box<dyn FileStat>,
+    targets: Mutex<HashMap<PathBuf, Option<ImportTarget>>>,
+    // the imports of each template, by its canonical path
+    edges: Mutex<HashMap<PathBuf, Vec<ImportEdge>>>,
+    // bumped whenever a watched file changes, the import checks of older ones are stale
+    generation: AtomicUsize,
+}
+
+// The verdict of an <#import> path, carried over to the next analysis of the template while
+// the edits leave its imports alone, see `Reactor::apply_content_change`
+#[derive(Clone, Debug)]
+pub struct ImportCheck {
+    pub target: Option<ImportTarget>,
+    pub refers_itself: bool,
+    // the imports leading back to the template
+    pub circular_chain: Option<Vec<(PathBuf, ImportEdge)>>,
+}
+
+impl Default for ImportValidity {
+    fn default() -> Self {
+        Self::with_stat(Box::new(DiskFileStat))
+    }
+}
+
+impl ImportValidity {
+    pub fn with_stat(stat: Box<dyn FileStat>) -> Self {
+        Self {
+            stat,
+            targets: Mutex::new(HashMap::new()),
+            edges: Mutex::new(HashMap::new()),
+            generation: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    pub fn resolve(&self, path: &Path) -> Option<ImportTarget> {
+        let mut targets = self.targets.lock().unwrap();
+        targets
+            .entry(path.to_path_buf())
+            .or_insert_with(|| self.stat.stat(path).ok())
+            .clone()
+    }
+
+    pub fn invalidate(&self, path: &Path) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        // a new file may complete the imports of any template
+        self.edges.lock().unwrap().clear();
+        let current = self.stat.stat(path).ok();
+        self.targets.lock().unwrap().retain(|requested, target| {
+            // a missing target has no canonical path to compare, e.g. "sub/../lib.ftl",
+            // any of them may be the new file
+            let affected = requested == path
+                || target
+                    .as_ref()
+                    .map_or(current.is_some(), |target| target.canonical_path == path);
+            // keep the entry if the file was not really modified
+            !affected
+                || target.as_ref().is_some_and(|target| {
+                    target.modified.is_some() && current.as_ref() == Some(target)
+                })
+        });
+    }
+}
+
+// The path an <#import> or <#include> refers to, relative to the importing template
+pub fn import_candidate(config: &Config, dir: &Path, path: &str) -> PathBuf {
+    let mapped = PathBuf::from(config.map_import_path(path));
+    match mapped.is_absolute() {
+        true => mapped,
+        false => dir.join(mapped),
+    }
+}
+
+impl ImportValidity {
+    pub fn resolve_import(&self, config: &Config, dir: &Path, path: &str) -> Option<ImportTarget> {
+        self.resolve(&import_candidate(config, dir, path))
+    }
+
+    // the target of the path, whether it is the template itself or leads back to it
+    pub fn check_import(&self, config: &Config, doc: &TextDocument, path: &str) -> ImportCheck {
+        let target = self.resolve_import(config, &doc.dir(), path);
+        let self_path = doc
+            .uri()
+            .to_file_path()
+            .and_then(|path| self.resolve(&path));
+        let mut check = ImportCheck {
+            target: target.clone(),
+            refers_itself: false,
+            circular_chain: None,
+        };
+        if let Some(target) = target.filter(|target| target.is_file)
+            && let Some(self_target) = &self_path
+        {
+            check.refers_itself = target.canonical_path == self_target.canonical_path;
+            if !check.refers_itself {
+                check.circular_chain = self.find_import_chain(
+                    config,
+                    &target.canonical_path,
+                    &self_target.canonical_path,
+                    config.max_import_depth(),
+                );
+            }
+        }
+        check
+    }
+
+    // the templates imported by a template, which is read until it changes on the disk
+    pub fn imports_of(&self, config: &Config, path: &Path) -> Vec<ImportEdge> {
+        if let Some(edges) = self.edges.lock().unwrap().get(path) {
+            return edges.clone();
+        }
+        let mut quoted_paths = vec![];
+        if let Ok(text) = std::fs::read_to_string(path)
+            && let Some(uri) = Uri::from_file_path(path)
+            && let Some(ast) = TextParser::new(&text).get_ast()
+        {
+            let doc =
+                TextDocument::new(&uri, &text).with_position_encoding(config.position_encoding);
+            collect_import_paths(&ast.root_node(), &doc, &mut quoted_paths);
+        }
+        let dir = path.parent().unwrap_or(path);
+        let edges: Vec<ImportEdge> = quoted_paths
+            .into_iter()
+            .filter_map(|(quoted, range)| {
+                self.resolve_import(config, dir, &quoted)
+                    .filter(|target| target.is_file)
+                    .map(|target| ImportEdge {
+                        target: target.canonical_path,
+                        range,
+                    })
+            })
+            .collect();
+        self.edges
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), edges.clone());
+        edges
+    }
+
+    // the imports leading from a template to another, following at most `max_depth` of them
+    pub fn find_import_chain(
+        &self,
+        config: &Config,
+        from: &Path,
+        to: &Path,
+        max_depth: usize,
+    ) -> Option<Vec<(PathBuf, ImportEdge)>> {
+        let mut visited = HashSet::new();
+        let mut chain = vec![];
+        self.search_import_chain(config, from, to, max_depth, &mut visited, &mut chain)
+            .then_some(chain)
+    }
+
+    fn search_import_chain(
+        &self,
+        config: &Config,
+        current: &Path,
+        to: &Path,
+        depth: usize,
+        visited: &mut HashSet<PathBuf>,
+        chain: &mut Vec<(PathBuf, ImportEdge)>,
+    ) -> bool {
+        if depth == 0 || !visited.insert(current.to_path_buf()) {
+            return false;
+        }
+        for edge in self.imports_of(config, current) {
+            let target = edge.target.clone();
+            chain.push((current.to_path_buf(), edge));
+            if target == to
+                || self.search_import_chain(config, &target, to, depth - 1, visited, chain)
+            {
+                return true;
+            }
+            chain.pop();
+        }
+        false
+    }
+}
+
+// the quoted paths of the <#import>s, without the quotes
+fn collect_import_paths(node: &Node, doc: &TextDocument, paths: &mut Vec<(String, Range)>) {
+    if let Ok(Rule::ImportStmt) = Rule::from_str(node.kind()) {
+        if let Some(path_node) = node.child_by_field_name(Rule::ImportPath.to_string())
+            && path_node.end_byte() - path_node.start_byte() >= 2
+        {
+            paths.push((
+                doc.get_ranged_text(path_node.start_byte() + 1..path_node.end_byte() - 1),
+                utils::parser_node_to_document_range(doc, &path_node),
+            ));
+        }
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_import_paths(&child, doc, paths);
+    }
+}
+
+// The verdict of the import resolution, reported by `freemarker/resolveImport`
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(
+    tag = "status",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum ImportResolution {
+    Resolved {
+        canonical_path: PathBuf,
+    },
+    NotAFile {
+        canonical_path: PathBuf,
+    },
+    // a file which only differs in case is a likely culprit
+    NotFound {
+        candidate: PathBuf,
+        case_mismatch: Option<PathBuf>,
+    },
+}
+
+fn find_case_mismatch(candidate: &Path) -> Option<PathBuf> {
+    let name = candidate.file_name()?.to_string_lossy().to_lowercase();
+    std::fs::read_dir(candidate.parent()?)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_name()
+                .is_some_and(|file_name| file_name.to_string_lossy().to_lowercase() == name)
+        })
+}
+
+pub fn explain_import(
+    validity: &ImportValidity,
+    config: &Config,
+    dir: &Path,
+    path: &str,
+) -> ImportResolution {
+    let candidate = import_candidate(config, dir, path);
+    match validity.resolve(&candidate) {
+        Some(target) if target.is_file => ImportResolution::Resolved {
+            canonical_path: target.canonical_path,
+        },
+        Some(target) => ImportResolution::NotAFile {
+            canonical_path: target.canonical_path,
+        },
+        None => ImportResolution::NotFound {
+            case_mismatch: find_case_mismatch(&candidate),
+            candidate,
+        },
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveImportParams {
+    pub text_document: TextDocumentIdentifier,
+    pub path: String,
+}
+
+impl ResolveImportFeature for Workspace {
+    async fn on_resolve_import(
+        &self,
+        params: ResolveImportParams,
+    ) -> jsonrpc::Result<ImportResolution> {
+        let Some(dir) = params
+            .text_document
+            .uri
+            .to_file_path()
+            .and_then(|path| path.parent().map(Path::to_path_buf))
+        else {
+            return Err(jsonrpc::Error::invalid_params(format!(
+                "not a file: {}",
+                params.text_document.uri.as_str()
+            )));
+        };
+        let config = self.get_config().await;
+        Ok(explain_import(
+            &self.imports.validity(),
+            &config,
+            &dir,
+            &params.path,
+        ))
+    }
+}
+
+// the templates under the root, hidden directories are skipped
+fn collect_templates(root: &Path, limit: usize) -> Vec<PathBuf> {
+    let mut templates = vec![];
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+        paths.sort();
+        for path in paths {
+            if path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+            {
+                continue;
+            }
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path
+                .extension()
+                .is_some_and(|extension| TEMPLATE_EXTENSIONS.iter().any(|e| extension == *e))
+            {
+                templates.push(path);
+                if templates.len() == limit {
+                    return templates;
+                }
+            }
+        }
+    }
+    templates
+}
+
+// An analysis is valid as long as the template is not modified
+#[derive(Debug)]
+struct CachedAnalysis {
+    modified: Option<SystemTime>,
+    analysis: Arc<Analysis>,
+}
+
+// Analyses of the imported templates which are not opened by the client, by
+// their canonical paths, cloned handles share the same analyses
+#[derive(Clone, Debug, Default)]
+pub struct ImportCache {
+    analyses: Arc<RwLock<HashMap<PathBuf, CachedAnalysis>>>,
+    validity: Arc<ImportValidity>,
+    // the settings of the workspace, the position encoding of the ranges included
+    config: Arc<RwLock<Arc<Config>>>,
+}
+
+impl ImportCache {
+    pub fn new() -> Self {
+        Self {
+            analyses: Arc::new(RwLock::new(HashMap::new())),
+            validity: Arc::new(ImportValidity::default()),
+            config: Arc::new(RwLock::new(Arc::new(Config::default()))),
+        }
+    }
+
+    // the analyses with the previous settings are dropped
+    pub async fn set_config(&self, config: Arc<Config>) {
+        *self.config.write().await = config;
+        self.analyses.write().await.clear();
+    }
+
+    // analyzes the templates under the root ahead of the first cross-file request,
+    // returns the number of analyzed templates
+    pub async fn warm_up(&self, root: &Path, progress: &mut impl ProgressSink) -> usize {
+        let templates = collect_templates(root, EAGER_INDEX_LIMIT);
+        let mut tracker =
+            ProgressTracker::begin(progress, "Indexing FreeMarker templates", templates.len())
+                .await;
+        let mut analyzed = 0;
+        for template in &templates {
+            if let Ok(path) = template.canonicalize()
+                && let Some(uri) = Uri::from_file_path(path)
+                && self.get(&uri).await.is_some()
+            {
+                analyzed += 1;
+            }
+            let relative = template.strip_prefix(root).unwrap_or(template);
+            tracker.step(relative.display().to_string()).await;
+        }
+        tracker.end(format!("{} templates", analyzed)).await;
+        analyzed
+    }
+
+    pub fn validity(&self) -> Arc<ImportValidity> {
+        self.validity.clone()
+    }
+
+    pub async fn get(&self, uri: &Uri) -> Option<Arc<Analysis>> {
+        // "lib/../lib.ftl" and "lib.ftl" are the same template
+        let target = self
+            .validity
+            .resolve(&uri.to_file_path()?)
+            .filter(|target| target.is_file)?;
+        if let Some(cached) = self.analyses.read().await.get(&target.canonical_path)
+            && cached.modified == target.modified
+        {
+            return Some(cached.analysis.clone());
+        }
+        let text = std::fs::read_to_string(&target.canonical_path).ok()?;
+        let config = self.config.read().await.clone();
+        let (_, _, analysis) = analyze_text(
+            &Uri::from_file_path(&target.canonical_path)?,
+            &text,
+            config,
+            self.validity.clone(),
+        );
+        let analysis = Arc::new(analysis);
+        self.analyses.write().await.insert(
+            target.canonical_path,
+            CachedAnalysis {
+                modified: target.modified,
+                analysis: analysis.clone(),
+            },
+        );
+        Some(analysis)
+    }
+
+    pub async fn invalidate(&self, uri: &Uri) {
+        let Some(path) = uri.to_file_path() else {
+            return;
+        };
+        self.validity.invalidate(&path);
+        let mut analyses = self.analyses.write().await;
+        analyses.remove(&*path);
+        // the modification time may not change within its resolution
+        if let Ok(canonical_path) = path.canonicalize() {
+            analyses.remove(&canonical_path);
+        }
+    }
+
+    #[cfg(test)]
+    pub async fn contains(&self, uri: &Uri) -> bool {
+        let path = uri.to_file_path().unwrap();
+        self.analyses.read().await.contains_key(&*path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        path::Path,
+        str::FromStr,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::{Duration, SystemTime},
+    };
+
+    use tower_lsp_server::ls_types::{
+        DidChangeWatchedFilesParams, FileChangeType, FileEvent, Position, Range,
+        TextDocumentContentChangeEvent, TextDocumentIdentifier, Uri,
+    };
+
+    use super::{
+        FileStat, ImportCache, ImportResolution, ImportTarget, ImportValidity, ResolveImportParams,
+        collect_templates,
+    };
+    use crate::progress::ProgressEvent;
+    use crate::{
+        analysis::Analysis,
+        config::{Config, PathMapping},
+        doc::{PositionEncodingKind, TextDocument},
+        parser::TextParser,
+        reactor::Reactor,
+        server::ResolveImportFeature,
+        utils,
+        workspace::Workspace,
+    };
+
+    // every stat sees a newer modification time
+    #[derive(Debug)]
+    struct CountingStat(Arc<AtomicUsize>);
+
+    impl FileStat for CountingStat {
+        fn stat(&self, path: &Path) -> io::Result<ImportTarget> {
+            let count = self.0.fetch_add(1, Ordering::SeqCst) as u64;
+            Ok(ImportTarget {
+                canonical_path: path.to_path_buf(),
+                is_file: true,
+                modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(count)),
+            })
+        }
+    }
+
+    #[test]
+    fn test_unchanged_import_is_not_restated() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let validity = Arc::new(ImportValidity::with_stat(Box::new(CountingStat(
+            count.clone(),
+        ))));
+        let uri = Uri::from_str("file:///tmp/main.ftl").unwrap();
+        let source = "<#import \"lib.ftl\" as lib>\n";
+        let doc = TextDocument::new(&uri, source);
+        let parser = TextParser::new(source);
+
+        // the import and the template itself
+        Analysis::new(&doc, &parser, Default::default(), validity.clone());
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+        Analysis::new(&doc, &parser, Default::default(), validity.clone());
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+
+        // the watched file is changed
+        validity.invalidate(Path::new("/tmp/lib.ftl"));
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+        Analysis::new(&doc, &parser, Default::default(), validity.clone());
+        assert_eq!(count.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_import_checks_carried_over_edits() {
+        let validity = Arc::new(ImportValidity::with_stat(Box::new(CountingStat(
+            Default::default(),
+        ))));
+        let uri = Uri::from_str("file:///tmp/main.ftl").unwrap();
+        let source = "<#import \"lib.ftl\" as lib>\n${x}\n";
+        let mut reactor = Reactor::new(&uri, source, 0, Default::default(), validity.clone());
+        let check_of =
+            |reactor: &Reactor| reactor.get_analysis().get_import_checks()["lib.ftl"].clone();
+        let change = |line: u32, character: u32, text: &str| TextDocumentContentChangeEvent {
+            range: Some(Range::new(
+                Position::new(line, character),
+                Position::new(line, character),
+            )),
+            range_length: None,
+            text: text.to_owned(),
+        };
+
+        // "${xy}" leaves the import alone
+        let check = check_of(&reactor);
+        reactor.apply_content_change(1, &change(1, 3, "y"), PositionEncodingKind::UTF16);
+        assert!(Arc::ptr_eq(&check, &check_of(&reactor)));
+
+        // an edit of the import line
+        reactor.apply_content_change(2, &change(0, 8, " "), PositionEncodingKind::UTF16);
+        assert!(!Arc::ptr_eq(&check, &check_of(&reactor)));
+
+        // a watched file is changed
+        let check = check_of(&reactor);
+        validity.invalidate(Path::new("/tmp/lib.ftl"));
+        reactor.apply_content_change(3, &change(1, 3, "z"), PositionEncodingKind::UTF16);
+        assert!(!Arc::ptr_eq(&check, &check_of(&reactor)));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_import() {
+        let dir = utils::fixture_dir(
+            "resolve_import",
+            &[
+                ("lib.ftl", ""),
+                ("Header.ftl", ""),
+                ("sub/placeholder.ftl", ""),
+            ],
+        );
+        let workspace = Workspace::new();
+        let resolve = async |path: &str| {
+            workspace
+                .on_resolve_import(ResolveImportParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: Uri::from_file_path(dir.join("main.ftl")).unwrap(),
+                    },
+                    path: path.to_owned(),
+                })
+                .await
+                .unwrap()
+        };
+        assert_eq!(
+            resolve("lib.ftl").await,
+            ImportResolution::Resolved {
+                canonical_path: dir.join("lib.ftl")
+            }
+        );
+        assert_eq!(
+            resolve("sub").await,
+            ImportResolution::NotAFile {
+                canonical_path: dir.join("sub")
+            }
+        );
+        // the file exists, but with another case
+        let resolution = resolve("header.ftl").await;
+        assert_eq!(
+            resolution,
+            ImportResolution::NotFound {
+                candidate: dir.join("header.ftl"),
+                case_mismatch: Some(dir.join("Header.ftl")),
+            }
+        );
+        let json = serde_json::to_value(&resolution).unwrap();
+        assert_eq!(json["status"], "notFound");
+        assert!(json["caseMismatch"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_eager_index() {
+        let dir = utils::fixture_dir(
+            "eager_index",
+            &[
+                ("main.ftl", "<#import \"lib/util.ftl\" as util>\n"),
+                ("lib/util.ftl", "<#macro foo a>\n</#macro>\n"),
+                ("README.md", "not a template"),
+                (".hidden/skipped.ftl", ""),
+            ],
+        );
+        assert_eq!(collect_templates(&dir, 1), vec![dir.join("main.ftl")]);
+        let imports = ImportCache::new();
+        let mut events = vec![];
+        assert_eq!(imports.warm_up(&dir, &mut events).await, 2);
+        assert_eq!(
+            events[2],
+            ProgressEvent::Report("lib/util.ftl".to_owned(), 100)
+        );
+        let util = Uri::from_file_path(dir.join("lib/util.ftl")).unwrap();
+        assert!(imports.contains(&util).await);
+        assert!(
+            !imports
+                .contains(&Uri::from_file_path(dir.join(".hidden/skipped.ftl")).unwrap())
+                .await
+        );
+        // the macros of the library are indexed
+        let analysis = imports.get(&util).await.unwrap();
+        assert_eq!(
+            analysis.get_macro_parameters("foo"),
+            Some(&vec!["a".to_owned()])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watched_change_busts_cache() {
+        let dir = utils::fixture_dir(
+            "import_cache",
+            &[("lib/lib.ftl", "<#macro foo>\n</#macro>\n")],
+        );
+        let workspace = Workspace::new();
+        let lib = Uri::from_file_path(dir.join("lib/lib.ftl")).unwrap();
+        let analysis = workspace.imports.get(&lib).await.unwrap();
+        assert!(analysis.find_macro_definition("foo").is_some());
+        // another spelling of the path shares the analysis
+        let spelled = Uri::from_file_path(dir.join("lib/../lib/lib.ftl")).unwrap();
+        assert!(Arc::ptr_eq(
+            &workspace.imports.get(&spelled).await.unwrap(),
+            &analysis
+        ));
+
+        std::fs::write(dir.join("lib/lib.ftl"), "<#macro bar>\n</#macro>\n").unwrap();
+        // the change is not seen until it is notified
+        let cached = workspace.imports.get(&lib).await.unwrap();
+        assert!(Arc::ptr_eq(&cached, &analysis));
+        workspace
+            .on_did_change_watched_files(DidChangeWatchedFilesParams {
+                changes: vec![FileEvent::new(lib.clone(), FileChangeType::CHANGED)],
+            })
+            .await;
+        let analysis = workspace.imports.get(&lib).await.unwrap();
+        assert!(analysis.find_macro_definition("foo").is_none());
+        assert!(analysis.find_macro_definition("bar").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_import_cache_with_workspace_config() {
+        let dir = utils::fixture_dir(
+            "import_cache_config",
+            &[
+                (
+                    "lib.ftl",
+                    "<#-- é --><#macro foo>\n</#macro>\n<#import \"@lib/util.ftl\" as u>\n<@u.bar/>\n<@spring.bind/>\n",
+                ),
+                ("lib/util.ftl", "<#macro bar>\n</#macro>\n"),
+            ],
+        );
+        let lib = Uri::from_file_path(dir.join("lib.ftl")).unwrap();
+        let workspace = Workspace::new();
+        let diagnostics = workspace
+            .imports
+            .get(&lib)
+            .await
+            .unwrap()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items;
+        // the import path is not mapped, the macro is not known
+        assert_eq!(diagnostics.len(), 2);
+
+        workspace
+            .set_config(Config {
+                path_mappings: vec![PathMapping {
+                    prefix: Some("@lib/".to_owned()),
+                    replacement: "lib/".to_owned(),
+                    append_extension: None,
+                }],
+                known_macros: ["spring".to_owned()].into(),
+                position_encoding: PositionEncodingKind::UTF8,
+                ..Default::default()
+            })
+            .await;
+        let analysis = workspace.imports.get(&lib).await.unwrap();
+        assert!(analysis.get_valid_import("@lib/util.ftl").is_some());
+        assert!(
+            analysis
+                .get_analyzed_full_diagnostics()
+                .full_document_diagnostic_report
+                .items
+                .is_empty()
+        );
+        // "é" takes two bytes
+        let foo = analysis.find_macro_definition("foo").unwrap();
+        assert_eq!(foo.range.start, Position::new(0, 19));
+    }
+
+    #[tokio::test]
+    async fn test_created_file_completes_missing_import() {
+        let dir = utils::fixture_dir("created_import", &[("sub/main.ftl", "")]);
+        let workspace = Workspace::new();
+        let validity = workspace.imports.validity();
+        // not the canonical path of the new file
+        let requested = dir.join("sub/../lib.ftl");
+        assert!(validity.resolve(&requested).is_none());
+
+        std::fs::write(dir.join("lib.ftl"), "").unwrap();
+        workspace
+            .on_did_change_watched_files(DidChangeWatchedFilesParams {
+                changes: vec![FileEvent::new(
+                    Uri::from_file_path(dir.join("lib.ftl")).unwrap(),
+                    FileChangeType::CREATED,
+                )],
+            })
+            .await;
+        assert!(
+            validity
+                .resolve(&requested)
+                .is_some_and(|target| target.is_file)
+        );
+    }
+}
@@ -0,0 +1,160 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use tower_lsp_server::ls_types::Range;
+use tree_sitter::Node;
+
+use crate::{
+    analysis::{Analysis, AnalysisContext, IncludeAnalysis, PathReference},
+    doc::TextDocument,
+    utils,
+};
+
+#[derive(Clone, Debug)]
+pub struct Include {
+    pub path: String,
+    // without the quotes
+    pub path_range: Range,
+    // `<#include "lib.ftl" as lib>` is a common mistake for `<#import>`
+    pub alias: Option<String>,
+    // "#include"
+    pub keyword_range: Range,
+    // the directive itself, the enclosing ERROR node may run past it
+    pub range: Range,
+}
+
+fn byte_range(doc: &TextDocument, start: usize, end: usize) -> Range {
+    Range {
+        start: doc.byte_to_position(start),
+        end: doc.byte_to_position(end),
+    }
+}
+
+fn parse_include(node: &Node, doc: &TextDocument) -> Option<Include> {
+    let directive = utils::error_node_directive(node, doc)
+        .filter(|directive| directive.name == "include" && !directive.closing)?;
+    let rest = directive.rest.as_str();
+    let path_start = rest.len() - rest.trim_start().len();
+    if path_start == 0 {
+        return None;
+    }
+    let quoted = &rest[path_start..];
+    let quote = quoted.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let path_len = quoted[1..].find(quote)?;
+    let path_byte = directive.rest_byte + path_start + 1;
+    let end_byte = quoted[path_len + 2..]
+        .find('>')
+        .map_or(node.end_byte(), |close| {
+            path_byte + path_len + 1 + close + 1
+        });
+    let alias = quoted[path_len + 2..]
+        .trim_start()
+        .strip_prefix("as")
+        .filter(|after| after.starts_with(char::is_whitespace))
+        .map(|after| {
+            after
+                .trim_start()
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .collect::<String>()
+        })
+        .filter(|alias| !alias.is_empty());
+    Some(Include {
+        path: quoted[1..path_len + 1].to_owned(),
+        path_range: byte_range(doc, path_byte, path_byte + path_len),
+        alias,
+        keyword_range: byte_range(doc, node.start_byte() + 1, directive.rest_byte),
+        range: byte_range(doc, node.start_byte(), end_byte),
+    })
+}
+
+impl IncludeAnalysis for Analysis {
+    fn analyze_include(
+        &mut self,
+        node: &Node,
+        doc: &TextDocument,
+        ctx: &mut AnalysisContext,
+    ) -> bool {
+        let Some(include) = parse_include(node, doc) else {
+            return false;
+        };
+        let include_target = ctx
+            .imports
+            .resolve_import(&ctx.config, &doc.dir(), &include.path);
+        if let Some(target) = include_target {
+            self.add_path_reference(PathReference {
+                text: include.path.clone(),
+                range: include.path_range,
+                directive_range: include.range,
+                target: target.canonical_path,
+            });
+        }
+        self.add_include(include);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tower_lsp_server::ls_types::{
+        CodeActionContext, CodeActionOrCommand, CodeActionParams, NumberOrString, Position,
+        TextDocumentIdentifier, Uri,
+    };
+
+    use crate::{reactor::Reactor, server::ActionFeature};
+
+    #[tokio::test]
+    async fn test_include_used_as_import() {
+        let uri = Uri::from_str("file:///tmp/include.ftl").unwrap();
+        let source = "<#include \"lib.ftl\" as lib>\n<#include 'header.ftl'>\n<@lib.bar/>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let includes = reactor.get_analysis().get_includes();
+        assert_eq!(includes.len(), 2);
+        assert_eq!(includes[0].path, "lib.ftl");
+        assert_eq!(includes[0].alias.as_deref(), Some("lib"));
+        assert_eq!(includes[1].path, "header.ftl");
+        assert_eq!(includes[1].alias, None);
+
+        let diagnostics = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items;
+        assert_eq!(diagnostics.len(), 1);
+        let diagnostic = diagnostics[0].clone();
+        assert_eq!(
+            diagnostic.code,
+            Some(NumberOrString::String("include_used_as_import".to_owned()))
+        );
+        assert_eq!(diagnostic.range.start, Position::new(2, 2));
+        let related = diagnostic.related_information.as_ref().unwrap();
+        assert_eq!(related[0].location.range.start, Position::new(0, 1));
+        assert_eq!(related[0].message, "\"lib.ftl\" is included here");
+
+        // the quickfix turns the include into an import
+        let actions = reactor
+            .on_code_action(CodeActionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                range: diagnostic.range,
+                context: CodeActionContext {
+                    diagnostics: vec![diagnostic],
+                    ..Default::default()
+                },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        let CodeActionOrCommand::CodeAction(action) = &actions[0] else {
+            panic!("expect a code action");
+        };
+        let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+        assert_eq!(edits[0].range.start, Position::new(0, 1));
+        assert_eq!(edits[0].range.end, Position::new(0, 9));
+        assert_eq!(edits[0].new_text, "#import");
+    }
+}
@@ -9,25 +9,48 @@ use tower_lsp_server::ls_types::{
 };
 use tracing::{Level, event};
 
+use crate::config::Config;
+use crate::doc::PositionEncodingKind;
 use crate::server::{Initializer, Server};
 use crate::{
-    action, completion, diagnosis, folding, format, goto, hover, tokenizer, window_log_info,
+    action, command, completion, delete, diagnosis, folding, format, goto, hierarchy, highlight,
+    hover, inlay, inline, link, reference, rename, selection, signature, symbol, tokenizer,
+    window_log_info,
 };
 
-fn do_initialize() -> InitializeResult {
-    InitializeResult {
+fn do_initialize(config: &Config, position_encoding: PositionEncodingKind) -> InitializeResult {
+    let mut result = InitializeResult {
         capabilities: ServerCapabilities {
+            position_encoding: Some(position_encoding.into()),
             text_document_sync: Some(TextDocumentSyncCapability::Kind(
                 TextDocumentSyncKind::INCREMENTAL,
             )),
             definition_provider: Some(goto::definition_capability()),
             hover_provider: Some(hover::hover_capability()),
             code_action_provider: Some(action::code_action_capability()),
-            completion_provider: Some(completion::completion_capability()),
+            completion_provider: Some(completion::completion_capability(config)),
             diagnostic_provider: Some(diagnosis::diagnostic_capability()),
             document_formatting_provider: Some(format::formatting_capability()),
+            document_range_formatting_provider: Some(format::range_formatting_capability()),
+            document_on_type_formatting_provider: Some(format::on_type_formatting_capability()),
             semantic_tokens_provider: Some(tokenizer::semantic_token_capability()),
             folding_range_provider: Some(folding::folding_capability()),
+            references_provider: Some(reference::references_capability()),
+            rename_provider: Some(rename::rename_capability()),
+            selection_range_provider: Some(selection::selection_range_capability()),
+            signature_help_provider: Some(signature::signature_help_capability()),
+            document_highlight_provider: Some(highlight::document_highlight_capability()),
+            document_symbol_provider: Some(symbol::document_symbol_capability()),
+            document_link_provider: Some(link::document_link_capability()),
+            workspace_symbol_provider: Some(symbol::workspace_symbol_capability()),
+            inline_value_provider: Some(inline::inline_value_capability()),
+            // opt-in, only announced when a kind of hint is enabled
+            inlay_hint_provider: config
+                .inlay_hints
+                .macro_parameters
+                .then(inlay::inlay_hint_capability),
+            call_hierarchy_provider: Some(hierarchy::call_hierarchy_capability()),
+            execute_command_provider: Some(command::execute_command_capability()),
             workspace: Some(WorkspaceServerCapabilities {
                 file_operations: Some(WorkspaceFileOperationsServerCapabilities {
                     did_delete: Some(FileOperationRegistrationOptions {
@@ -39,6 +62,8 @@ fn do_initialize() -> InitializeResult {
                             ..Default::default()
                         }],
                     }),
+                    will_delete: Some(delete::will_delete_capability()),
+                    will_rename: Some(rename::will_rename_capability()),
                     ..Default::default()
                 }),
                 ..Default::default()
@@ -49,6 +74,38 @@ fn do_initialize() -> InitializeResult {
             name: Server::NAME.to_owned(),
             version: Some(env!("CARGO_PKG_VERSION").to_string()),
         }),
+    };
+    disable_features(&mut result.capabilities, config);
+    result
+}
+
+// leave the features to other extensions, the client won't send their requests
+fn disable_features(capabilities: &mut ServerCapabilities, config: &Config) {
+    for feature in &config.disabled_features {
+        match feature.as_str() {
+            "callHierarchy" => capabilities.call_hierarchy_provider = None,
+            "codeAction" => capabilities.code_action_provider = None,
+            "completion" => capabilities.completion_provider = None,
+            "definition" => capabilities.definition_provider = None,
+            "diagnostics" => capabilities.diagnostic_provider = None,
+            "documentHighlight" => capabilities.document_highlight_provider = None,
+            "documentLink" => capabilities.document_link_provider = None,
+            "documentSymbol" => capabilities.document_symbol_provider = None,
+            "folding" => capabilities.folding_range_provider = None,
+            "formatting" => capabilities.document_formatting_provider = None,
+            "hover" => capabilities.hover_provider = None,
+            "inlayHint" => capabilities.inlay_hint_provider = None,
+            "inlineValue" => capabilities.inline_value_provider = None,
+            "onTypeFormatting" => capabilities.document_on_type_formatting_provider = None,
+            "rangeFormatting" => capabilities.document_range_formatting_provider = None,
+            "references" => capabilities.references_provider = None,
+            "rename" => capabilities.rename_provider = None,
+            "selectionRange" => capabilities.selection_range_provider = None,
+            "semanticTokens" => capabilities.semantic_tokens_provider = None,
+            "signatureHelp" => capabilities.signature_help_provider = None,
+            "workspaceSymbol" => capabilities.workspace_symbol_provider = None,
+            unknown => event!(Level::WARN, "unknown feature to disable: {}", unknown),
+        }
     }
 }
 
@@ -64,6 +121,75 @@ impl Initializer for Server {
             );
             root_path.clone_from(&params.root_path.unwrap_or_default());
         }
-        do_initialize()
+        let mut config = Config::from_initialization_options(params.initialization_options);
+        let position_encoding = PositionEncodingKind::negotiate(
+            params
+                .capabilities
+                .general
+                .as_ref()
+                .and_then(|general| general.position_encodings.as_ref()),
+        );
+        let code_action_resolve = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|text_document| text_document.code_action.as_ref())
+            .and_then(|code_action| code_action.resolve_support.as_ref())
+            .is_some_and(|support| support.properties.iter().any(|p| p == "edit"));
+        config.position_encoding = position_encoding;
+        let result = do_initialize(&config, position_encoding);
+        self.workspace.set_config(config).await;
+        self.workspace
+            .set_position_encoding(position_encoding)
+            .await;
+        self.workspace
+            .set_code_action_resolve(code_action_resolve)
+            .await;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::do_initialize;
+    use crate::{config::Config, doc::PositionEncodingKind};
+
+    #[test]
+    fn test_disabled_features() {
+        let capabilities =
+            do_initialize(&Default::default(), PositionEncodingKind::UTF16).capabilities;
+        assert!(capabilities.document_formatting_provider.is_some());
+
+        let config = Config::from_initialization_options(Some(json!({
+            "disabledFeatures": ["formatting", "semanticTokens"]
+        })));
+        let capabilities = do_initialize(&config, PositionEncodingKind::UTF16).capabilities;
+        assert!(capabilities.document_formatting_provider.is_none());
+        assert!(capabilities.semantic_tokens_provider.is_none());
+        assert!(capabilities.hover_provider.is_some());
+        // opt-in
+        assert!(capabilities.inlay_hint_provider.is_none());
+        let config = Config::from_initialization_options(Some(json!({
+            "inlayHints": { "macroParameters": true }
+        })));
+        let capabilities = do_initialize(&config, PositionEncodingKind::UTF16).capabilities;
+        assert!(capabilities.inlay_hint_provider.is_some());
+    }
+
+    #[test]
+    fn test_trigger_characters() {
+        let config = Config::from_initialization_options(Some(json!({
+            "completion": { "triggerCharacters": [".", "[", "<#"] }
+        })));
+        let capabilities = do_initialize(&config, PositionEncodingKind::UTF16).capabilities;
+        let triggers = capabilities
+            .completion_provider
+            .unwrap()
+            .trigger_characters
+            .unwrap();
+        // merged with the defaults, without duplicates nor the invalid entries
+        assert_eq!(triggers, vec!["#", "{", "?", "@", ".", "["]);
     }
 }
@@ -0,0 +1,179 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::str::FromStr;
+
+use tower_lsp_server::{
+    jsonrpc::Result as JsonRpcResult,
+    ls_types::{
+        InlayHint, InlayHintKind, InlayHintLabel, InlayHintParams, InlayHintServerCapabilities,
+        InlayHintTooltip, OneOf, Range,
+    },
+};
+use tree_sitter::Node;
+use tree_sitter_freemarker::grammar::Rule;
+
+use crate::{doc::TextDocument, reactor::Reactor, server::InlayHintFeature, utils};
+
+pub fn inlay_hint_capability() -> OneOf<bool, InlayHintServerCapabilities> {
+    OneOf::Left(true)
+}
+
+const OPTIONAL_HINT: &str = "(optional)";
+const REQUIRED_HINT: &str = "(required)";
+
+fn overlaps(node: &Node, range: &Range, doc: &TextDocument) -> bool {
+    let node_range = utils::parser_node_to_document_range(doc, node);
+    node_range.start <= range.end && range.start <= node_range.end
+}
+
+fn parameter_hint(
+    label: &str,
+    end: &Node,
+    tooltip: Option<String>,
+    doc: &TextDocument,
+) -> InlayHint {
+    InlayHint {
+        position: utils::parser_node_to_document_range(doc, end).end,
+        label: InlayHintLabel::String(label.to_owned()),
+        kind: Some(InlayHintKind::PARAMETER),
+        text_edits: None,
+        tooltip: tooltip.map(InlayHintTooltip::String),
+        padding_left: Some(true),
+        padding_right: None,
+        data: None,
+    }
+}
+
+// "x=5" is optional, "y" is required unless it is a catch-all "y..."
+fn macro_parameter_hints(clause: &Node, doc: &TextDocument, hints: &mut Vec<InlayHint>) {
+    let mut cursor = clause.walk();
+    for parameter in clause.children_by_field_name("parameter", &mut cursor) {
+        match Rule::from_str(parameter.kind()) {
+            Ok(Rule::AssignExpression) => {
+                let tooltip = parameter.child_by_field_name("right").map(|right| {
+                    format!("defaults to {}", doc.get_ranged_text(right.byte_range()))
+                });
+                hints.push(parameter_hint(OPTIONAL_HINT, &parameter, tooltip, doc));
+            }
+            Ok(Rule::Identifier) => {
+                // the grammar leaves the ellipsis as an ERROR node
+                match parameter.next_sibling().filter(|next| {
+                    next.is_error() && doc.get_ranged_text(next.byte_range()).starts_with("...")
+                }) {
+                    Some(ellipsis) => hints.push(parameter_hint(
+                        OPTIONAL_HINT,
+                        &ellipsis,
+                        Some("catches the remaining arguments".to_owned()),
+                        doc,
+                    )),
+                    None => hints.push(parameter_hint(REQUIRED_HINT, &parameter, None, doc)),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn collect_hints(node: &Node, doc: &TextDocument, range: &Range, hints: &mut Vec<InlayHint>) {
+    if let Ok(Rule::MacroClause) = Rule::from_str(node.kind()) {
+        macro_parameter_hints(node, doc, hints);
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        if overlaps(&child, range, doc) {
+            collect_hints(&child, doc, range, hints);
+        }
+    }
+}
+
+impl InlayHintFeature for Reactor {
+    async fn on_inlay_hint(
+        &self,
+        params: InlayHintParams,
+    ) -> JsonRpcResult<Option<Vec<InlayHint>>> {
+        if !self.get_config().inlay_hints.macro_parameters {
+            return Ok(None);
+        }
+        let Some(ast) = self.get_parser().get_ast() else {
+            return Ok(None);
+        };
+        let mut hints = vec![];
+        collect_hints(
+            &ast.root_node(),
+            self.get_document(),
+            &params.range,
+            &mut hints,
+        );
+        hints.retain(|hint| {
+            params.range.start <= hint.position && hint.position <= params.range.end
+        });
+        Ok(Some(hints))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use tower_lsp_server::ls_types::{
+        InlayHintLabel, InlayHintParams, Position, Range, TextDocumentIdentifier, Uri,
+    };
+
+    use crate::{
+        config::{Config, InlayHintsConfig},
+        reactor::Reactor,
+        server::InlayHintFeature,
+    };
+
+    fn inlay_hint_params(uri: &Uri) -> InlayHintParams {
+        InlayHintParams {
+            work_done_progress_params: Default::default(),
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            range: Range::new(Position::new(0, 0), Position::new(3, 0)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_macro_parameter_hints() {
+        let uri = Uri::from_str("file:///tmp/inlay.ftl").unwrap();
+        let source = "<#macro foo x=5 y rest...>\n${x}\n</#macro>\n";
+        let config = Config {
+            inlay_hints: InlayHintsConfig {
+                macro_parameters: true,
+            },
+            ..Default::default()
+        };
+        let reactor = Reactor::new(&uri, source, 0, Arc::new(config), Default::default());
+        let hints: Vec<(Position, String)> = reactor
+            .on_inlay_hint(inlay_hint_params(&uri))
+            .await
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|hint| match hint.label {
+                InlayHintLabel::String(label) => (hint.position, label),
+                _ => panic!("expect a string label"),
+            })
+            .collect();
+        assert_eq!(
+            hints,
+            vec![
+                (Position::new(0, 15), "(optional)".to_owned()),
+                (Position::new(0, 17), "(required)".to_owned()),
+                (Position::new(0, 25), "(optional)".to_owned()),
+            ]
+        );
+
+        // opt-in
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        assert!(
+            reactor
+                .on_inlay_hint(inlay_hint_params(&uri))
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
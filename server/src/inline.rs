@@ -0,0 +1,129 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::str::FromStr;
+
+use tower_lsp_server::{
+    jsonrpc::Result as JsonRpcResult,
+    ls_types::{
+        InlineValue, InlineValueParams, InlineValueServerCapabilities, InlineValueVariableLookup,
+        OneOf, Range,
+    },
+};
+use tree_sitter::Node;
+use tree_sitter_freemarker::grammar::Rule;
+
+use crate::{doc::TextDocument, reactor::Reactor, server::InlineValueFeature, utils};
+
+pub fn inline_value_capability() -> OneOf<bool, InlineValueServerCapabilities> {
+    OneOf::Left(true)
+}
+
+fn overlaps(node: &Node, range: &Range, doc: &TextDocument) -> bool {
+    let node_range = utils::parser_node_to_document_range(doc, node);
+    node_range.start <= range.end && range.start <= node_range.end
+}
+
+// a debugger looks up the value of each variable read or written in the range
+fn collect_variable_lookups(
+    node: &Node,
+    doc: &TextDocument,
+    range: &Range,
+    lookups: &mut Vec<InlineValue>,
+) {
+    if let Ok(Rule::Variable) = Rule::from_str(node.kind())
+        && let Some(name) = node.child_by_field_name("name")
+    {
+        lookups.push(InlineValue::VariableLookup(InlineValueVariableLookup {
+            range: utils::parser_node_to_document_range(doc, &name),
+            variable_name: Some(doc.get_ranged_text(name.start_byte()..name.end_byte())),
+            case_sensitive_lookup: true,
+        }));
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        if overlaps(&child, range, doc) {
+            collect_variable_lookups(&child, doc, range, lookups);
+        }
+    }
+}
+
+impl InlineValueFeature for Reactor {
+    async fn on_inline_value(
+        &self,
+        params: InlineValueParams,
+    ) -> JsonRpcResult<Option<Vec<InlineValue>>> {
+        let Some(ast) = self.get_parser().get_ast() else {
+            return Ok(None);
+        };
+        let mut lookups = vec![];
+        collect_variable_lookups(
+            &ast.root_node(),
+            self.get_document(),
+            &params.range,
+            &mut lookups,
+        );
+        Ok(Some(lookups))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tower_lsp_server::ls_types::{
+        InlineValue, InlineValueContext, InlineValueParams, Position, Range,
+        TextDocumentIdentifier, Uri,
+    };
+
+    use crate::{reactor::Reactor, server::InlineValueFeature};
+
+    #[tokio::test]
+    async fn test_inline_variable_lookups() {
+        let uri = Uri::from_str("file:///tmp/inline.ftl").unwrap();
+        let source = "<#assign total = 0>\n<#list items as item>\n${item.price + total}\n</#list>\n${footer}\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let inline_values = reactor
+            .on_inline_value(InlineValueParams {
+                work_done_progress_params: Default::default(),
+                text_document: TextDocumentIdentifier { uri },
+                range: Range::new(Position::new(1, 0), Position::new(3, 0)),
+                context: InlineValueContext {
+                    frame_id: 0,
+                    stopped_location: Range::new(Position::new(2, 0), Position::new(2, 0)),
+                },
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        let lookups: Vec<(String, Range)> = inline_values
+            .into_iter()
+            .map(|value| match value {
+                InlineValue::VariableLookup(lookup) => {
+                    (lookup.variable_name.unwrap(), lookup.range)
+                }
+                _ => panic!("expect a variable lookup"),
+            })
+            .collect();
+        // "total" of the first line and "footer" are out of the range
+        assert_eq!(
+            lookups,
+            vec![
+                (
+                    "items".to_owned(),
+                    Range::new(Position::new(1, 7), Position::new(1, 12))
+                ),
+                (
+                    "item".to_owned(),
+                    Range::new(Position::new(2, 2), Position::new(2, 6))
+                ),
+                (
+                    "total".to_owned(),
+                    Range::new(Position::new(2, 15), Position::new(2, 20))
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,50 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+// The analysis is usable without a language server session, see `analysis::analyze_source`
+
+#![deny(clippy::print_stdout)]
+#![deny(clippy::print_stderr)]
+
+mod action;
+pub mod analysis;
+mod assets;
+mod client;
+mod command;
+mod completion;
+pub mod config;
+mod delete;
+mod diagnosis;
+pub mod doc;
+mod escape;
+mod folding;
+mod format;
+mod goto;
+mod hierarchy;
+mod highlight;
+mod hover;
+mod import;
+mod include;
+mod init;
+mod inlay;
+mod inline;
+mod link;
+pub mod parser;
+mod progress;
+mod reactor;
+mod reference;
+mod rename;
+mod selection;
+mod server;
+mod setting;
+mod signature;
+mod suppress;
+mod symbol;
+mod tokenizer;
+mod utils;
+mod workspace;
+
+pub use import::RESOLVE_IMPORT;
+pub use server::Server;
+pub use signature::MACRO_SIGNATURE;
@@ -0,0 +1,95 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use tower_lsp_server::{
+    jsonrpc::Result as JsonRpcResult,
+    ls_types::{DocumentLink, DocumentLinkOptions, DocumentLinkParams, Uri},
+};
+
+use crate::{reactor::Reactor, server::DocumentLinkFeature};
+
+pub fn document_link_capability() -> DocumentLinkOptions {
+    DocumentLinkOptions {
+        resolve_provider: Some(false),
+        work_done_progress_options: Default::default(),
+    }
+}
+
+impl DocumentLinkFeature for Reactor {
+    async fn on_document_link(
+        &self,
+        _: DocumentLinkParams,
+    ) -> JsonRpcResult<Option<Vec<DocumentLink>>> {
+        let uri = self.get_document().uri();
+        let self_path = uri.to_file_path();
+        // the paths of <#import> and <#include>, the invalid ones have a diagnostic instead
+        let links = self
+            .get_analysis()
+            .get_path_references()
+            .iter()
+            .filter(|reference| reference.target.is_file())
+            .filter(|reference| self_path.as_deref() != Some(reference.target.as_path()))
+            .filter_map(|reference| {
+                Some(DocumentLink {
+                    range: reference.range,
+                    target: Some(Uri::from_file_path(&reference.target)?),
+                    tooltip: None,
+                    data: None,
+                })
+            })
+            .collect();
+        Ok(Some(links))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::{
+        DocumentLinkParams, Position, Range, TextDocumentIdentifier, Uri,
+    };
+
+    use crate::{reactor::Reactor, server::DocumentLinkFeature, utils};
+
+    #[tokio::test]
+    async fn test_import_links() {
+        let source = "<#import \"lib.ftl\" as lib>\n<#import \"missing.ftl\" as m>\n<#include \"dir\">\n<#include \"footer.ftl\">\n";
+        let dir = utils::fixture_dir(
+            "document_links",
+            &[
+                ("lib.ftl", "<#macro bar>\n</#macro>\n"),
+                ("footer.ftl", ""),
+                ("dir/other.ftl", ""),
+                ("main.ftl", source),
+            ],
+        );
+        let uri = Uri::from_file_path(dir.join("main.ftl")).unwrap();
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let links: Vec<(Range, Uri)> = reactor
+            .on_document_link(DocumentLinkParams {
+                text_document: TextDocumentIdentifier { uri },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|link| (link.range, link.target.unwrap()))
+            .collect();
+        // the missing template and the directory are skipped
+        assert_eq!(
+            links,
+            vec![
+                (
+                    Range::new(Position::new(0, 10), Position::new(0, 17)),
+                    Uri::from_file_path(dir.join("lib.ftl").canonicalize().unwrap()).unwrap(),
+                ),
+                (
+                    Range::new(Position::new(3, 11), Position::new(3, 21)),
+                    Uri::from_file_path(dir.join("footer.ftl").canonicalize().unwrap()).unwrap(),
+                ),
+            ]
+        );
+    }
+}
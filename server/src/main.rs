@@ -5,34 +5,157 @@
 #![deny(clippy::print_stdout)]
 #![deny(clippy::print_stderr)]
 
-use std::env;
-use tower_lsp_server::LspService;
+use std::{
+    env, fs,
+    io::{self, Write},
+    path, process,
+    str::FromStr,
+};
+use tower_lsp_server::{
+    LspService,
+    ls_types::{DiagnosticSeverity, NumberOrString, Uri},
+};
 use tracing::{level_filters::LevelFilter, subscriber};
 use tracing_subscriber::fmt::format::FmtSpan;
 
-mod action;
-mod analysis;
-mod client;
-mod completion;
-mod diagnosis;
-mod doc;
-mod folding;
-mod format;
-mod goto;
-mod hover;
-mod init;
-mod parser;
-mod reactor;
-mod server;
-mod symbol;
-mod tokenizer;
-mod utils;
-mod workspace;
+use lsp_for_freemarker::{
+    MACRO_SIGNATURE, RESOLVE_IMPORT, Server, analysis, config::Config, doc::PositionEncodingKind,
+};
+
+const USAGE: &str = "\
+Usage: lsp-for-freemarker [OPTIONS]
+       lsp-for-freemarker lint <FILE>...
+
+Serves the Language Server Protocol for FreeMarker templates over stdio, or prints the
+diagnostics of the files as FILE:LINE:COLUMN, the column counting characters, and fails if
+any of them is an error.
+
+Options:
+      --log-level <LEVEL>  Maximum level of the log file: off, error, warn, info, debug or trace
+                           [env: LSP_FREEMARKER_LOG] [default: info]
+  -h, --help               Print this help and exit
+  -V, --version            Print the version and exit
+";
+
+const LOG_LEVEL_FLAG: &str = "--log-level";
+const LOG_LEVEL_ENV: &str = "LSP_FREEMARKER_LOG";
+
+// the output of the informational flags, other arguments (e.g. "--stdio") are left to the server
+fn cli_output(arg: &str) -> Option<String> {
+    match arg {
+        "-V" | "--version" => Some(format!(
+            "{} {}\n",
+            Server::CODE_NAME,
+            env!("CARGO_PKG_VERSION")
+        )),
+        "-h" | "--help" => Some(USAGE.to_owned()),
+        _ => None,
+    }
+}
+
+// the flag takes precedence over the environment variable, an invalid level falls back to INFO
+fn log_level(args: &[String], env_value: Option<String>) -> LevelFilter {
+    let flag_value = args.iter().enumerate().find_map(|(i, arg)| {
+        match arg.strip_prefix(LOG_LEVEL_FLAG)?.strip_prefix('=') {
+            Some(value) => Some(value.to_owned()),
+            None if arg == LOG_LEVEL_FLAG => args.get(i + 1).cloned(),
+            None => None,
+        }
+    });
+    flag_value
+        .or(env_value)
+        .and_then(|value| LevelFilter::from_str(&value).ok())
+        .unwrap_or(LevelFilter::INFO)
+}
+
+const LINT_SUBCOMMAND: &str = "lint";
+
+fn severity_name(severity: Option<DiagnosticSeverity>) -> &'static str {
+    match severity {
+        Some(DiagnosticSeverity::ERROR) => "error",
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        Some(DiagnosticSeverity::INFORMATION) => "info",
+        _ => "hint",
+    }
+}
+
+// e.g. "main.ftl:3:5 error undefined_macro ...", and whether an error is reported, the columns
+// of UTF-32 are the characters
+fn lint_report(path: &str, text: &str) -> (String, bool) {
+    let uri = path::absolute(path)
+        .ok()
+        .and_then(Uri::from_file_path)
+        .unwrap_or_else(|| Uri::from_str(&format!("file:///{}", path)).unwrap());
+    let config = Config {
+        position_encoding: PositionEncodingKind::UTF32,
+        ..Default::default()
+    };
+    let diagnostics = analysis::analyze_source_with(&uri, text, config)
+        .get_analyzed_full_diagnostics()
+        .full_document_diagnostic_report
+        .items;
+    let mut report = String::new();
+    for diagnostic in &diagnostics {
+        let code = match &diagnostic.code {
+            Some(NumberOrString::String(code)) => code.clone(),
+            Some(NumberOrString::Number(code)) => code.to_string(),
+            None => "-".to_owned(),
+        };
+        report.push_str(&format!(
+            "{}:{}:{} {} {} {}\n",
+            path,
+            diagnostic.range.start.line + 1,
+            diagnostic.range.start.character + 1,
+            severity_name(diagnostic.severity),
+            code,
+            diagnostic.message
+        ));
+    }
+    let failed = diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Some(DiagnosticSeverity::ERROR));
+    (report, failed)
+}
+
+// the exit code, an unreadable file fails as well, so does no file at all
+fn lint(paths: &[String]) -> i32 {
+    if paths.is_empty() {
+        let _ = io::stderr().lock().write_all(USAGE.as_bytes());
+        return 2;
+    }
+    let mut failed = false;
+    let mut stdout = io::stdout().lock();
+    for path in paths {
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                let (report, has_error) = lint_report(path, &text);
+                let _ = stdout.write_all(report.as_bytes());
+                failed |= has_error;
+            }
+            Err(e) => {
+                let _ = stdout.write_all(format!("{}: {}\n", path, e).as_bytes());
+                failed = true;
+            }
+        }
+    }
+    i32::from(failed)
+}
 
 #[tokio::main]
 async fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    // e.g. "lint --help" lints a file named "--help"
+    if let Some(output) = args.first().and_then(|arg| cli_output(arg)) {
+        // the server is not started, no protocol message shares stdout with the output
+        let _ = io::stdout().lock().write_all(output.as_bytes());
+        return;
+    }
+    if args.first().is_some_and(|arg| arg == LINT_SUBCOMMAND) {
+        process::exit(lint(&args[1..]));
+    }
+
     // tracing facility
-    let cache_dir = env::temp_dir().join(server::Server::CODE_NAME);
+    let cache_dir = env::temp_dir().join(Server::CODE_NAME);
     let file_appender = tracing_appender::rolling::hourly(cache_dir, "lsp-for-freemarker.log");
     let (non_blocking_writer, _guard) = tracing_appender::non_blocking(file_appender);
 
@@ -54,19 +177,81 @@ async fn main() {
         .with_writer(non_blocking_writer)
         // Disabled ANSI color codes for better compatibility with some terminals
         .with_ansi(false)
-        // TODO: log level control
-        .with_max_level(LevelFilter::INFO)
+        // "--log-level" or LSP_FREEMARKER_LOG
+        .with_max_level(log_level(&args, env::var(LOG_LEVEL_ENV).ok()))
         // Build the subscriber
         .finish();
 
     // use that subscriber to process traces emitted after this point
     subscriber::set_global_default(subscriber).expect("Could not set global default subscriber");
 
-    // TODO: support other commands (e.g. `--version`, `--log`)
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
-    let (service, socket) = LspService::new(server::Server::new);
+    let (service, socket) = LspService::build(Server::new)
+        .custom_method(RESOLVE_IMPORT, Server::resolve_import)
+        .custom_method(MACRO_SIGNATURE, Server::macro_signature)
+        .finish();
     tower_lsp_server::Server::new(stdin, stdout, socket)
         .serve(service)
         .await;
 }
+
+#[cfg(test)]
+mod tests {
+    use tracing::level_filters::LevelFilter;
+
+    use super::{cli_output, lint, lint_report, log_level};
+
+    #[test]
+    fn test_cli_flags() {
+        assert_eq!(
+            cli_output("--version").unwrap(),
+            format!("lsp-for-freemarker {}\n", env!("CARGO_PKG_VERSION"))
+        );
+        assert!(cli_output("-h").unwrap().contains("--version"));
+        assert_eq!(cli_output("--stdio"), None);
+    }
+
+    #[test]
+    fn test_lint_report() {
+        let (report, failed) =
+            lint_report("lint.ftl", "<#list xs as x><#break></#list>\n<@missing/>\n");
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("lint.ftl:1:16 "));
+        assert!(lines[1].starts_with("lint.ftl:2:3 error undefined_macro "));
+        assert!(failed);
+        assert_eq!(lint_report("lint.ftl", "${x}\n"), (String::new(), false));
+        // "😀" is one character, two code units of UTF-16
+        let (report, _) = lint_report("lint.ftl", "${\"😀\"}<@missing/>\n");
+        assert!(report.starts_with("lint.ftl:1:9 error undefined_macro "));
+        assert_eq!(lint(&[]), 2);
+    }
+
+    #[test]
+    fn test_log_level() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(log_level(&args(&[]), None), LevelFilter::INFO);
+        assert_eq!(
+            log_level(&args(&["--log-level", "debug"]), None),
+            LevelFilter::DEBUG
+        );
+        assert_eq!(
+            log_level(&args(&["--stdio", "--log-level=TRACE"]), None),
+            LevelFilter::TRACE
+        );
+        assert_eq!(
+            log_level(&args(&[]), Some("warn".to_owned())),
+            LevelFilter::WARN
+        );
+        // the flag wins over the environment
+        assert_eq!(
+            log_level(&args(&["--log-level", "error"]), Some("trace".to_owned())),
+            LevelFilter::ERROR
+        );
+        assert_eq!(
+            log_level(&args(&["--log-level", "loud"]), None),
+            LevelFilter::INFO
+        );
+    }
+}
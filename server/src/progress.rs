@@ -0,0 +1,143 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use tower_lsp_server::{Bounded, NotCancellable, OngoingProgress, ls_types::NumberOrString};
+
+use crate::client::get_client;
+
+// the stages of a long operation, sent as "$/progress" notifications
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProgressEvent {
+    Begin(String),
+    Report(String, u32),
+    End(String),
+}
+
+pub trait ProgressSink {
+    async fn notify(&mut self, event: ProgressEvent);
+}
+
+// the token is created by "window/workDoneProgress/create"
+pub struct ClientProgress {
+    token: NumberOrString,
+    ongoing: Option<OngoingProgress<Bounded, NotCancellable>>,
+}
+
+impl ClientProgress {
+    // none if there is no client, or it doesn't accept the token
+    pub async fn create(token: &str) -> Option<Self> {
+        let token = NumberOrString::String(token.to_owned());
+        get_client()?
+            .create_work_done_progress(token.clone())
+            .await
+            .ok()?;
+        Some(Self {
+            token,
+            ongoing: None,
+        })
+    }
+}
+
+impl ProgressSink for ClientProgress {
+    async fn notify(&mut self, event: ProgressEvent) {
+        let Some(client) = get_client() else {
+            return;
+        };
+        match event {
+            ProgressEvent::Begin(title) => {
+                self.ongoing = Some(
+                    client
+                        .progress(self.token.clone(), title)
+                        .with_percentage(0)
+                        .begin()
+                        .await,
+                );
+            }
+            ProgressEvent::Report(message, percentage) => {
+                if let Some(ongoing) = &self.ongoing {
+                    ongoing.report_with_message(message, percentage).await;
+                }
+            }
+            ProgressEvent::End(message) => {
+                if let Some(ongoing) = self.ongoing.take() {
+                    ongoing.finish_with_message(message).await;
+                }
+            }
+        }
+    }
+}
+
+// the events are dropped if the client doesn't support the progress
+impl<S: ProgressSink> ProgressSink for Option<S> {
+    async fn notify(&mut self, event: ProgressEvent) {
+        if let Some(sink) = self {
+            sink.notify(event).await;
+        }
+    }
+}
+
+#[cfg(test)]
+impl ProgressSink for Vec<ProgressEvent> {
+    async fn notify(&mut self, event: ProgressEvent) {
+        self.push(event);
+    }
+}
+
+// counts the steps of an operation to report the percentage done
+pub struct ProgressTracker<'a, S: ProgressSink> {
+    sink: &'a mut S,
+    total: usize,
+    done: usize,
+}
+
+impl<'a, S: ProgressSink> ProgressTracker<'a, S> {
+    pub async fn begin(sink: &'a mut S, title: &str, total: usize) -> Self {
+        sink.notify(ProgressEvent::Begin(title.to_owned())).await;
+        Self {
+            sink,
+            total,
+            done: 0,
+        }
+    }
+
+    pub async fn step(&mut self, message: String) {
+        self.done += 1;
+        let percentage = (self.done * 100 / self.total.max(1)).min(100) as u32;
+        self.sink
+            .notify(ProgressEvent::Report(message, percentage))
+            .await;
+    }
+
+    pub async fn end(self, message: String) {
+        self.sink.notify(ProgressEvent::End(message)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProgressEvent, ProgressTracker};
+
+    #[tokio::test]
+    async fn test_progress_events() {
+        let mut events = vec![];
+        let mut tracker = ProgressTracker::begin(&mut events, "Summing", 4).await;
+        let mut sum = 0;
+        for n in 1..=4 {
+            sum += n;
+            tracker.step(sum.to_string()).await;
+        }
+        tracker.end("4 done".to_owned()).await;
+        assert_eq!(
+            events,
+            vec![
+                ProgressEvent::Begin("Summing".to_owned()),
+                ProgressEvent::Report("1".to_owned(), 25),
+                ProgressEvent::Report("3".to_owned(), 50),
+                ProgressEvent::Report("6".to_owned(), 75),
+                ProgressEvent::Report("10".to_owned(), 100),
+                ProgressEvent::End("4 done".to_owned()),
+            ]
+        );
+    }
+}
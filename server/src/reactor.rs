@@ -2,12 +2,19 @@
 // Licensed under the BSD 3-Clause License.
 // SPDX-License-Identifier: BSD-3-Clause
 
+use std::{collections::HashMap, sync::Arc};
+
 use tower_lsp_server::ls_types::{TextDocumentContentChangeEvent, Uri};
+use tree_sitter::InputEdit;
 
 use crate::{
-    analysis::Analysis,
+    analysis::{Analysis, analyze_text},
+    config::Config,
     doc::{PositionEncodingKind, TextDocument},
+    hover::HoverCache,
+    import::ImportValidity,
     parser::TextParser,
+    tokenizer::SemanticTokensCache,
 };
 
 #[derive(Debug)]
@@ -16,18 +23,34 @@ pub struct Reactor {
     doc: TextDocument,
     parser: TextParser,
     analysis: Analysis,
+    config: Arc<Config>,
+    imports: Arc<ImportValidity>,
+    // of the imports when the analysis began
+    imports_generation: usize,
+    hover_cache: HoverCache,
+    tokens_cache: SemanticTokensCache,
 }
 
 impl Reactor {
-    pub fn new(uri: &Uri, text: &str, version: i32) -> Self {
-        let doc = TextDocument::new(uri, text);
-        let parser = TextParser::new(text);
-        let analysis = Analysis::new(&doc, &parser);
+    pub fn new(
+        uri: &Uri,
+        text: &str,
+        version: i32,
+        config: Arc<Config>,
+        imports: Arc<ImportValidity>,
+    ) -> Self {
+        let imports_generation = imports.generation();
+        let (doc, parser, analysis) = analyze_text(uri, text, config.clone(), imports.clone());
         Reactor {
             version,
             doc,
             parser,
             analysis,
+            config,
+            imports,
+            imports_generation,
+            hover_cache: HoverCache::default(),
+            tokens_cache: SemanticTokensCache::default(),
         }
     }
 
@@ -43,16 +66,51 @@ impl Reactor {
         &self.analysis
     }
 
-    pub fn apply_content_change(&mut self, version: i32, change: &TextDocumentContentChangeEvent) {
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn get_hover_cache(&self) -> &HoverCache {
+        &self.hover_cache
+    }
+
+    pub fn get_tokens_cache(&self) -> &SemanticTokensCache {
+        &self.tokens_cache
+    }
+
+    pub fn apply_content_change(
+        &mut self,
+        version: i32,
+        change: &TextDocumentContentChangeEvent,
+        position_encoding: PositionEncodingKind,
+    ) {
         // always?
         self.version = version;
-        //TODO: what if the document's encoding is not UTF8?
-        if let Ok(edit) = self
-            .doc
-            .apply_content_change(change, PositionEncodingKind::UTF8)
-        {
+        if let Ok(edit) = self.doc.apply_content_change(change, position_encoding) {
+            // the imports are checked again once an edit touches them or a watched file changes
+            let import_checks = match edit.as_ref().is_some_and(|edit| !self.edits_imports(edit))
+                && self.imports_generation == self.imports.generation()
+            {
+                true => self.analysis.get_import_checks().clone(),
+                false => HashMap::new(),
+            };
             self.parser.apply_edit(&self.doc.to_string(), edit);
-            self.analysis = Analysis::new(&self.doc, &self.parser);
+            self.imports_generation = self.imports.generation();
+            self.analysis = Analysis::with_import_checks(
+                &self.doc,
+                &self.parser,
+                self.config.clone(),
+                self.imports.clone(),
+                import_checks,
+            );
         }
     }
+
+    // an <#import> typed anew has no check to carry over anyway
+    fn edits_imports(&self, edit: &InputEdit) -> bool {
+        self.analysis
+            .get_import_ranges()
+            .iter()
+            .any(|range| range.start <= edit.old_end_byte && edit.start_byte <= range.end)
+    }
 }
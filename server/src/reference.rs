@@ -0,0 +1,149 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::str::FromStr;
+
+use tower_lsp_server::{
+    jsonrpc::Result as JsonRpcResult,
+    ls_types::{Location, OneOf, Position, Range, ReferenceOptions, ReferenceParams},
+};
+use tree_sitter_freemarker::grammar::Rule;
+
+use crate::{reactor::Reactor, server::ReferencesFeature, utils};
+
+pub fn references_capability() -> OneOf<bool, ReferenceOptions> {
+    OneOf::Left(true)
+}
+
+impl Reactor {
+    // the namespace under the cursor, and every range in this document which refers to it
+    pub(crate) fn find_namespace_references(
+        &self,
+        position: &Position,
+        include_declaration: bool,
+    ) -> Option<(Range, Vec<Range>)> {
+        let doc = self.get_document();
+        let point = utils::lsp_position_to_parser_point(doc, position);
+        let node = self.get_parser().get_node_at_point(point)?;
+        // a macro namespace is either a macro of this file or an import alias
+        if !matches!(
+            Rule::from_str(node.kind()),
+            Ok(Rule::MacroName | Rule::MacroNamespace | Rule::ImportAlias)
+        ) {
+            return None;
+        }
+        let analysis = self.get_analysis();
+        let namespace = doc.get_ranged_text(node.start_byte()..node.end_byte());
+
+        let mut ranges = Vec::new();
+        if include_declaration && let Ok(symbols) = analysis.find_symbol_definition(&namespace) {
+            ranges.extend(
+                symbols
+                    .iter()
+                    .filter(|s| matches!(s.rule, Rule::MacroName | Rule::ImportAlias))
+                    .map(|s| s.range),
+            );
+        }
+        ranges.extend(
+            analysis
+                .get_macro_calls()
+                .iter()
+                .filter(|call| call.namespace == namespace)
+                .map(|call| call.namespace_range),
+        );
+        Some((utils::parser_node_to_document_range(doc, &node), ranges))
+    }
+}
+
+impl ReferencesFeature for Reactor {
+    async fn on_references(&self, params: ReferenceParams) -> JsonRpcResult<Option<Vec<Location>>> {
+        let uri = self.get_document().uri();
+        Ok(self
+            .find_namespace_references(
+                &params.text_document_position.position,
+                params.context.include_declaration,
+            )
+            .map(|(_, ranges)| {
+                ranges
+                    .into_iter()
+                    .map(|range| Location {
+                        uri: uri.clone(),
+                        range,
+                    })
+                    .collect()
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tower_lsp_server::ls_types::{
+        Position, ReferenceContext, ReferenceParams, TextDocumentIdentifier,
+        TextDocumentPositionParams, Uri,
+    };
+
+    use crate::{reactor::Reactor, server::ReferencesFeature};
+
+    async fn references(
+        reactor: &Reactor,
+        position: Position,
+        include_declaration: bool,
+    ) -> Vec<Position> {
+        reactor
+            .on_references(ReferenceParams {
+                text_document_position: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: reactor.get_document().uri(),
+                    },
+                    position,
+                },
+                context: ReferenceContext {
+                    include_declaration,
+                },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|location| location.range.start)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_macro_references() {
+        let uri = Uri::from_str("file:///tmp/references.ftl").unwrap();
+        let source = "<#import \"lib.ftl\" as lib>\n<#macro foo a>\n<@foo a=1/>\n</#macro>\n<@foo/>\n<@lib.bar/>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+
+        // from the definition
+        assert_eq!(
+            references(&reactor, Position::new(1, 9), true).await,
+            vec![
+                Position::new(1, 8),
+                Position::new(2, 2),
+                Position::new(4, 2)
+            ]
+        );
+        // from a call site
+        assert_eq!(
+            references(&reactor, Position::new(4, 3), false).await,
+            vec![Position::new(2, 2), Position::new(4, 2)]
+        );
+        // from the import alias
+        assert_eq!(
+            references(&reactor, Position::new(0, 23), true).await,
+            vec![Position::new(0, 22), Position::new(5, 2)]
+        );
+        // not a macro namespace
+        assert!(
+            references(&reactor, Position::new(2, 6), true)
+                .await
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,310 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+};
+
+use tower_lsp_server::{
+    jsonrpc::{Error as JsonRpcError, Result as JsonRpcResult},
+    ls_types::{
+        FileOperationFilter, FileOperationPattern, FileOperationRegistrationOptions, OneOf,
+        PrepareRenameResponse, RenameFilesParams, RenameOptions, RenameParams,
+        TextDocumentPositionParams, TextEdit, Uri, WorkspaceEdit,
+    },
+};
+
+use crate::{
+    reactor::Reactor,
+    server::{RenameFeature, RenameFilesFeature},
+    workspace::Workspace,
+};
+
+pub fn rename_capability() -> OneOf<bool, RenameOptions> {
+    OneOf::Right(RenameOptions {
+        prepare_provider: Some(true),
+        work_done_progress_options: Default::default(),
+    })
+}
+
+pub fn will_rename_capability() -> FileOperationRegistrationOptions {
+    FileOperationRegistrationOptions {
+        filters: vec![FileOperationFilter {
+            pattern: FileOperationPattern {
+                glob: "**".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }],
+    }
+}
+
+pub(crate) fn canonicalize(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+// the new path does not exist yet
+fn canonicalize_new(path: &Path) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => canonicalize(parent).join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn relative_path(from_dir: &Path, to: &Path) -> String {
+    let from: Vec<Component> = from_dir.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut parts: Vec<String> = vec!["..".to_owned(); from.len() - common];
+    parts.extend(
+        to[common..]
+            .iter()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned()),
+    );
+    parts.join("/")
+}
+
+// a renamed directory moves everything below it
+fn renamed_target(target: &Path, renames: &[(PathBuf, PathBuf)]) -> Option<PathBuf> {
+    renames.iter().find_map(|(old, new)| {
+        target
+            .strip_prefix(old)
+            .ok()
+            .map(|rest| match rest.as_os_str().is_empty() {
+                true => new.clone(),
+                false => new.join(rest),
+            })
+    })
+}
+
+pub(crate) fn file_path(uri: &str) -> Option<PathBuf> {
+    Uri::from_str(uri)
+        .ok()?
+        .to_file_path()
+        .map(|path| path.into_owned())
+}
+
+impl RenameFilesFeature for Workspace {
+    #[allow(clippy::mutable_key_type)]
+    async fn on_will_rename_files(
+        &self,
+        params: RenameFilesParams,
+    ) -> JsonRpcResult<Option<WorkspaceEdit>> {
+        let renames: Vec<(PathBuf, PathBuf)> = params
+            .files
+            .iter()
+            .filter_map(|rename| {
+                Some((
+                    canonicalize(&file_path(&rename.old_uri)?),
+                    canonicalize_new(&file_path(&rename.new_uri)?),
+                ))
+            })
+            .collect();
+        let config = self.get_config().await;
+        let read_guard = self.reactors.read().await;
+        let mut changes: HashMap<Uri, Vec<TextEdit>> = HashMap::new();
+        for (uri, reactor) in read_guard.iter() {
+            let dir = canonicalize(&reactor.get_document().dir());
+            for reference in reactor.get_analysis().get_path_references() {
+                // paths which are mapped by the configuration are left as they are
+                if config.map_import_path(&reference.text) != reference.text {
+                    continue;
+                }
+                if let Some(new_target) = renamed_target(&reference.target, &renames) {
+                    let new_text = match Path::new(&reference.text).is_absolute() {
+                        true => new_target.to_string_lossy().into_owned(),
+                        false => relative_path(&dir, &new_target),
+                    };
+                    changes
+                        .entry(uri.clone())
+                        .or_default()
+                        .push(TextEdit::new(reference.range, new_text));
+                }
+            }
+        }
+        if changes.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(WorkspaceEdit {
+            changes: Some(changes),
+            ..Default::default()
+        }))
+    }
+}
+
+// `-`, `.` and `:` are allowed in identifiers only when escaped with a backslash
+fn is_plain_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || matches!(c, '_' | '$' | '@'))
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '$' | '@'))
+}
+
+impl RenameFeature for Reactor {
+    async fn on_prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> JsonRpcResult<Option<PrepareRenameResponse>> {
+        Ok(self
+            .find_namespace_references(&params.position, false)
+            .map(|(range, _)| PrepareRenameResponse::Range(range)))
+    }
+
+    async fn on_rename(&self, params: RenameParams) -> JsonRpcResult<Option<WorkspaceEdit>> {
+        let Some((_, ranges)) =
+            self.find_namespace_references(&params.text_document_position.position, true)
+        else {
+            return Ok(None);
+        };
+        if !is_plain_identifier(&params.new_name) {
+            return Err(JsonRpcError::invalid_params(format!(
+                "'{}' is not a valid name, it would need escaping with a backslash.",
+                params.new_name
+            )));
+        }
+        let edits = ranges
+            .into_iter()
+            .map(|range| TextEdit::new(range, params.new_name.clone()))
+            .collect();
+        Ok(Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(self.get_document().uri(), edits)])),
+            ..Default::default()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tower_lsp_server::ls_types::{
+        DidOpenTextDocumentParams, FileRename, Position, PrepareRenameResponse, Range,
+        RenameFilesParams, RenameParams, TextDocumentIdentifier, TextDocumentItem,
+        TextDocumentPositionParams, Uri,
+    };
+
+    use crate::{
+        reactor::Reactor,
+        server::{RenameFeature, RenameFilesFeature},
+        utils,
+        workspace::Workspace,
+    };
+
+    fn position_params(reactor: &Reactor, position: Position) -> TextDocumentPositionParams {
+        TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier {
+                uri: reactor.get_document().uri(),
+            },
+            position,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rename_macro() {
+        let uri = Uri::from_str("file:///tmp/rename.ftl").unwrap();
+        let source =
+            "<#import \"lib.ftl\" as lib>\n<#macro foo>\n</#macro>\n<@foo/>\n<@lib.bar/>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+
+        let prepared = reactor
+            .on_prepare_rename(position_params(&reactor, Position::new(3, 3)))
+            .await
+            .unwrap();
+        assert_eq!(
+            prepared,
+            Some(PrepareRenameResponse::Range(Range::new(
+                Position::new(3, 2),
+                Position::new(3, 5)
+            )))
+        );
+
+        let rename = |position, new_name: &str| RenameParams {
+            text_document_position: position_params(&reactor, position),
+            new_name: new_name.to_owned(),
+            work_done_progress_params: Default::default(),
+        };
+        let edit = reactor
+            .on_rename(rename(Position::new(1, 9), "baz"))
+            .await
+            .unwrap()
+            .unwrap();
+        #[allow(clippy::mutable_key_type)]
+        let changes = edit.changes.unwrap();
+        let edits = &changes[&uri];
+        assert_eq!(edits.len(), 2);
+        assert!(edits.iter().all(|edit| edit.new_text == "baz"));
+        assert_eq!(edits[0].range.start, Position::new(1, 8));
+        assert_eq!(edits[1].range.start, Position::new(3, 2));
+
+        // the import alias is renamed along with its calls
+        let edit = reactor
+            .on_rename(rename(Position::new(4, 3), "util"))
+            .await
+            .unwrap()
+            .unwrap();
+        #[allow(clippy::mutable_key_type)]
+        let changes = edit.changes.unwrap();
+        assert_eq!(changes[&uri].len(), 2);
+
+        // names which need escaping are refused
+        assert!(
+            reactor
+                .on_rename(rename(Position::new(1, 9), "my-macro"))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_will_rename_imported_file() {
+        let source = "<#import \"lib.ftl\" as lib>\n<#include 'lib.ftl'>\n<#import \"other.ftl\" as other>\n";
+        let dir = utils::fixture_dir(
+            "will_rename",
+            &[
+                ("lib.ftl", "<#macro bar>\n</#macro>\n"),
+                ("other.ftl", ""),
+                ("sub/placeholder.ftl", ""),
+                ("main.ftl", source),
+            ],
+        );
+        let main_uri = Uri::from_file_path(dir.join("main.ftl")).unwrap();
+        let workspace = Workspace::new();
+        workspace
+            .on_did_open(&DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: main_uri.clone(),
+                    language_id: "ftl".to_owned(),
+                    version: 0,
+                    text: source.to_owned(),
+                },
+            })
+            .await;
+
+        let edit = workspace
+            .on_will_rename_files(RenameFilesParams {
+                files: vec![FileRename {
+                    old_uri: Uri::from_file_path(dir.join("lib.ftl"))
+                        .unwrap()
+                        .to_string(),
+                    new_uri: Uri::from_file_path(dir.join("sub/util.ftl"))
+                        .unwrap()
+                        .to_string(),
+                }],
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        #[allow(clippy::mutable_key_type)]
+        let changes = edit.changes.unwrap();
+        let edits = &changes[&main_uri];
+        assert_eq!(edits.len(), 2);
+        assert!(edits.iter().all(|edit| edit.new_text == "sub/util.ftl"));
+        assert_eq!(edits[0].range.start, Position::new(0, 10));
+        assert_eq!(edits[0].range.end, Position::new(0, 17));
+        assert_eq!(edits[1].range.start, Position::new(1, 11));
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use tower_lsp_server::{
+    jsonrpc::Result as JsonRpcResult,
+    ls_types::{
+        Position, Range, SelectionRange, SelectionRangeParams, SelectionRangeProviderCapability,
+    },
+};
+
+use crate::{reactor::Reactor, server::SelectionRangeFeature, utils};
+
+pub fn selection_range_capability() -> SelectionRangeProviderCapability {
+    SelectionRangeProviderCapability::Simple(true)
+}
+
+impl Reactor {
+    // from the innermost named node out to the root
+    fn selection_range(&self, position: &Position) -> Option<SelectionRange> {
+        let doc = self.get_document();
+        let point = utils::lsp_position_to_parser_point(doc, position);
+        let mut node_cursor = self.get_parser().get_node_at_point(point);
+        let mut ranges = vec![];
+        while let Some(node) = node_cursor {
+            let range = utils::parser_node_to_document_range(doc, &node);
+            // a parent spanning the same range doesn't expand the selection
+            if ranges.last() != Some(&range) {
+                ranges.push(range);
+            }
+            node_cursor = node.parent();
+        }
+        ranges.into_iter().rev().fold(None, |parent, range| {
+            Some(SelectionRange {
+                range,
+                parent: parent.map(Box::new),
+            })
+        })
+    }
+}
+
+impl SelectionRangeFeature for Reactor {
+    async fn on_selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> JsonRpcResult<Option<Vec<SelectionRange>>> {
+        // the result must have one entry per requested position
+        Ok(Some(
+            params
+                .positions
+                .iter()
+                .map(|position| {
+                    self.selection_range(position).unwrap_or(SelectionRange {
+                        range: Range::new(*position, *position),
+                        parent: None,
+                    })
+                })
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tower_lsp_server::ls_types::{
+        Position, Range, SelectionRangeParams, TextDocumentIdentifier, Uri,
+    };
+
+    use crate::{reactor::Reactor, server::SelectionRangeFeature};
+
+    #[tokio::test]
+    async fn test_expand_selection() {
+        let uri = Uri::from_str("file:///tmp/selection.ftl").unwrap();
+        let source = "<#if a>\n${x + y}\n</#if>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let selections = reactor
+            .on_selection_range(SelectionRangeParams {
+                text_document: TextDocumentIdentifier { uri },
+                positions: vec![Position::new(1, 6), Position::new(2, 1)],
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(selections.len(), 2);
+
+        let mut ranges = vec![];
+        let mut selection = Some(&selections[0]);
+        while let Some(current) = selection {
+            ranges.push(current.range);
+            selection = current.parent.as_deref();
+        }
+        // "y" is the innermost, the whole document is the outermost
+        assert_eq!(
+            ranges[0],
+            Range::new(Position::new(1, 6), Position::new(1, 7))
+        );
+        assert_eq!(
+            *ranges.last().unwrap(),
+            Range::new(Position::new(0, 0), Position::new(3, 0))
+        );
+        // every range contains the previous one
+        assert!(
+            ranges
+                .windows(2)
+                .all(|pair| pair[1].start <= pair[0].start && pair[0].end <= pair[1].end)
+        );
+    }
+}
@@ -2,24 +2,42 @@
 // Licensed under the BSD 3-Clause License.
 // SPDX-License-Identifier: BSD-3-Clause
 
-use std::sync::Arc;
+use std::{path::Path, sync::Arc};
 
 use tokio::sync::RwLock;
 use tower_lsp_server::{
     Client, LanguageServer, jsonrpc,
     ls_types::{
-        CodeActionOrCommand, CodeActionParams, CompletionItem, CompletionParams,
+        CallHierarchyIncomingCall, CallHierarchyIncomingCallsParams, CallHierarchyItem,
+        CallHierarchyOutgoingCall, CallHierarchyOutgoingCallsParams, CallHierarchyPrepareParams,
+        CodeAction, CodeActionOrCommand, CodeActionParams, CompletionItem, CompletionParams,
         CompletionResponse, DeleteFilesParams, DidChangeTextDocumentParams,
         DidChangeWatchedFilesParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
         DocumentDiagnosticParams, DocumentDiagnosticReportResult, DocumentFormattingParams,
-        FoldingRange, FoldingRangeParams, GotoDefinitionParams, GotoDefinitionResponse, Hover,
-        HoverParams, InitializeParams, InitializeResult, InitializedParams, SemanticTokensParams,
-        SemanticTokensResult, TextEdit,
+        DocumentHighlight, DocumentHighlightParams, DocumentLink, DocumentLinkParams,
+        DocumentOnTypeFormattingParams, DocumentRangeFormattingParams, DocumentSymbolParams,
+        DocumentSymbolResponse, ExecuteCommandParams, FoldingRange, FoldingRangeParams,
+        GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverParams, InitializeParams,
+        InitializeResult, InitializedParams, InlayHint, InlayHintParams, InlineValue,
+        InlineValueParams, LSPAny, Location, PrepareRenameResponse, ReferenceParams,
+        RenameFilesParams, RenameParams, SelectionRange, SelectionRangeParams,
+        SemanticTokensDeltaParams, SemanticTokensFullDeltaResult, SemanticTokensParams,
+        SemanticTokensRangeParams, SemanticTokensRangeResult, SemanticTokensResult, SignatureHelp,
+        SignatureHelpParams, TextDocumentPositionParams, TextEdit, WorkspaceDiagnosticParams,
+        WorkspaceDiagnosticReportResult, WorkspaceEdit, WorkspaceSymbolParams,
+        WorkspaceSymbolResponse,
     },
 };
 use tracing::{self, instrument};
 
-use crate::{client::save_client, window_log_info, workspace::Workspace};
+use crate::{
+    client::save_client,
+    import::{EAGER_INDEX_TOKEN, ImportResolution, ResolveImportParams},
+    progress::ClientProgress,
+    signature::{MacroSignature, MacroSignatureParams},
+    window_log_info,
+    workspace::Workspace,
+};
 
 #[derive(Debug)]
 pub struct Server {
@@ -40,6 +58,23 @@ impl Server {
     }
 }
 
+// custom requests
+impl Server {
+    pub async fn resolve_import(
+        &self,
+        params: ResolveImportParams,
+    ) -> jsonrpc::Result<ImportResolution> {
+        self.workspace.on_resolve_import(params).await
+    }
+
+    pub async fn macro_signature(
+        &self,
+        params: MacroSignatureParams,
+    ) -> jsonrpc::Result<Option<MacroSignature>> {
+        self.workspace.on_macro_signature(params).await
+    }
+}
+
 pub trait Initializer {
     async fn on_initialize(&self, params: InitializeParams) -> InitializeResult;
 }
@@ -52,6 +87,16 @@ impl LanguageServer for Server {
 
     async fn initialized(&self, _: InitializedParams) {
         window_log_info!("[Server] initialized.");
+        let root_path = self.root_path.read().await.clone();
+        if self.workspace.get_config().await.eager_index && !root_path.is_empty() {
+            // `initialize` has returned already, the requests are served meanwhile
+            let imports = self.workspace.imports.clone();
+            tokio::spawn(async move {
+                let mut progress = ClientProgress::create(EAGER_INDEX_TOKEN).await;
+                let analyzed = imports.warm_up(Path::new(&root_path), &mut progress).await;
+                tracing::info!("eager index of {}: {} templates", root_path, analyzed);
+            });
+        }
     }
 
     async fn shutdown(&self) -> jsonrpc::Result<()> {
@@ -80,6 +125,20 @@ impl LanguageServer for Server {
         self.workspace.on_did_delete_files(params).await;
     }
 
+    async fn will_delete_files(
+        &self,
+        params: DeleteFilesParams,
+    ) -> jsonrpc::Result<Option<WorkspaceEdit>> {
+        self.workspace.on_will_delete_files(params).await
+    }
+
+    async fn will_rename_files(
+        &self,
+        params: RenameFilesParams,
+    ) -> jsonrpc::Result<Option<WorkspaceEdit>> {
+        self.workspace.on_will_rename_files(params).await
+    }
+
     // LSP request/response
     async fn diagnostic(
         &self,
@@ -88,6 +147,13 @@ impl LanguageServer for Server {
         self.workspace.on_diagnostic(params).await
     }
 
+    async fn workspace_diagnostic(
+        &self,
+        params: WorkspaceDiagnosticParams,
+    ) -> jsonrpc::Result<WorkspaceDiagnosticReportResult> {
+        self.workspace.on_workspace_diagnostic(params).await
+    }
+
     async fn semantic_tokens_full(
         &self,
         params: SemanticTokensParams,
@@ -95,6 +161,20 @@ impl LanguageServer for Server {
         self.workspace.on_semantic_tokens_full(params).await
     }
 
+    async fn semantic_tokens_range(
+        &self,
+        params: SemanticTokensRangeParams,
+    ) -> jsonrpc::Result<Option<SemanticTokensRangeResult>> {
+        self.workspace.on_semantic_tokens_range(params).await
+    }
+
+    async fn semantic_tokens_full_delta(
+        &self,
+        params: SemanticTokensDeltaParams,
+    ) -> jsonrpc::Result<Option<SemanticTokensFullDeltaResult>> {
+        self.workspace.on_semantic_tokens_full_delta(params).await
+    }
+
     async fn hover(&self, params: HoverParams) -> jsonrpc::Result<Option<Hover>> {
         self.workspace.on_hover(params).await
     }
@@ -106,6 +186,10 @@ impl LanguageServer for Server {
         self.workspace.on_completion(params).await
     }
 
+    async fn completion_resolve(&self, item: CompletionItem) -> jsonrpc::Result<CompletionItem> {
+        self.workspace.on_completion_resolve(item).await
+    }
+
     #[instrument(skip_all)]
     async fn goto_definition(
         &self,
@@ -122,6 +206,22 @@ impl LanguageServer for Server {
         self.workspace.on_formatting(params).await
     }
 
+    #[instrument(skip_all)]
+    async fn range_formatting(
+        &self,
+        params: DocumentRangeFormattingParams,
+    ) -> jsonrpc::Result<Option<Vec<TextEdit>>> {
+        self.workspace.on_range_formatting(params).await
+    }
+
+    #[instrument(skip_all)]
+    async fn on_type_formatting(
+        &self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> jsonrpc::Result<Option<Vec<TextEdit>>> {
+        self.workspace.on_type_formatting(params).await
+    }
+
     async fn folding_range(
         &self,
         params: FoldingRangeParams,
@@ -129,12 +229,112 @@ impl LanguageServer for Server {
         self.workspace.on_folding_range(params).await
     }
 
+    async fn signature_help(
+        &self,
+        params: SignatureHelpParams,
+    ) -> jsonrpc::Result<Option<SignatureHelp>> {
+        self.workspace.on_signature_help(params).await
+    }
+
+    async fn selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> jsonrpc::Result<Option<Vec<SelectionRange>>> {
+        self.workspace.on_selection_range(params).await
+    }
+
+    async fn document_link(
+        &self,
+        params: DocumentLinkParams,
+    ) -> jsonrpc::Result<Option<Vec<DocumentLink>>> {
+        self.workspace.on_document_link(params).await
+    }
+
+    async fn inline_value(
+        &self,
+        params: InlineValueParams,
+    ) -> jsonrpc::Result<Option<Vec<InlineValue>>> {
+        self.workspace.on_inline_value(params).await
+    }
+
+    async fn inlay_hint(&self, params: InlayHintParams) -> jsonrpc::Result<Option<Vec<InlayHint>>> {
+        self.workspace.on_inlay_hint(params).await
+    }
+
+    async fn document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> jsonrpc::Result<Option<Vec<DocumentHighlight>>> {
+        self.workspace.on_document_highlight(params).await
+    }
+
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> jsonrpc::Result<Option<DocumentSymbolResponse>> {
+        self.workspace.on_document_symbol(params).await
+    }
+
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> jsonrpc::Result<Option<WorkspaceSymbolResponse>> {
+        self.workspace.on_workspace_symbol(params).await
+    }
+
+    async fn references(&self, params: ReferenceParams) -> jsonrpc::Result<Option<Vec<Location>>> {
+        self.workspace.on_references(params).await
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> jsonrpc::Result<Option<PrepareRenameResponse>> {
+        self.workspace.on_prepare_rename(params).await
+    }
+
+    async fn rename(&self, params: RenameParams) -> jsonrpc::Result<Option<WorkspaceEdit>> {
+        self.workspace.on_rename(params).await
+    }
+
     async fn code_action(
         &self,
         params: CodeActionParams,
     ) -> jsonrpc::Result<Option<Vec<CodeActionOrCommand>>> {
         self.workspace.on_code_action(params).await
     }
+
+    async fn code_action_resolve(&self, action: CodeAction) -> jsonrpc::Result<CodeAction> {
+        self.workspace.on_code_action_resolve(action).await
+    }
+
+    async fn prepare_call_hierarchy(
+        &self,
+        params: CallHierarchyPrepareParams,
+    ) -> jsonrpc::Result<Option<Vec<CallHierarchyItem>>> {
+        self.workspace.on_prepare_call_hierarchy(params).await
+    }
+
+    async fn incoming_calls(
+        &self,
+        params: CallHierarchyIncomingCallsParams,
+    ) -> jsonrpc::Result<Option<Vec<CallHierarchyIncomingCall>>> {
+        self.workspace.on_incoming_calls(params).await
+    }
+
+    async fn outgoing_calls(
+        &self,
+        params: CallHierarchyOutgoingCallsParams,
+    ) -> jsonrpc::Result<Option<Vec<CallHierarchyOutgoingCall>>> {
+        self.workspace.on_outgoing_calls(params).await
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> jsonrpc::Result<Option<LSPAny>> {
+        self.workspace.on_execute_command(params).await
+    }
 }
 
 // LSP features
@@ -145,6 +345,34 @@ pub trait ActionFeature {
     ) -> jsonrpc::Result<Option<Vec<CodeActionOrCommand>>>;
 }
 
+pub trait ActionResolveFeature {
+    async fn on_code_action_resolve(&self, action: CodeAction) -> jsonrpc::Result<CodeAction>;
+}
+
+pub trait CallHierarchyFeature {
+    async fn on_prepare_call_hierarchy(
+        &self,
+        params: CallHierarchyPrepareParams,
+    ) -> jsonrpc::Result<Option<Vec<CallHierarchyItem>>>;
+
+    async fn on_incoming_calls(
+        &self,
+        params: CallHierarchyIncomingCallsParams,
+    ) -> jsonrpc::Result<Option<Vec<CallHierarchyIncomingCall>>>;
+
+    async fn on_outgoing_calls(
+        &self,
+        params: CallHierarchyOutgoingCallsParams,
+    ) -> jsonrpc::Result<Option<Vec<CallHierarchyOutgoingCall>>>;
+}
+
+pub trait CommandFeature {
+    async fn on_execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> jsonrpc::Result<Option<LSPAny>>;
+}
+
 pub trait CompletionFeature {
     async fn on_completion(
         &self,
@@ -154,6 +382,17 @@ pub trait CompletionFeature {
     fn list_macro_definitions(&self) -> Vec<CompletionItem>;
 }
 
+pub trait CompletionResolveFeature {
+    async fn on_completion_resolve(&self, item: CompletionItem) -> jsonrpc::Result<CompletionItem>;
+}
+
+pub trait DeleteFilesFeature {
+    async fn on_will_delete_files(
+        &self,
+        params: DeleteFilesParams,
+    ) -> jsonrpc::Result<Option<WorkspaceEdit>>;
+}
+
 pub trait DiagnosticFeature {
     async fn on_diagnostic(
         &self,
@@ -161,6 +400,41 @@ pub trait DiagnosticFeature {
     ) -> jsonrpc::Result<DocumentDiagnosticReportResult>;
 }
 
+pub trait WorkspaceDiagnosticFeature {
+    async fn on_workspace_diagnostic(
+        &self,
+        params: WorkspaceDiagnosticParams,
+    ) -> jsonrpc::Result<WorkspaceDiagnosticReportResult>;
+}
+
+pub trait DocumentHighlightFeature {
+    async fn on_document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> jsonrpc::Result<Option<Vec<DocumentHighlight>>>;
+}
+
+pub trait DocumentLinkFeature {
+    async fn on_document_link(
+        &self,
+        params: DocumentLinkParams,
+    ) -> jsonrpc::Result<Option<Vec<DocumentLink>>>;
+}
+
+pub trait DocumentSymbolFeature {
+    async fn on_document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> jsonrpc::Result<Option<DocumentSymbolResponse>>;
+}
+
+pub trait WorkspaceSymbolFeature {
+    async fn on_workspace_symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> jsonrpc::Result<Option<WorkspaceSymbolResponse>>;
+}
+
 pub trait FoldingFeature {
     async fn on_folding_range(
         &self,
@@ -175,6 +449,20 @@ pub trait FormatFeature {
     ) -> jsonrpc::Result<Option<Vec<TextEdit>>>;
 }
 
+pub trait OnTypeFormatFeature {
+    async fn on_type_formatting(
+        &self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> jsonrpc::Result<Option<Vec<TextEdit>>>;
+}
+
+pub trait RangeFormatFeature {
+    async fn on_range_formatting(
+        &self,
+        params: DocumentRangeFormattingParams,
+    ) -> jsonrpc::Result<Option<Vec<TextEdit>>>;
+}
+
 pub trait GotoFeature {
     async fn on_goto_definition(
         &self,
@@ -187,9 +475,84 @@ pub trait HoverFeature {
     async fn on_hover(&self, params: HoverParams) -> jsonrpc::Result<Option<Hover>>;
 }
 
+pub trait ReferencesFeature {
+    async fn on_references(
+        &self,
+        params: ReferenceParams,
+    ) -> jsonrpc::Result<Option<Vec<Location>>>;
+}
+
+pub trait RenameFeature {
+    async fn on_prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> jsonrpc::Result<Option<PrepareRenameResponse>>;
+
+    async fn on_rename(&self, params: RenameParams) -> jsonrpc::Result<Option<WorkspaceEdit>>;
+}
+
+pub trait RenameFilesFeature {
+    async fn on_will_rename_files(
+        &self,
+        params: RenameFilesParams,
+    ) -> jsonrpc::Result<Option<WorkspaceEdit>>;
+}
+
+pub trait SignatureHelpFeature {
+    async fn on_signature_help(
+        &self,
+        params: SignatureHelpParams,
+    ) -> jsonrpc::Result<Option<SignatureHelp>>;
+}
+
+pub trait MacroSignatureFeature {
+    async fn on_macro_signature(
+        &self,
+        params: MacroSignatureParams,
+    ) -> jsonrpc::Result<Option<MacroSignature>>;
+}
+
+pub trait ResolveImportFeature {
+    async fn on_resolve_import(
+        &self,
+        params: ResolveImportParams,
+    ) -> jsonrpc::Result<ImportResolution>;
+}
+
+pub trait InlineValueFeature {
+    async fn on_inline_value(
+        &self,
+        params: InlineValueParams,
+    ) -> jsonrpc::Result<Option<Vec<InlineValue>>>;
+}
+
+pub trait InlayHintFeature {
+    async fn on_inlay_hint(
+        &self,
+        params: InlayHintParams,
+    ) -> jsonrpc::Result<Option<Vec<InlayHint>>>;
+}
+
+pub trait SelectionRangeFeature {
+    async fn on_selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> jsonrpc::Result<Option<Vec<SelectionRange>>>;
+}
+
 pub trait SemanticTokenFeature {
     async fn on_semantic_tokens_full(
         &self,
         params: SemanticTokensParams,
     ) -> jsonrpc::Result<Option<SemanticTokensResult>>;
+
+    async fn on_semantic_tokens_range(
+        &self,
+        params: SemanticTokensRangeParams,
+    ) -> jsonrpc::Result<Option<SemanticTokensRangeResult>>;
+
+    async fn on_semantic_tokens_full_delta(
+        &self,
+        params: SemanticTokensDeltaParams,
+    ) -> jsonrpc::Result<Option<SemanticTokensFullDeltaResult>>;
 }
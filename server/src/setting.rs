@@ -0,0 +1,191 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use tower_lsp_server::ls_types::{Diagnostic, Position, Range};
+use tree_sitter::Node;
+
+use crate::{
+    analysis::{Analysis, SettingAnalysis},
+    diagnosis::Scenario,
+    doc::TextDocument,
+    utils,
+};
+
+// https://freemarker.apache.org/docs/ref_directive_setting.html
+pub const SETTING_NAMES: &[&str] = &[
+    "locale",
+    "number_format",
+    "c_format",
+    "boolean_format",
+    "date_format",
+    "time_format",
+    "datetime_format",
+    "time_zone",
+    "sql_date_and_time_time_zone",
+    "url_escaping_charset",
+    "output_encoding",
+    "classic_compatible",
+];
+
+// the settings which `?string` without a format argument depends on
+const FORMAT_SETTING_NAMES: &[&str] = &[
+    "number_format",
+    "boolean_format",
+    "date_format",
+    "time_format",
+    "datetime_format",
+];
+
+// https://freemarker.apache.org/docs/ref_directive_ftl.html, with a hint of the value
+pub const FTL_ATTRIBUTES: &[(&str, &str)] = &[
+    ("encoding", "\"UTF-8\""),
+    ("strip_whitespace", "true"),
+    ("strip_text", "false"),
+    ("strict_syntax", "true"),
+    ("ns_prefixes", "{\"D\": \"http://example.com/ns\"}"),
+    ("attributes", "{\"key\": \"value\"}"),
+    ("output_format", "\"HTML\""),
+    ("auto_esc", "true"),
+];
+
+#[derive(Clone, Debug)]
+pub struct Setting {
+    // normalized to snake_case
+    pub name: String,
+    pub name_range: Range,
+    pub range: Range,
+}
+
+// setting names are accepted in camelCase as well
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            snake.push('_');
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+fn parse_setting(node: &Node, doc: &TextDocument) -> Option<Setting> {
+    let directive = utils::error_node_directive(node, doc)
+        .filter(|directive| directive.name == "setting" && !directive.closing)?;
+    let rest = directive.rest.as_str();
+    let name_start = rest.len() - rest.trim_start().len();
+    if name_start == 0 {
+        return None;
+    }
+    let name: String = rest[name_start..]
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect();
+    if name.is_empty() {
+        return None;
+    }
+    let name_byte = directive.rest_byte + name_start;
+    Some(Setting {
+        name: to_snake_case(&name),
+        name_range: Range {
+            start: doc.byte_to_position(name_byte),
+            end: doc.byte_to_position(name_byte + name.len()),
+        },
+        range: utils::parser_node_to_document_range(doc, node),
+    })
+}
+
+pub fn is_known_setting(name: &str) -> bool {
+    SETTING_NAMES.contains(&name)
+}
+
+// the header accepts camelCase as well, e.g. "stripWhitespace"
+pub fn is_known_ftl_attribute(name: &str) -> bool {
+    let name = to_snake_case(name);
+    FTL_ATTRIBUTES
+        .iter()
+        .any(|(attribute, _)| *attribute == name)
+}
+
+// the nearest format setting which precedes the position
+pub fn find_format_setting<'a>(
+    settings: &'a [Setting],
+    position: &Position,
+) -> Option<&'a Setting> {
+    settings.iter().rev().find(|setting| {
+        setting.range.end <= *position && FORMAT_SETTING_NAMES.contains(&setting.name.as_str())
+    })
+}
+
+impl SettingAnalysis for Analysis {
+    fn analyze_setting(&mut self, node: &Node, doc: &TextDocument) -> bool {
+        let Some(setting) = parse_setting(node, doc) else {
+            return false;
+        };
+        if !is_known_setting(&setting.name) {
+            self.add_diagnostic(Diagnostic {
+                range: setting.name_range,
+                message: format!("Unknown setting '{}'.", setting.name),
+                ..Scenario::UNKNOWN_SETTING.into()
+            });
+        }
+        self.add_setting(setting);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tower_lsp_server::ls_types::{NumberOrString, Uri};
+
+    use crate::reactor::Reactor;
+
+    #[test]
+    fn test_setting_names() {
+        let uri = Uri::from_str("file:///tmp/setting.ftl").unwrap();
+        let source =
+            "<#setting locale=\"en_US\">\n<#setting numberFormat=\"0.##\">\n<#setting foo=1>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let settings = reactor.get_analysis().get_settings();
+        assert_eq!(settings.len(), 3);
+        assert_eq!(settings[1].name, "number_format");
+        let diagnostics = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items;
+        // only the unknown setting is reported
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("unknown_setting".to_owned()))
+        );
+        assert_eq!(diagnostics[0].range.start.line, 2);
+        assert_eq!(diagnostics[0].range.start.character, 10);
+        assert_eq!(diagnostics[0].range.end.character, 13);
+    }
+
+    #[test]
+    fn test_ftl_attributes() {
+        let uri = Uri::from_str("file:///tmp/setting.ftl").unwrap();
+        let source = "<#ftl encoding=\"UTF-8\" stripWhitespace=true foo=1>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let diagnostics = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items;
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("unknown_ftl_attribute".to_owned()))
+        );
+        assert_eq!(diagnostics[0].message, "Unknown <#ftl> attribute 'foo'.");
+        assert_eq!(diagnostics[0].range.start.character, 44);
+        assert_eq!(diagnostics[0].range.end.character, 47);
+    }
+}
@@ -0,0 +1,382 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tower_lsp_server::{
+    jsonrpc::{Error as JsonRpcError, Result as JsonRpcResult},
+    ls_types::{
+        ParameterInformation, ParameterLabel, Position, SignatureHelp, SignatureHelpOptions,
+        SignatureHelpParams, SignatureInformation, TextDocumentIdentifier, Uri,
+    },
+};
+
+use crate::{
+    analysis::{Analysis, MacroCall},
+    reactor::Reactor,
+    server::{MacroSignatureFeature, SignatureHelpFeature},
+    workspace::{GET_REACTOR_EXPECT, Workspace},
+};
+
+pub const MACRO_SIGNATURE: &str = "freemarker/macroSignature";
+
+pub fn signature_help_capability() -> SignatureHelpOptions {
+    SignatureHelpOptions {
+        trigger_characters: Some(vec![" ".to_owned(), "=".to_owned()]),
+        ..Default::default()
+    }
+}
+
+// "b=1" and "c..." are both named "b" and "c"
+pub(crate) fn parameter_name(label: &str) -> &str {
+    label
+        .split('=')
+        .next()
+        .unwrap_or(label)
+        .trim_end_matches("...")
+}
+
+// A declared parameter of a macro, reported by `freemarker/macroSignature`
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MacroParameter {
+    pub name: String,
+    // the default expression, as written
+    pub default: Option<String>,
+    pub catch_all: bool,
+}
+
+impl MacroParameter {
+    pub fn from_label(label: &str) -> Self {
+        Self {
+            name: parameter_name(label).to_owned(),
+            default: label
+                .split_once('=')
+                .map(|(_, default)| default.trim().to_owned()),
+            catch_all: label.ends_with("..."),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MacroSignature {
+    pub name: String,
+    pub parameters: Vec<MacroParameter>,
+    pub uses_nested: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MacroSignatureParams {
+    pub text_document: TextDocumentIdentifier,
+    // "foo", or "lib.foo" for a macro of an imported template
+    pub name: String,
+}
+
+fn utf16_len(text: &str) -> u32 {
+    text.encode_utf16().count() as u32
+}
+
+fn macro_signature(name: &str, parameters: &[String], typed: &str) -> SignatureInformation {
+    let mut label = name.to_owned();
+    let mut parameter_infos = vec![];
+    for parameter in parameters {
+        label.push(' ');
+        let start = utf16_len(&label);
+        label.push_str(parameter);
+        parameter_infos.push(ParameterInformation {
+            label: ParameterLabel::LabelOffsets([start, utf16_len(&label)]),
+            documentation: None,
+        });
+    }
+    // the parameter whose name is being typed, e.g. "<@foo b=|"
+    let current = typed
+        .split_whitespace()
+        .next_back()
+        .and_then(|word| word.split('=').next());
+    let active_parameter = current.and_then(|current| {
+        parameters
+            .iter()
+            .position(|parameter| parameter_name(parameter) == current)
+            .map(|index| index as u32)
+    });
+    SignatureInformation {
+        label,
+        documentation: None,
+        parameters: Some(parameter_infos),
+        active_parameter,
+    }
+}
+
+impl Reactor {
+    // the macro call being typed and the text typed after its name, e.g. " a=1 b" of "<@foo a=1 b|"
+    pub(crate) fn open_macro_call(&self, position: &Position) -> Option<(&MacroCall, String)> {
+        let doc = self.get_document();
+        // the call being typed is not closed yet, so the position is past its node
+        let cursor_byte = doc.position_to_byte(position);
+        self.get_analysis()
+            .get_macro_calls()
+            .iter()
+            .rev()
+            .find(|call| call.range.end <= *position)
+            .map(|call| {
+                let typed = doc.get_ranged_text(doc.position_to_byte(&call.range.end)..cursor_byte);
+                (call, typed)
+            })
+            .filter(|(_, typed)| !typed.contains('>'))
+    }
+}
+
+impl Workspace {
+    // looks up a macro of the same template, or of an imported one for "lib.foo"
+    async fn resolve_macro<T>(
+        &self,
+        reactors: &HashMap<Uri, Reactor>,
+        analysis: &Analysis,
+        namespace: &str,
+        member: Option<&str>,
+        lookup: impl Fn(&Analysis, &str) -> Option<T>,
+    ) -> Option<T> {
+        let Some(member) = member else {
+            return lookup(analysis, namespace);
+        };
+        let import_uri = analysis.get_import_alias(namespace)?;
+        match reactors.get(import_uri) {
+            Some(imported) => lookup(imported.get_analysis(), member),
+            None => lookup(&*self.imports.get(import_uri).await?, member),
+        }
+    }
+
+    // the parameters of a macro of the same template, or of an imported one for "<@lib.foo"
+    pub(crate) async fn resolve_macro_parameters(
+        &self,
+        reactors: &HashMap<Uri, Reactor>,
+        analysis: &Analysis,
+        call: &MacroCall,
+    ) -> Option<Vec<String>> {
+        self.resolve_macro(
+            reactors,
+            analysis,
+            &call.namespace,
+            call.member.as_deref(),
+            |analysis, name| analysis.get_macro_parameters(name).cloned(),
+        )
+        .await
+    }
+}
+
+impl SignatureHelpFeature for Workspace {
+    async fn on_signature_help(
+        &self,
+        params: SignatureHelpParams,
+    ) -> JsonRpcResult<Option<SignatureHelp>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+        let read_guard = self.reactors.read().await;
+        let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
+        let Some((call, typed)) = reactor.open_macro_call(&position) else {
+            return Ok(None);
+        };
+        let name = match &call.member {
+            Some(member) => format!("{}.{}", call.namespace, member),
+            None => call.namespace.clone(),
+        };
+        let signature = self
+            .resolve_macro_parameters(&read_guard, reactor.get_analysis(), call)
+            .await
+            .map(|parameters| macro_signature(&name, &parameters, &typed));
+        Ok(signature.map(|signature| SignatureHelp {
+            active_parameter: signature.active_parameter,
+            signatures: vec![signature],
+            active_signature: Some(0),
+        }))
+    }
+}
+
+impl MacroSignatureFeature for Workspace {
+    async fn on_macro_signature(
+        &self,
+        params: MacroSignatureParams,
+    ) -> JsonRpcResult<Option<MacroSignature>> {
+        let read_guard = self.reactors.read().await;
+        let Some(reactor) = read_guard.get(&params.text_document.uri) else {
+            return Err(JsonRpcError::invalid_params(format!(
+                "not opened: {}",
+                params.text_document.uri.as_str()
+            )));
+        };
+        let (namespace, member) = match params.name.split_once('.') {
+            Some((namespace, member)) => (namespace, Some(member)),
+            None => (params.name.as_str(), None),
+        };
+        let signature = self
+            .resolve_macro(
+                &read_guard,
+                reactor.get_analysis(),
+                namespace,
+                member,
+                |analysis, name| {
+                    let labels = analysis.get_macro_parameters(name)?;
+                    Some(MacroSignature {
+                        name: params.name.clone(),
+                        parameters: labels
+                            .iter()
+                            .map(|label| MacroParameter::from_label(label))
+                            .collect(),
+                        uses_nested: analysis.macro_uses_nested(name),
+                    })
+                },
+            )
+            .await;
+        Ok(signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp_server::ls_types::{
+        DidOpenTextDocumentParams, ParameterLabel, Position, SignatureHelp, SignatureHelpParams,
+        TextDocumentIdentifier, TextDocumentItem, TextDocumentPositionParams, Uri,
+    };
+
+    use super::{MacroParameter, MacroSignatureParams};
+    use crate::{
+        server::{MacroSignatureFeature, SignatureHelpFeature},
+        utils,
+        workspace::Workspace,
+    };
+
+    async fn signature_help(
+        workspace: &Workspace,
+        uri: &Uri,
+        position: Position,
+    ) -> Option<SignatureHelp> {
+        workspace
+            .on_signature_help(SignatureHelpParams {
+                context: None,
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    position,
+                },
+                work_done_progress_params: Default::default(),
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_macro_signature_help() {
+        let source = "<#import \"lib.ftl\" as lib>\n<#macro foo a b=1 c...>\n</#macro>\n<@foo a=1 b\n<@lib.bar \n<@foo/>\n";
+        let dir = utils::fixture_dir(
+            "signature_help",
+            &[
+                ("lib.ftl", "<#macro bar title>\n</#macro>\n"),
+                ("main.ftl", source),
+            ],
+        );
+        let uri = Uri::from_file_path(dir.join("main.ftl")).unwrap();
+        let workspace = Workspace::new();
+        workspace
+            .on_did_open(&DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: uri.clone(),
+                    language_id: "ftl".to_owned(),
+                    version: 0,
+                    text: source.to_owned(),
+                },
+            })
+            .await;
+
+        let help = signature_help(&workspace, &uri, Position::new(3, 11))
+            .await
+            .unwrap();
+        let signature = &help.signatures[0];
+        assert_eq!(signature.label, "foo a b=1 c...");
+        let parameters = signature.parameters.as_ref().unwrap();
+        assert_eq!(parameters.len(), 3);
+        assert_eq!(parameters[1].label, ParameterLabel::LabelOffsets([6, 9]));
+        assert_eq!(help.active_parameter, Some(1));
+
+        // the macro of an imported template
+        let help = signature_help(&workspace, &uri, Position::new(4, 10))
+            .await
+            .unwrap();
+        assert_eq!(help.signatures[0].label, "lib.bar title");
+        assert_eq!(help.active_parameter, None);
+
+        // outside of any call
+        assert!(
+            signature_help(&workspace, &uri, Position::new(6, 0))
+                .await
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_macro_signature_request() {
+        let source =
+            "<#import \"lib.ftl\" as lib>\n<#macro foo a b=1 c...>\n  <#nested>\n</#macro>\n";
+        let dir = utils::fixture_dir(
+            "macro_signature",
+            &[
+                ("lib.ftl", "<#macro bar title=\"x\">\n</#macro>\n"),
+                ("main.ftl", source),
+            ],
+        );
+        let uri = Uri::from_file_path(dir.join("main.ftl")).unwrap();
+        let workspace = Workspace::new();
+        workspace
+            .on_did_open(&DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: uri.clone(),
+                    language_id: "ftl".to_owned(),
+                    version: 0,
+                    text: source.to_owned(),
+                },
+            })
+            .await;
+        let macro_signature = async |name: &str| {
+            workspace
+                .on_macro_signature(MacroSignatureParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    name: name.to_owned(),
+                })
+                .await
+                .unwrap()
+        };
+
+        let signature = macro_signature("foo").await.unwrap();
+        assert_eq!(signature.name, "foo");
+        assert_eq!(
+            signature.parameters,
+            vec![
+                MacroParameter {
+                    name: "a".to_owned(),
+                    default: None,
+                    catch_all: false,
+                },
+                MacroParameter {
+                    name: "b".to_owned(),
+                    default: Some("1".to_owned()),
+                    catch_all: false,
+                },
+                MacroParameter {
+                    name: "c".to_owned(),
+                    default: None,
+                    catch_all: true,
+                },
+            ]
+        );
+        assert!(signature.uses_nested);
+
+        // the macro of an imported template
+        let signature = macro_signature("lib.bar").await.unwrap();
+        assert_eq!(signature.parameters[0].default.as_deref(), Some("\"x\""));
+        assert!(!signature.uses_nested);
+
+        assert!(macro_signature("baz").await.is_none());
+    }
+}
@@ -0,0 +1,210 @@
+// Copyright 2025-2026 Nokia
+// Licensed under the BSD 3-Clause License.
+// SPDX-License-Identifier: BSD-3-Clause
+
+use std::str::FromStr;
+
+use tower_lsp_server::ls_types::{Diagnostic, NumberOrString};
+use tree_sitter::Node;
+use tree_sitter_freemarker::grammar::Rule;
+
+use crate::{
+    analysis::{Analysis, AnalysisContext, SuppressAnalysis},
+    config::Config,
+    doc::TextDocument,
+};
+
+const DISABLE_NEXT_LINE: &str = "lsp-disable-next-line";
+const DISABLE_FILE: &str = "lsp-disable-file";
+
+// diagnostic codes disabled by an inline comment
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suppression {
+    // none for the whole file
+    pub line: Option<u32>,
+    pub codes: Vec<String>,
+}
+
+impl Suppression {
+    fn matches(&self, code: &str, line: u32) -> bool {
+        self.line.is_none_or(|l| l == line) && self.codes.iter().any(|c| c == code)
+    }
+}
+
+// `<#-- lsp-disable-next-line undefined_macro, unknown_setting -->`
+fn parse_suppression(comment: &str, end_line: u32) -> Option<Suppression> {
+    let content = comment
+        .trim_start_matches(['<', '[', '#', '-'])
+        .trim_end_matches(['>', ']', '-'])
+        .trim();
+    let (directive, codes) = content.split_once(char::is_whitespace)?;
+    let line = match directive {
+        DISABLE_NEXT_LINE => Some(end_line + 1),
+        DISABLE_FILE => None,
+        _ => return None,
+    };
+    let codes: Vec<String> = codes
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|code| !code.is_empty())
+        .map(str::to_owned)
+        .collect();
+    (!codes.is_empty()).then_some(Suppression { line, codes })
+}
+
+impl SuppressAnalysis for Analysis {
+    fn analyze_suppression(&mut self, node: &Node, doc: &TextDocument, ctx: &mut AnalysisContext) {
+        if let Ok(Rule::Comment) = Rule::from_str(node.kind()) {
+            let text = doc.get_ranged_text(node.start_byte()..node.end_byte());
+            if let Some(suppression) = parse_suppression(&text, node.end_position().row as u32) {
+                ctx.suppressions.push(suppression);
+            }
+        }
+    }
+
+    fn apply_suppressions(&mut self, ctx: &mut AnalysisContext) {
+        if !ctx.config.disabled_diagnostics.is_empty() {
+            let config = ctx.config.clone();
+            self.retain_diagnostics(|diagnostic| !is_disabled(&config, diagnostic));
+        }
+        if ctx.suppressions.is_empty() {
+            return;
+        }
+        self.retain_diagnostics(|diagnostic| !is_suppressed(&ctx.suppressions, diagnostic));
+        // kept for the diagnostics reported by the workspace later
+        self.set_suppressions(std::mem::take(&mut ctx.suppressions));
+    }
+}
+
+// by the "disabledDiagnostics" option
+pub fn is_disabled(config: &Config, diagnostic: &Diagnostic) -> bool {
+    match &diagnostic.code {
+        Some(NumberOrString::String(code)) => config.is_diagnostic_disabled(code),
+        _ => false,
+    }
+}
+
+// by the "diagnosticSeverity" option
+pub fn override_severity(config: &Config, diagnostic: &mut Diagnostic) {
+    if let Some(NumberOrString::String(code)) = &diagnostic.code
+        && let Some(severity) = config.diagnostic_severity(code)
+    {
+        diagnostic.severity = Some(severity);
+    }
+}
+
+pub fn is_suppressed(suppressions: &[Suppression], diagnostic: &Diagnostic) -> bool {
+    match &diagnostic.code {
+        Some(NumberOrString::String(code)) => suppressions
+            .iter()
+            .any(|suppression| suppression.matches(code, diagnostic.range.start.line)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tower_lsp_server::ls_types::{DiagnosticSeverity, NumberOrString, Uri};
+
+    use crate::{
+        config::Config,
+        reactor::Reactor,
+        suppress::{Suppression, parse_suppression},
+    };
+
+    fn codes_of(source: &str, config: Config) -> Vec<(u32, String)> {
+        let uri = Uri::from_str("file:///tmp/suppress.ftl").unwrap();
+        let reactor = Reactor::new(&uri, source, 0, config.into(), Default::default());
+        reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items
+            .into_iter()
+            .filter_map(|d| match d.code {
+                Some(NumberOrString::String(code)) => Some((d.range.start.line, code)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_suppression() {
+        assert_eq!(
+            parse_suppression("<#-- lsp-disable-next-line a, b -->", 3),
+            Some(Suppression {
+                line: Some(4),
+                codes: vec!["a".to_owned(), "b".to_owned()],
+            })
+        );
+        assert_eq!(parse_suppression("<#-- lsp-disable-file -->", 0), None);
+        assert_eq!(parse_suppression("<#-- just a comment -->", 0), None);
+    }
+
+    #[test]
+    fn test_suppress_next_line() {
+        let source = "<#-- lsp-disable-next-line undefined_macro -->\n<@foo/>\n<@bar/>\n";
+        assert_eq!(
+            codes_of(source, Default::default()),
+            vec![(2, "undefined_macro".to_owned())]
+        );
+        // other codes are still reported
+        let source = "<#-- lsp-disable-next-line unknown_setting -->\n<@foo/>\n";
+        assert_eq!(
+            codes_of(source, Default::default()),
+            vec![(1, "undefined_macro".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_suppress_file() {
+        let source =
+            "<@foo/>\n<#-- lsp-disable-file undefined_macro -->\n<#if a = 1></#if>\n<@bar/>\n";
+        assert_eq!(
+            codes_of(source, Default::default()),
+            vec![(2, "deprecated_equal_operator".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_disabled_diagnostics() {
+        let source = "<@foo/>\n<#if a = 1></#if>\n<#list xs as x></#list>\n";
+        let config = Config::from_initialization_options(Some(serde_json::json!({
+            "disabledDiagnostics": ["deprecated_equal_operator", "no_such_code"],
+        })));
+        assert_eq!(
+            codes_of(source, config),
+            vec![(0, "undefined_macro".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_severity() {
+        let uri = Uri::from_str("file:///tmp/suppress.ftl").unwrap();
+        let source = "<@foo/>\n<#if a = 1></#if>\n";
+        let config = Config::from_initialization_options(Some(serde_json::json!({
+            "diagnosticSeverity": {
+                "deprecated_equal_operator": "hint",
+                "undefined_macro": "fatal",
+            },
+        })));
+        let reactor = Reactor::new(&uri, source, 0, config.into(), Default::default());
+        let severities: Vec<_> = reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items
+            .into_iter()
+            .map(|d| (d.range.start.line, d.severity))
+            .collect();
+        // an invalid severity keeps the default one
+        assert_eq!(
+            severities,
+            vec![
+                (0, Some(DiagnosticSeverity::ERROR)),
+                (1, Some(DiagnosticSeverity::HINT)),
+            ]
+        );
+    }
+}
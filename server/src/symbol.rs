@@ -2,27 +2,38 @@
 // Licensed under the BSD 3-Clause License.
 // SPDX-License-Identifier: BSD-3-Clause
 
-use std::{path::PathBuf, str::FromStr};
+use std::{collections::HashSet, str::FromStr, sync::Arc};
 
-use tower_lsp_server::ls_types::{
-    CodeDescription, Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location,
-    NumberOrString, Range, Uri,
+use tower_lsp_server::{
+    jsonrpc::Result as JsonRpcResult,
+    ls_types::{
+        CodeDescription, Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity,
+        DiagnosticTag, DocumentSymbol, DocumentSymbolOptions, DocumentSymbolParams,
+        DocumentSymbolResponse, Location, NumberOrString, OneOf, Range, SymbolInformation,
+        SymbolKind, Uri, WorkspaceSymbolOptions, WorkspaceSymbolParams, WorkspaceSymbolResponse,
+    },
 };
 use tree_sitter::Node;
 use tree_sitter_freemarker::href::DIRECTIVE_IMPORT;
 use tree_sitter_freemarker::{SEMANTICS, grammar::Rule};
 
-use crate::diagnosis::Scenario;
+use crate::diagnosis::{Category, Scenario};
 use crate::{
-    analysis::{Analysis, AnalysisContext, Symbol, SymbolAnalysis},
+    analysis::{Analysis, AnalysisContext, MacroCall, PathReference, Symbol, SymbolAnalysis},
     doc::TextDocument,
+    import::ImportTarget,
+    reactor::Reactor,
+    server::{DocumentSymbolFeature, WorkspaceSymbolFeature},
     utils,
+    workspace::Workspace,
 };
 
 struct ImportWarning(&'static str, &'static str);
 
+pub(crate) const PATH_DUPLICATED_CODE: &str = "path_duplicated";
+
 impl ImportWarning {
-    const PATH_DUPLICATED: Self = ImportWarning("path_duplicated", "import path is dupicated");
+    const PATH_DUPLICATED: Self = ImportWarning(PATH_DUPLICATED_CODE, "import path is dupicated");
 
     pub fn build(
         &self,
@@ -39,6 +50,7 @@ impl ImportWarning {
             source: Some(SEMANTICS.to_owned()),
             message: self.1.to_owned(),
             related_information,
+            data: Category::Semantics.data(),
             ..Default::default()
         }
     }
@@ -46,11 +58,14 @@ impl ImportWarning {
 
 struct ImportError(&'static str, &'static str);
 
+pub(crate) const PATH_REF_SELF_CODE: &str = "path_refer_itself";
+pub(crate) const PATH_UNCANONICAL_CODE: &str = "path_uncanonical";
+
 impl ImportError {
-    const PATH_UNCANONICAL: Self = ImportError("path_uncanonical", "import path is uncanonical");
+    const PATH_UNCANONICAL: Self = ImportError(PATH_UNCANONICAL_CODE, "import path is uncanonical");
     const PATH_NOT_FILE: Self = ImportError("path_not_file", "import path is not a file");
-    const PATH_NOT_EXISTS: Self = ImportError("path_not_exists", "import path is not exists");
-    const PATH_REF_SELF: Self = ImportError("path_refer_itself", "import path refers to itself");
+    const PATH_REF_SELF: Self = ImportError(PATH_REF_SELF_CODE, "import path refers to itself");
+    const PATH_CIRCULAR: Self = ImportError("circular_import", "import path is circular");
 
     pub fn build(
         &self,
@@ -67,6 +82,7 @@ impl ImportError {
             source: Some(SEMANTICS.to_owned()),
             message: self.1.to_owned(),
             related_information,
+            data: Category::Semantics.data(),
             ..Default::default()
         }
     }
@@ -82,7 +98,7 @@ fn analyze_import_statement(
     let alias_node = import_node
         .child_by_field_name(Rule::ImportAlias.to_string())
         .unwrap();
-    let alias_range = utils::parser_node_to_document_range(&alias_node);
+    let alias_range = utils::parser_node_to_document_range(doc, &alias_node);
     let import_alias = doc.get_ranged_text(alias_node.start_byte()..alias_node.end_byte());
     analysis.add_symbol(
         &import_alias,
@@ -98,27 +114,62 @@ fn analyze_import_statement(
     let path_node = import_node
         .child_by_field_name(Rule::ImportPath.to_string())
         .unwrap();
-    let path_range = utils::parser_node_to_document_range(&path_node);
+    let path_range = utils::parser_node_to_document_range(doc, &path_node);
     // the tree-sitter parser had ensured the import_path is '"' quoted, so it is safe to slice like this [1..len()-1]
     let import_path_str = doc.get_ranged_text(path_node.start_byte() + 1..path_node.end_byte() - 1);
-    let import_path_buf = PathBuf::from(&import_path_str);
-    let canonicalize_import = match import_path_buf.is_absolute() {
-        true => import_path_buf.canonicalize(),
-        false => doc.dir().join(import_path_buf).canonicalize(),
+    analysis.add_import_range(import_node.byte_range());
+    let check = match ctx.carried_import_checks.get(&import_path_str) {
+        Some(check) => check.clone(),
+        None => Arc::new(ctx.imports.check_import(&ctx.config, doc, &import_path_str)),
     };
+    analysis.record_import_check(&import_path_str, check.clone());
 
-    match canonicalize_import {
-        Ok(canonicalize_import_path) => {
-            if !canonicalize_import_path.is_file() {
+    match &check.target {
+        Some(ImportTarget {
+            canonical_path: canonicalize_import_path,
+            is_file,
+            ..
+        }) => {
+            if !is_file {
                 // import must be a file
                 analysis.add_diagnostic(ImportError::PATH_NOT_FILE.build(path_range, None));
-            } else if !canonicalize_import_path.exists() {
-                // import must exists
-                analysis.add_diagnostic(ImportError::PATH_NOT_EXISTS.build(path_range, None));
-            } else if doc.canonical_uri() == canonicalize_import_path {
+            } else if check.refers_itself {
                 // don't import yourself
-                analysis.add_diagnostic(ImportError::PATH_REF_SELF.build(path_range, None));
+                // the whole import statement is useless
+                analysis.add_diagnostic(Diagnostic {
+                    tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                    ..ImportError::PATH_REF_SELF
+                        .build(utils::parser_node_to_document_range(doc, import_node), None)
+                });
+            } else if let Some(chain) = &check.circular_chain {
+                // each hop leads back to this template
+                let hops = chain
+                    .iter()
+                    .filter_map(|(importer, edge)| {
+                        Some(DiagnosticRelatedInformation {
+                            location: Location {
+                                uri: Uri::from_file_path(importer)?,
+                                range: edge.range,
+                            },
+                            message: format!("imports \"{}\"", edge.target.display()),
+                        })
+                    })
+                    .collect();
+                analysis.add_diagnostic(ImportError::PATH_CIRCULAR.build(path_range, Some(hops)));
             }
+            analysis.add_path_reference(PathReference {
+                text: import_path_str.clone(),
+                range: Range {
+                    start: doc.byte_to_position(path_node.start_byte() + 1),
+                    end: doc.byte_to_position(path_node.end_byte() - 1),
+                },
+                directive_range: utils::parser_node_to_document_range(doc, import_node),
+                target: canonicalize_import_path.clone(),
+            });
+            analysis.record_import_alias(
+                &import_alias,
+                Uri::from_file_path(canonicalize_import_path).unwrap(),
+            );
             //
             let canonicalize_import_str = canonicalize_import_path.to_str().unwrap();
             ctx.import_map
@@ -140,7 +191,7 @@ fn analyze_import_statement(
                 .or_insert_with(|| {
                     analysis.record_valid_import(
                         &import_path_str, // record original text as key
-                        Uri::from_file_path(&canonicalize_import_path).unwrap(),
+                        Uri::from_file_path(canonicalize_import_path).unwrap(),
                     );
                     vec![Symbol {
                         rule: Rule::ImportPath,
@@ -150,24 +201,321 @@ fn analyze_import_statement(
                     }]
                 });
         }
-        Err(_) => {
+        None => {
             analysis.add_diagnostic(ImportError::PATH_UNCANONICAL.build(path_range, None));
         }
     }
 }
 
+// e.g. "user" and "b" of `<#global user = 1 b = 2>` or "c" of `<#global c>x</#global>`
+fn global_names(node: &Node, doc: &TextDocument) -> Vec<(String, Range)> {
+    let Some(directive) = utils::error_node_directive(node, doc)
+        .filter(|directive| directive.name == "global" && !directive.closing)
+    else {
+        return vec![];
+    };
+    let rest = directive.rest.as_str();
+    let tag = &rest[..rest.find('>').unwrap_or(rest.len())];
+    let is_name = |c: char| c.is_alphanumeric() || c == '_';
+    let mut names = vec![];
+    let mut offset = 0;
+    while let Some(start) = tag[offset..].find(is_name).map(|start| offset + start) {
+        let end = tag[start..]
+            .find(|c: char| !is_name(c))
+            .map_or(tag.len(), |end| start + end);
+        let after = tag[end..].trim_start();
+        let preceded_by_space = tag[..start].ends_with(char::is_whitespace);
+        // the first name may be captured, the others are followed by '='
+        let assigned = after.starts_with('=') && !after.starts_with("==");
+        if preceded_by_space && (names.is_empty() || assigned) {
+            let name_start = directive.rest_byte + start;
+            names.push((
+                tag[start..end].to_owned(),
+                Range::new(
+                    doc.byte_to_position(name_start),
+                    doc.byte_to_position(name_start + end - start),
+                ),
+            ));
+        }
+        offset = end;
+    }
+    names
+}
+
+fn collect_variables(node: &Node, doc: &TextDocument, variables: &mut Vec<(String, Range)>) {
+    if let Ok(Rule::Variable) = Rule::from_str(node.kind()) {
+        variables.push((
+            doc.get_ranged_text(node.start_byte()..node.end_byte()),
+            utils::parser_node_to_document_range(doc, node),
+        ));
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_variables(&child, doc, variables);
+    }
+}
+
+// the macro or function which contains the node
+fn enclosing_definition<'a>(node: &Node<'a>) -> Option<Node<'a>> {
+    let mut node_cursor = node.parent();
+    while let Some(parent) = node_cursor {
+        if let Ok(Rule::MacroStmt | Rule::FunctionStmt) = Rule::from_str(parent.kind()) {
+            return Some(parent);
+        }
+        node_cursor = parent.parent();
+    }
+    None
+}
+
+fn is_top_level(node: &Node) -> bool {
+    enclosing_definition(node).is_none()
+}
+
+fn analyze_top_level_names(node: &Node, doc: &TextDocument, ctx: &mut AnalysisContext) {
+    if !is_top_level(node) {
+        return;
+    }
+    let mut variables = vec![];
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        match Rule::from_str(child.kind()) {
+            // <#assign x = 1 y = 2>
+            Ok(Rule::AssignInline) => {
+                let mut inline_cursor = child.walk();
+                for expression in child.named_children(&mut inline_cursor) {
+                    if let Some(left) = expression.child_by_field_name("left") {
+                        collect_variables(&left, doc, &mut variables);
+                    }
+                }
+            }
+            // <#assign x>...</#assign>
+            Ok(Rule::AssignClause) => {
+                if let Some(into) = child.child_by_field_name("into") {
+                    collect_variables(&into, doc, &mut variables);
+                }
+            }
+            // <#function f>...</#function>
+            Ok(Rule::FunctionClause) => {
+                if let Some(name) = child.child_by_field_name("name") {
+                    let text = doc.get_ranged_text(name.start_byte()..name.end_byte());
+                    ctx.function_definitions
+                        .entry(text.clone())
+                        .or_insert(utils::parser_node_to_document_range(doc, &name));
+                    ctx.top_level_names.insert(text);
+                }
+            }
+            _ => {}
+        }
+    }
+    ctx.top_level_names
+        .extend(variables.into_iter().map(|(name, _)| name));
+}
+
+// `add(1, 2)` outside of any macro or function
+fn analyze_function_call(call_node: &Node, doc: &TextDocument, ctx: &mut AnalysisContext) {
+    if !is_top_level(call_node) {
+        return;
+    }
+    let mut cursor = call_node.walk();
+    if let Some(name) = call_node
+        .named_children(&mut cursor)
+        .find(|child| matches!(Rule::from_str(child.kind()), Ok(Rule::FunctionName)))
+        .and_then(|function_name| function_name.child_by_field_name("name"))
+    {
+        ctx.function_calls.push((
+            doc.get_ranged_text(name.start_byte()..name.end_byte()),
+            utils::parser_node_to_document_range(doc, &name),
+        ));
+    }
+}
+
+fn analyze_assign_statement(assign_node: &Node, doc: &TextDocument, analysis: &mut Analysis) {
+    let mut variables = vec![];
+    let mut cursor = assign_node.walk();
+    for child in assign_node.named_children(&mut cursor) {
+        match Rule::from_str(child.kind()) {
+            Ok(Rule::AssignInline | Rule::LocalInline) => {
+                let mut inline_cursor = child.walk();
+                for expression in child.named_children(&mut inline_cursor) {
+                    if let Some(left) = expression.child_by_field_name("left") {
+                        variables.push(left);
+                    }
+                }
+            }
+            Ok(Rule::AssignClause | Rule::LocalClause) => {
+                if let Some(into) = child.child_by_field_name("into") {
+                    variables.push(into);
+                }
+            }
+            _ => {}
+        }
+    }
+    // a local variable is only visible inside of its macro or function
+    let scope = match Rule::from_str(assign_node.kind()) {
+        Ok(Rule::LocalStmt) => enclosing_definition(assign_node)
+            .map(|definition| utils::parser_node_to_document_range(doc, &definition)),
+        _ => None,
+    };
+    let top_level = scope.is_none() && is_top_level(assign_node);
+    for variable in variables {
+        if top_level {
+            analysis.add_document_symbol(document_symbol(
+                doc.get_ranged_text(variable.start_byte()..variable.end_byte()),
+                SymbolKind::VARIABLE,
+                "assign",
+                assign_node,
+                &variable,
+                doc,
+            ));
+        }
+        analysis.add_assignment(
+            &doc.get_ranged_text(variable.start_byte()..variable.end_byte()),
+            Symbol {
+                rule: Rule::Variable,
+                start_byte: variable.start_byte(),
+                end_byte: variable.end_byte(),
+                range: utils::parser_node_to_document_range(doc, &variable),
+            },
+            scope,
+        );
+    }
+}
+
+#[allow(deprecated)]
+fn document_symbol(
+    name: String,
+    kind: SymbolKind,
+    detail: &str,
+    node: &Node,
+    selection_node: &Node,
+    doc: &TextDocument,
+) -> DocumentSymbol {
+    DocumentSymbol {
+        name,
+        detail: Some(detail.to_owned()),
+        kind,
+        tags: None,
+        deprecated: None,
+        range: utils::parser_node_to_document_range(doc, node),
+        selection_range: utils::parser_node_to_document_range(doc, selection_node),
+        children: None,
+    }
+}
+
+fn analyze_function_statement(function_node: &Node, doc: &TextDocument, analysis: &mut Analysis) {
+    let mut cursor = function_node.walk();
+    let Some(name_node) = function_node
+        .named_children(&mut cursor)
+        .find(|child| matches!(Rule::from_str(child.kind()), Ok(Rule::FunctionClause)))
+        .and_then(|clause| clause.child_by_field_name("name"))
+    else {
+        return;
+    };
+    analysis.add_document_symbol(document_symbol(
+        doc.get_ranged_text(name_node.start_byte()..name_node.end_byte()),
+        SymbolKind::FUNCTION,
+        "function",
+        function_node,
+        &name_node,
+        doc,
+    ));
+}
+
+fn analyze_macro_defaults(macro_node: &Node, doc: &TextDocument, ctx: &mut AnalysisContext) {
+    let mut cursor = macro_node.walk();
+    let Some(clause) = macro_node
+        .named_children(&mut cursor)
+        .find(|child| matches!(Rule::from_str(child.kind()), Ok(Rule::MacroClause)))
+    else {
+        return;
+    };
+    // a default value can refer to the parameters declared before it
+    let mut parameters = HashSet::new();
+    let mut clause_cursor = clause.walk();
+    for parameter in clause.children_by_field_name("parameter", &mut clause_cursor) {
+        match Rule::from_str(parameter.kind()) {
+            Ok(Rule::Identifier) => {
+                parameters
+                    .insert(doc.get_ranged_text(parameter.start_byte()..parameter.end_byte()));
+            }
+            Ok(Rule::AssignExpression) => {
+                if let Some(right) = parameter.child_by_field_name("right") {
+                    let mut references = vec![];
+                    collect_variables(&right, doc, &mut references);
+                    ctx.default_references.extend(
+                        references
+                            .into_iter()
+                            .filter(|(name, _)| !parameters.contains(name)),
+                    );
+                }
+                if let Some(left) = parameter.child_by_field_name("left") {
+                    parameters.insert(doc.get_ranged_text(left.start_byte()..left.end_byte()));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// "a", "b=1" or "c..." (the grammar leaves the ellipsis as an ERROR node)
+fn macro_parameters(macro_node: &Node, doc: &TextDocument) -> Vec<String> {
+    let mut cursor = macro_node.walk();
+    let Some(clause) = macro_node
+        .named_children(&mut cursor)
+        .find(|child| matches!(Rule::from_str(child.kind()), Ok(Rule::MacroClause)))
+    else {
+        return vec![];
+    };
+    let mut parameters = vec![];
+    let mut clause_cursor = clause.walk();
+    for parameter in clause.children_by_field_name("parameter", &mut clause_cursor) {
+        let mut label = doc.get_ranged_text(parameter.start_byte()..parameter.end_byte());
+        if let Some(next) = parameter.next_sibling()
+            && next.is_error()
+            // the ERROR might swallow the rest of the tag, e.g. "...>\n  <#nested"
+            && doc
+                .get_ranged_text(next.start_byte()..next.end_byte())
+                .starts_with("...")
+        {
+            label.push_str("...");
+        }
+        parameters.push(label);
+    }
+    parameters
+}
+
+// the grammar doesn't parse <#nested>, it is left in an ERROR node of the body
+fn macro_uses_nested(macro_node: &Node, doc: &TextDocument) -> bool {
+    let text = doc.get_ranged_text(macro_node.start_byte()..macro_node.end_byte());
+    text.contains("<#nested") || text.contains("[#nested")
+}
+
 fn analyze_macro_statement(
     macro_node: &Node,
     doc: &TextDocument,
-    _: &mut AnalysisContext,
+    ctx: &mut AnalysisContext,
     analysis: &mut Analysis,
 ) {
+    analyze_macro_defaults(macro_node, doc, ctx);
     // "import as" alias
     let name_node = macro_node
         .child_by_field_name(Rule::MacroName.to_string())
         .unwrap();
-    let name_range = utils::parser_node_to_document_range(&name_node);
+    let name_range = utils::parser_node_to_document_range(doc, &name_node);
     let name_text = doc.get_ranged_text(name_node.start_byte()..name_node.end_byte());
+    analysis.add_document_symbol(document_symbol(
+        name_text.clone(),
+        SymbolKind::FUNCTION,
+        "macro",
+        macro_node,
+        &name_node,
+        doc,
+    ));
+    analysis.add_macro_parameters(&name_text, macro_parameters(macro_node, doc));
+    if macro_uses_nested(macro_node, doc) {
+        analysis.mark_macro_nested(&name_text);
+    }
     analysis.add_symbol(
         &name_text,
         Symbol {
@@ -179,6 +527,52 @@ fn analyze_macro_statement(
     );
 }
 
+fn analyze_macro_call(call_node: &Node, doc: &TextDocument, analysis: &mut Analysis) {
+    let mut cursor = call_node.walk();
+    let mut namespace_node = None;
+    let mut specs_node = None;
+    for child in call_node.children(&mut cursor) {
+        match Rule::from_str(child.kind()) {
+            Ok(Rule::MacroNamespace) => namespace_node = Some(child),
+            Ok(Rule::MacroSpecs) => specs_node = Some(child),
+            _ => {}
+        }
+    }
+    let Some(namespace_node) = namespace_node.filter(|n| !n.is_missing()) else {
+        return;
+    };
+    let namespace = doc.get_ranged_text(namespace_node.start_byte()..namespace_node.end_byte());
+    // "<@ns.foo/>" calls the macro "foo" of the namespace "ns"
+    let member = specs_node
+        .map(|n| doc.get_ranged_text(n.start_byte()..n.end_byte()))
+        .map(|text| text.trim_start_matches('.').to_owned())
+        .filter(|text| !text.is_empty());
+    let namespace_range = utils::parser_node_to_document_range(doc, &namespace_node);
+    let mut range = namespace_range;
+    if let Some(specs_node) = specs_node {
+        range.end = utils::parser_node_to_document_range(doc, &specs_node).end;
+    }
+    // the enclosing macro (if any) is the caller
+    let mut caller = None;
+    let mut node_cursor = call_node.parent();
+    while let Some(parent) = node_cursor {
+        if let Ok(Rule::MacroStmt) = Rule::from_str(parent.kind())
+            && let Some(name_node) = parent.child_by_field_name(Rule::MacroName.to_string())
+        {
+            caller = Some(doc.get_ranged_text(name_node.start_byte()..name_node.end_byte()));
+            break;
+        }
+        node_cursor = parent.parent();
+    }
+    analysis.add_macro_call(MacroCall {
+        namespace,
+        member,
+        caller,
+        namespace_range,
+        range,
+    });
+}
+
 impl SymbolAnalysis for Analysis {
     fn analyze_syntatic_symbols(
         &mut self,
@@ -186,6 +580,22 @@ impl SymbolAnalysis for Analysis {
         doc: &TextDocument,
         ctx: &mut AnalysisContext,
     ) {
+        if node.is_error() {
+            if ctx
+                .config
+                .is_diagnostic_enabled(Scenario::GLOBAL_SHADOWS_DATAMODEL.code())
+            {
+                for (name, range) in global_names(node, doc) {
+                    if ctx.config.is_data_model_root(&name) {
+                        self.add_diagnostic(Diagnostic {
+                            range,
+                            ..Scenario::GLOBAL_SHADOWS_DATAMODEL.into()
+                        });
+                    }
+                }
+            }
+            return;
+        }
         let rule = Rule::from_str(node.kind());
         if rule.is_err() {
             return;
@@ -197,6 +607,29 @@ impl SymbolAnalysis for Analysis {
             Rule::MacroStmt => {
                 analyze_macro_statement(node, doc, ctx, self);
             }
+            Rule::MacroCall => {
+                analyze_macro_call(node, doc, self);
+            }
+            Rule::AssignStmt => {
+                analyze_assign_statement(node, doc, self);
+                analyze_top_level_names(node, doc, ctx);
+            }
+            Rule::LocalStmt => {
+                analyze_assign_statement(node, doc, self);
+            }
+            Rule::FunctionStmt => {
+                analyze_function_statement(node, doc, self);
+                analyze_top_level_names(node, doc, ctx);
+            }
+            Rule::CallExpression => {
+                analyze_function_call(node, doc, ctx);
+            }
+            Rule::Variable => {
+                if let Some(name) = node.child_by_field_name("name") {
+                    ctx.variable_names
+                        .insert(doc.get_ranged_text(name.start_byte()..name.end_byte()));
+                }
+            }
             _ => {}
         }
     }
@@ -214,6 +647,7 @@ impl SymbolAnalysis for Analysis {
                         code: Some(NumberOrString::String("duplicated_symbol".to_owned())),
                         source: Some(SEMANTICS.to_owned()),
                         message: "redefinition of symbol".to_owned(),
+                        data: Category::Semantics.data(),
                         related_information: Some(vec![DiagnosticRelatedInformation {
                             location: Location {
                                 uri: doc.uri(),
@@ -227,18 +661,482 @@ impl SymbolAnalysis for Analysis {
             }
         });
         self.add_diagnostics(duplicated_symbols);
+        // check unused imports, a call of a macro which shadows the alias is a use as well
+        let mut unused_imports = vec![];
+        self.foreach_symbol(|name, symbols| {
+            if ctx.macro_call_map.contains_key(name) || ctx.variable_names.contains(name) {
+                return;
+            }
+            for alias in symbols.iter().filter(|s| s.rule == Rule::ImportAlias) {
+                unused_imports.push(Diagnostic {
+                    range: alias.range,
+                    message: format!("'{}' is imported but never used.", name),
+                    tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                    ..Scenario::UNUSED_IMPORT.into()
+                });
+            }
+        });
+        self.add_diagnostics(unused_imports);
+        // check unused macros, likewise
+        if ctx.config.lint_unused_macros
+            || ctx
+                .config
+                .is_diagnostic_enabled(Scenario::UNUSED_MACRO.code())
+        {
+            let mut unused_macros = vec![];
+            self.foreach_symbol(|name, symbols| {
+                if ctx.macro_call_map.contains_key(name) || ctx.variable_names.contains(name) {
+                    return;
+                }
+                for definition in symbols.iter().filter(|s| s.rule == Rule::MacroName) {
+                    unused_macros.push(Diagnostic {
+                        range: definition.range,
+                        message: format!("'{}' is defined but never called.", name),
+                        tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                        ..Scenario::UNUSED_MACRO.into()
+                    });
+                }
+            });
+            self.add_diagnostics(unused_macros);
+        }
         // check undefined macro calls
         ctx.macro_call_map
             .iter()
             .for_each(|(call_name, call_symbols)| {
-                if self.find_symbol_definition(call_name).is_err() {
-                    call_symbols.iter().for_each(|sym| {
-                        self.add_diagnostic(Diagnostic {
+                if self.find_symbol_definition(call_name).is_err()
+                    && !ctx.config.is_known_macro(call_name)
+                {
+                    // <#include> doesn't create a namespace
+                    let include = self
+                        .get_includes()
+                        .iter()
+                        .find(|include| include.alias.as_ref() == Some(call_name))
+                        .cloned();
+                    call_symbols.iter().for_each(|sym| match &include {
+                        Some(include) => self.add_diagnostic(Diagnostic {
+                            range: sym.range,
+                            related_information: Some(vec![DiagnosticRelatedInformation {
+                                location: Location {
+                                    uri: doc.uri(),
+                                    range: include.keyword_range,
+                                },
+                                message: format!("\"{}\" is included here", include.path),
+                            }]),
+                            ..Scenario::INCLUDE_USED_AS_IMPORT.into()
+                        }),
+                        None => self.add_diagnostic(Diagnostic {
                             range: sym.range,
                             ..Scenario::UNDEFINED_MACRO.into()
-                        })
+                        }),
                     })
                 }
             });
+        // check default values of macro parameters
+        if ctx
+            .config
+            .is_diagnostic_enabled(Scenario::UNDEFINED_IN_DEFAULT.code())
+        {
+            for (name, range) in &ctx.default_references {
+                if !ctx.top_level_names.contains(name) && self.find_symbol_definition(name).is_err()
+                {
+                    self.add_diagnostic(Diagnostic {
+                        range: *range,
+                        message: format!(
+                            "'{}' is not defined at the top level of the template.",
+                            name
+                        ),
+                        ..Scenario::UNDEFINED_IN_DEFAULT.into()
+                    });
+                }
+            }
+        }
+        // check calls of functions defined later
+        if ctx
+            .config
+            .is_diagnostic_enabled(Scenario::FORWARD_REFERENCE.code())
+        {
+            for (name, range) in &ctx.function_calls {
+                if let Some(definition) = ctx.function_definitions.get(name)
+                    && range.start < definition.start
+                {
+                    self.add_diagnostic(Diagnostic {
+                        range: *range,
+                        message: format!("'{}' is called before its definition.", name),
+                        related_information: Some(vec![DiagnosticRelatedInformation {
+                            location: Location {
+                                uri: doc.uri(),
+                                range: *definition,
+                            },
+                            message: "defined here".to_owned(),
+                        }]),
+                        ..Scenario::FORWARD_REFERENCE.into()
+                    });
+                }
+            }
+        }
+    }
+}
+
+pub fn document_symbol_capability() -> OneOf<bool, DocumentSymbolOptions> {
+    OneOf::Left(true)
+}
+
+pub fn workspace_symbol_capability() -> OneOf<bool, WorkspaceSymbolOptions> {
+    OneOf::Left(true)
+}
+
+impl DocumentSymbolFeature for Reactor {
+    async fn on_document_symbol(
+        &self,
+        _: DocumentSymbolParams,
+    ) -> JsonRpcResult<Option<DocumentSymbolResponse>> {
+        Ok(Some(DocumentSymbolResponse::Nested(
+            self.get_analysis().get_document_symbols(),
+        )))
+    }
+}
+
+// the macros defined in the opened templates, whose name contains the query in any case
+impl WorkspaceSymbolFeature for Workspace {
+    #[allow(deprecated)]
+    async fn on_workspace_symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> JsonRpcResult<Option<WorkspaceSymbolResponse>> {
+        let query = params.query.to_lowercase();
+        let read_guard = self.reactors.read().await;
+        let mut symbols = vec![];
+        for (uri, reactor) in read_guard.iter() {
+            reactor.get_analysis().foreach_symbol(|name, definitions| {
+                if !name.to_lowercase().contains(&query) {
+                    return;
+                }
+                for definition in definitions.iter().filter(|s| s.rule == Rule::MacroName) {
+                    symbols.push(SymbolInformation {
+                        name: name.to_owned(),
+                        kind: SymbolKind::FUNCTION,
+                        tags: None,
+                        deprecated: None,
+                        location: Location::new(uri.clone(), definition.range),
+                        container_name: None,
+                    });
+                }
+            });
+        }
+        // the reactors and symbols are hashed, sort for a stable order
+        symbols.sort_by(|a, b| {
+            (&a.name, a.location.uri.as_str(), a.location.range.start).cmp(&(
+                &b.name,
+                b.location.uri.as_str(),
+                b.location.range.start,
+            ))
+        });
+        Ok(Some(WorkspaceSymbolResponse::Flat(symbols)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use tower_lsp_server::ls_types::{
+        Diagnostic, DiagnosticSeverity, DiagnosticTag, DidOpenTextDocumentParams,
+        DocumentSymbolParams, DocumentSymbolResponse, NumberOrString, Position, Range, SymbolKind,
+        TextDocumentIdentifier, TextDocumentItem, Uri, WorkspaceSymbolParams,
+        WorkspaceSymbolResponse,
+    };
+
+    use crate::{
+        config::Config,
+        reactor::Reactor,
+        server::{DocumentSymbolFeature, WorkspaceSymbolFeature},
+        utils,
+        workspace::Workspace,
+    };
+
+    fn diagnostics_of(source: &str, config: Config) -> Vec<Diagnostic> {
+        let uri = Uri::from_str("file:///tmp/symbol.ftl").unwrap();
+        let reactor = Reactor::new(&uri, source, 0, Arc::new(config), Default::default());
+        reactor
+            .get_analysis()
+            .get_analyzed_full_diagnostics()
+            .full_document_diagnostic_report
+            .items
+    }
+
+    fn has_code(diagnostic: &Diagnostic, code: &str) -> bool {
+        diagnostic.code == Some(NumberOrString::String(code.to_owned()))
+    }
+
+    #[tokio::test]
+    async fn test_document_symbols() {
+        let uri = Uri::from_str("file:///tmp/symbol.ftl").unwrap();
+        let source = "<#assign a = 1>\n<#macro foo>\n<#assign b = 2>\n<#function f x>\n<#return x>\n</#function>\n</#macro>\n<#assign c>text</#assign>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let Some(DocumentSymbolResponse::Nested(symbols)) = reactor
+            .on_document_symbol(DocumentSymbolParams {
+                text_document: TextDocumentIdentifier { uri },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .unwrap()
+        else {
+            panic!("expect nested document symbols");
+        };
+        let names: Vec<_> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "foo", "c"]);
+        assert_eq!(symbols[1].kind, SymbolKind::FUNCTION);
+        assert_eq!(symbols[1].selection_range.start, Position::new(1, 8));
+        // only the function is nested in the macro, "b" is not top-level
+        let children = symbols[1].children.as_ref().unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].name, "f");
+        assert_eq!(symbols[2].kind, SymbolKind::VARIABLE);
+    }
+
+    #[test]
+    fn test_undefined_in_default() {
+        let source = "<#assign y = 1>\n<#macro foo a b=a c=y d=bar>\n</#macro>\n";
+        let config = Config {
+            enabled_diagnostics: ["undefined_in_default".to_owned()].into(),
+            ..Default::default()
+        };
+        let diagnostics: Vec<_> = diagnostics_of(source, config)
+            .into_iter()
+            .filter(|d| has_code(d, "undefined_in_default"))
+            .collect();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start.line, 1);
+        assert_eq!(diagnostics[0].range.start.character, 24);
+        // off by default
+        assert!(
+            !diagnostics_of(source, Config::default())
+                .iter()
+                .any(|d| has_code(d, "undefined_in_default"))
+        );
+    }
+
+    #[test]
+    fn test_forward_reference() {
+        let source = "<#assign y = add(1, 2)>\n<#macro m>${add(3, 4)}</#macro>\n<#function add a b>\n<#return a + b>\n</#function>\n${add(5, 6)}\n";
+        let config = Config {
+            enabled_diagnostics: ["forward_reference".to_owned()].into(),
+            ..Default::default()
+        };
+        let diagnostics: Vec<_> = diagnostics_of(source, config)
+            .into_iter()
+            .filter(|d| has_code(d, "forward_reference"))
+            .collect();
+        // neither the call inside the macro nor the one after the definition
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start, Position::new(0, 13));
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::HINT));
+        // off by default
+        assert!(
+            !diagnostics_of(source, Config::default())
+                .iter()
+                .any(|d| has_code(d, "forward_reference"))
+        );
+    }
+
+    #[test]
+    fn test_global_shadows_datamodel() {
+        let source = "<#global user = 1 title = user.name>\n<#global count = a == b>\n<#global request>x</#global>\n";
+        let config = Config {
+            enabled_diagnostics: ["global_shadows_datamodel".to_owned()].into(),
+            data_model_roots: ["user".to_owned(), "request".to_owned(), "b".to_owned()].into(),
+            ..Default::default()
+        };
+        let ranges: Vec<_> = diagnostics_of(source, config)
+            .into_iter()
+            .filter(|d| has_code(d, "global_shadows_datamodel"))
+            .map(|d| d.range)
+            .collect();
+        // "user.name" and "b" of the comparison are not assigned
+        assert_eq!(
+            ranges,
+            vec![
+                Range::new(Position::new(0, 9), Position::new(0, 13)),
+                Range::new(Position::new(2, 9), Position::new(2, 16)),
+            ]
+        );
+        // off by default
+        assert!(
+            !diagnostics_of(source, Config::default())
+                .iter()
+                .any(|d| has_code(d, "global_shadows_datamodel"))
+        );
+    }
+
+    #[test]
+    fn test_unused_import() {
+        let source = "<#import \"a.ftl\" as unused>\n<#import \"b.ftl\" as called>\n<#import \"c.ftl\" as read>\n<#import \"d.ftl\" as shadowed>\n<@called.foo/>\n${read.x}\n<#macro shadowed></#macro>\n<@shadowed/>\n";
+        let diagnostics: Vec<_> = diagnostics_of(source, Config::default())
+            .into_iter()
+            .filter(|d| has_code(d, "unused_import"))
+            .collect();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start, Position::new(0, 20));
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::HINT));
+        assert_eq!(diagnostics[0].tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+    }
+
+    #[test]
+    fn test_unused_macro() {
+        let source = "<#macro unused></#macro>\n<#macro called></#macro>\n<#macro passed></#macro>\n<@called/>\n<#assign m = passed>\n";
+        let config = Config {
+            enabled_diagnostics: ["unused_macro".to_owned()].into(),
+            ..Default::default()
+        };
+        let diagnostics: Vec<_> = diagnostics_of(source, config)
+            .into_iter()
+            .filter(|d| has_code(d, "unused_macro"))
+            .collect();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start, Position::new(0, 8));
+        assert_eq!(
+            diagnostics[0].message,
+            "'unused' is defined but never called."
+        );
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::HINT));
+        assert_eq!(diagnostics[0].tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+        // off by default
+        assert!(
+            !diagnostics_of(source, Config::default())
+                .iter()
+                .any(|d| has_code(d, "unused_macro"))
+        );
+        // or turned on by its own option
+        let config = Config::from_initialization_options(Some(serde_json::json!({
+            "lintUnusedMacros": true
+        })));
+        assert_eq!(
+            diagnostics_of(source, config)
+                .iter()
+                .filter(|d| has_code(d, "unused_macro"))
+                .count(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_workspace_symbols() {
+        let workspace = Workspace::new();
+        for (path, text) in [
+            (
+                "/tmp/a.ftl",
+                "<#macro formRow>\n</#macro>\n<#macro footer>\n</#macro>\n",
+            ),
+            (
+                "/tmp/b.ftl",
+                "<#assign form = 1>\n<#macro inputForm>\n</#macro>\n",
+            ),
+        ] {
+            workspace
+                .on_did_open(&DidOpenTextDocumentParams {
+                    text_document: TextDocumentItem {
+                        uri: Uri::from_str(&format!("file://{path}")).unwrap(),
+                        language_id: "ftl".to_owned(),
+                        version: 0,
+                        text: text.to_owned(),
+                    },
+                })
+                .await;
+        }
+        let Some(WorkspaceSymbolResponse::Flat(symbols)) = workspace
+            .on_workspace_symbol(WorkspaceSymbolParams {
+                query: "FORM".to_owned(),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+        else {
+            panic!("expect flat symbols");
+        };
+        let found: Vec<(&str, &str, Range)> = symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.location.uri.as_str(), s.location.range))
+            .collect();
+        // the variable "form" is not a macro
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "formRow",
+                    "file:///tmp/a.ftl",
+                    Range::new(Position::new(0, 8), Position::new(0, 15))
+                ),
+                (
+                    "inputForm",
+                    "file:///tmp/b.ftl",
+                    Range::new(Position::new(1, 8), Position::new(1, 17))
+                ),
+            ]
+        );
+        assert!(symbols.iter().all(|s| s.kind == SymbolKind::FUNCTION));
+    }
+
+    #[test]
+    fn test_circular_import() {
+        let dir = utils::fixture_dir(
+            "circular_import",
+            &[
+                (
+                    "a.ftl",
+                    "<#import \"b.ftl\" as b>\n<#import \"d.ftl\" as d>\n",
+                ),
+                ("b.ftl", "<#import \"lib/c.ftl\" as c>\n"),
+                ("lib/c.ftl", "<#-- c -->\n<#import \"../a.ftl\" as a>\n"),
+                ("d.ftl", ""),
+            ],
+        );
+        let uri = Uri::from_file_path(dir.join("a.ftl")).unwrap();
+        let source = std::fs::read_to_string(dir.join("a.ftl")).unwrap();
+        let analyze = |config: Config| {
+            Reactor::new(&uri, &source, 0, Arc::new(config), Default::default())
+                .get_analysis()
+                .get_analyzed_full_diagnostics()
+                .full_document_diagnostic_report
+                .items
+                .into_iter()
+                .filter(|d| has_code(d, "circular_import"))
+                .collect::<Vec<_>>()
+        };
+
+        let diagnostics = analyze(Config::default());
+        assert_eq!(diagnostics.len(), 1);
+        let circular = &diagnostics[0];
+        assert!(has_code(circular, "circular_import"));
+        assert_eq!(circular.severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(circular.range.start, Position::new(0, 9));
+        // b.ftl imports c.ftl, which imports a.ftl
+        let hops: Vec<(Uri, Position)> = circular
+            .related_information
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|hop| (hop.location.uri.clone(), hop.location.range.start))
+            .collect();
+        assert_eq!(
+            hops,
+            vec![
+                (
+                    Uri::from_file_path(dir.join("b.ftl")).unwrap(),
+                    Position::new(0, 9)
+                ),
+                (
+                    Uri::from_file_path(dir.join("lib/c.ftl")).unwrap(),
+                    Position::new(1, 9)
+                ),
+            ]
+        );
+
+        // the cycle is longer than the imports followed
+        let config = Config {
+            max_import_depth: Some(1),
+            ..Default::default()
+        };
+        assert!(analyze(config).is_empty());
     }
 }
@@ -2,16 +2,18 @@
 // Licensed under the BSD 3-Clause License.
 // SPDX-License-Identifier: BSD-3-Clause
 
-use std::{ops::BitOr, str::FromStr};
+use std::{ops::BitOr, str::FromStr, sync::Mutex};
 
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 use tower_lsp_server::{
     jsonrpc,
     ls_types::{
-        SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens,
-        SemanticTokensFullOptions, SemanticTokensLegend, SemanticTokensOptions,
-        SemanticTokensParams, SemanticTokensResult, SemanticTokensServerCapabilities,
+        self, Position, SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens,
+        SemanticTokensDelta, SemanticTokensDeltaParams, SemanticTokensEdit,
+        SemanticTokensFullDeltaResult, SemanticTokensFullOptions, SemanticTokensLegend,
+        SemanticTokensOptions, SemanticTokensParams, SemanticTokensRangeParams,
+        SemanticTokensRangeResult, SemanticTokensResult, SemanticTokensServerCapabilities,
         WorkDoneProgressOptions,
     },
 };
@@ -72,6 +74,7 @@ impl From<TokenType> for SemanticTokenType {
 enum Modifier {
     Deprecated, // normally deprecated text will be strike-through
     Readonly,   // normally mutable variables will have lighter color than read-only ones.
+    Todo,       // not a standard one, "TODO" or "FIXME" of a comment
 }
 
 impl From<Modifier> for SemanticTokenModifier {
@@ -79,6 +82,7 @@ impl From<Modifier> for SemanticTokenModifier {
         match val {
             Modifier::Deprecated => SemanticTokenModifier::DEPRECATED,
             Modifier::Readonly => SemanticTokenModifier::READONLY,
+            Modifier::Todo => SemanticTokenModifier::new("todo"),
         }
     }
 }
@@ -96,6 +100,7 @@ impl BitOr for Modifiers {
 
 const READONLY: Modifiers = Modifiers(1 << (Modifier::Readonly as u8));
 const DEPRECATED: Modifiers = Modifiers(1 << (Modifier::Deprecated as u8));
+const TODO: Modifiers = Modifiers(1 << (Modifier::Todo as u8));
 
 struct Token(TokenType, Range, Option<Modifiers>);
 
@@ -139,11 +144,18 @@ fn tokenize_from(node: &Node<'_>) -> Option<Token> {
             | Rule::MacroCloseTag
             | Rule::MacroClose
             | Rule::MacroCallBegin
-            | Rule::MacroCallEnd
-            | Rule::InterpolationPrepend => Some(Token(TokenType::Macro, range, None)),
-            Rule::ImportAlias | Rule::MacroNamespace => {
-                Some(Token(TokenType::Namespace, range, None))
-            }
+            | Rule::MacroCallEnd => Some(Token(TokenType::Macro, range, None)),
+            // the delimiters of `${x}` are punctuation, colored like the other operators
+            Rule::InterpolationPrepend => Some(Token(TokenType::Operator, range, None)),
+            // the called user-defined directive is a decorator: "foo" of `<@foo/>` and
+            // "bar" of `<@lib.bar/>`, while "lib" remains a namespace
+            Rule::MacroNamespace => match node.next_named_sibling().map(|n| n.kind()) {
+                Some(next) if next == Rule::MacroSpecs.to_string() => {
+                    Some(Token(TokenType::Namespace, range, None))
+                }
+                _ => Some(Token(TokenType::Decorator, range, None)),
+            },
+            Rule::ImportAlias => Some(Token(TokenType::Namespace, range, None)),
             Rule::Number => Some(Token(TokenType::Number, range, None)),
             Rule::EqualOperator
             | Rule::AssignOperator
@@ -156,9 +168,24 @@ fn tokenize_from(node: &Node<'_>) -> Option<Token> {
                 Some(Token(TokenType::Operator, range, Some(DEPRECATED)))
             }
             Rule::ParameterName => Some(Token(TokenType::Parameter, range, None)),
-            Rule::Variable | Rule::Identifier | Rule::MacroSpecs => {
-                Some(Token(TokenType::Variable, range, None))
+            Rule::Identifier
+                if node
+                    .parent()
+                    .is_some_and(|parent| parent.kind() == Rule::MacroSpecs.to_string()) =>
+            {
+                Some(Token(TokenType::Decorator, range, None))
             }
+            // the name of `${add(1, 2)}` is already a call token, and "user" of
+            // `${user.name}` a variable token
+            Rule::Identifier
+                if node.parent().is_some_and(|parent| {
+                    parent.kind() == Rule::FunctionName.to_string()
+                        || parent.kind() == Rule::Variable.to_string()
+                }) =>
+            {
+                None
+            }
+            Rule::Variable | Rule::Identifier => Some(Token(TokenType::Variable, range, None)),
             Rule::StringLiteral | Rule::ImportPath | Rule::AmbiguousStringLiteral => {
                 Some(Token(TokenType::String, range, None))
             }
@@ -174,6 +201,15 @@ fn tokenize_from(node: &Node<'_>) -> Option<Token> {
                 None
             }
         },
+        // the anonymous "{" and "}" of an interpolation have no rule of their own
+        Err(_unknown)
+            if matches!(kind, "{" | "}")
+                && node
+                    .parent()
+                    .is_some_and(|parent| parent.kind() == Rule::Interpolation.to_string()) =>
+        {
+            Some(Token(TokenType::Operator, range, None))
+        }
         Err(_unknown) => None,
     }
 }
@@ -190,11 +226,91 @@ pub fn semantic_token_capability() -> SemanticTokensServerCapabilities {
             token_types: TokenType::iter().map(|t| t.into()).collect(),
             token_modifiers: Modifier::iter().map(|m| m.into()).collect(),
         },
-        range: None,
-        full: Some(SemanticTokensFullOptions::Bool(true)),
+        range: Some(true),
+        full: Some(SemanticTokensFullOptions::Delta { delta: Some(true) }),
     })
 }
 
+// the integers of a token in the encoded array, which the edits are counted in
+const SEMANTIC_TOKEN_INTEGERS: u32 = 5;
+
+// The tokens last sent for a document, kept across the versions so that the next
+// request is answered by the edits against them
+#[derive(Debug, Default)]
+pub struct SemanticTokensCache {
+    previous: Mutex<Option<(String, Vec<SemanticToken>)>>,
+}
+
+impl SemanticTokensCache {
+    pub fn get(&self, result_id: &str) -> Option<Vec<SemanticToken>> {
+        match &*self.previous.lock().unwrap() {
+            Some((id, tokens)) if id == result_id => Some(tokens.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&self, result_id: String, tokens: Vec<SemanticToken>) {
+        *self.previous.lock().unwrap() = Some((result_id, tokens));
+    }
+}
+
+// a single edit replacing what is between the common prefix and the common suffix
+fn semantic_tokens_edits(
+    previous: &[SemanticToken],
+    current: &[SemanticToken],
+) -> Vec<SemanticTokensEdit> {
+    let prefix = previous
+        .iter()
+        .zip(current)
+        .take_while(|(p, c)| p == c)
+        .count();
+    if prefix == previous.len() && prefix == current.len() {
+        return vec![];
+    }
+    let suffix = previous[prefix..]
+        .iter()
+        .rev()
+        .zip(current[prefix..].iter().rev())
+        .take_while(|(p, c)| p == c)
+        .count();
+    vec![SemanticTokensEdit {
+        start: prefix as u32 * SEMANTIC_TOKEN_INTEGERS,
+        delete_count: (previous.len() - prefix - suffix) as u32 * SEMANTIC_TOKEN_INTEGERS,
+        data: Some(current[prefix..current.len() - suffix].to_vec()),
+    }]
+}
+
+// The tokens which intersect the range, as if the others were not there: the first one
+// is relative to the start of the document again
+fn semantic_tokens_in_range(
+    tokens: &[SemanticToken],
+    range: &ls_types::Range,
+) -> Vec<SemanticToken> {
+    let mut in_range = vec![];
+    let (mut start, mut prev_start) = (Position::default(), Position::default());
+    for token in tokens {
+        start = match token.delta_line {
+            0 => Position::new(start.line, start.character + token.delta_start),
+            delta_line => Position::new(start.line + delta_line, token.delta_start),
+        };
+        let end = Position::new(start.line, start.character + token.length);
+        if end <= range.start || start >= range.end {
+            continue;
+        }
+        let delta_line = start.line - prev_start.line;
+        in_range.push(SemanticToken {
+            delta_line,
+            delta_start: match delta_line {
+                0 => start.character - prev_start.character,
+                _ => start.character,
+            },
+            ..*token
+        });
+        prev_start = start;
+    }
+    in_range
+}
+
 fn encode_semantic_token(
     prev_start: &Point,
     token_type: TokenType,
@@ -222,6 +338,56 @@ fn encode_semantic_token(
     }
 }
 
+// emphasized inside the comments
+const COMMENT_MARKERS: [&str; 2] = ["TODO", "FIXME"];
+
+// (byte offset, length) of the whole words "TODO" and "FIXME" in a comment line
+fn comment_markers(line: &str) -> Vec<(usize, usize)> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut markers: Vec<(usize, usize)> = COMMENT_MARKERS
+        .iter()
+        .flat_map(|marker| line.match_indices(marker))
+        .filter(|(offset, marker)| {
+            !line[..*offset].ends_with(is_word)
+                && !line[offset + marker.len()..].starts_with(is_word)
+        })
+        .map(|(offset, marker)| (offset, marker.len()))
+        .collect();
+    markers.sort();
+    markers
+}
+
+// a comment line is split around its markers, which are comments with the "todo" modifier
+fn split_comment_line(
+    doc: &TextDocument,
+    start: Point,
+    length: usize,
+) -> Vec<(Option<Modifiers>, Point, usize)> {
+    let line = doc.get_line_text(start.row);
+    let end = (start.column + length).min(line.len());
+    let Some(text) = line.get(start.column..end) else {
+        return vec![(None, start, length)];
+    };
+    let mut spans = vec![];
+    let mut column = start.column;
+    for (offset, marker_len) in comment_markers(text) {
+        let marker_column = start.column + offset;
+        if marker_column > column {
+            spans.push((None, Point::new(start.row, column), marker_column - column));
+        }
+        spans.push((Some(TODO), Point::new(start.row, marker_column), marker_len));
+        column = marker_column + marker_len;
+    }
+    if start.column + length > column {
+        spans.push((
+            None,
+            Point::new(start.row, column),
+            start.column + length - column,
+        ));
+    }
+    spans
+}
+
 impl HighlightAnalysis for Analysis {
     fn analyze_semantic_highlight(
         &mut self,
@@ -234,63 +400,52 @@ impl HighlightAnalysis for Analysis {
             // not sure if it is proper
             return;
         }
+        let Some(Token(token_type, range, modifiers)) = tokenize_from(node) else {
+            return;
+        };
+        // (start, length) of each line, multi-line token is not allowed
+        let mut lines = vec![];
+        if range.end_point.row == range.start_point.row {
+            // single-line token
+            lines.push((range.start_point, range.end_byte - range.start_byte));
+        } else {
+            // token of 1st line
+            let first_start = range.start_point;
+            lines.push((first_start, doc.rope.line(first_start.row).len_bytes()));
+            // tokens from 2nd to last-1 line
+            for next_row in first_start.row + 1..range.end_point.row {
+                lines.push((Point::new(next_row, 0), doc.rope.line(next_row).len_bytes()));
+            }
+            // token of last line
+            lines.push((Point::new(range.end_point.row, 0), range.end_point.column));
+        }
+        // most comments have no marker, they remain a token per line
+        let has_markers = token_type == TokenType::Comment && {
+            let text = doc.get_ranged_text(range.start_byte..range.end_byte);
+            COMMENT_MARKERS.iter().any(|marker| text.contains(marker))
+        };
         let mut semantic_tokens = vec![];
-        if let Some(token) = tokenize_from(node) {
-            let Token(token_type, range, modifiers) = token;
-            if range.end_point.row == range.start_point.row {
-                // single-line token
-                semantic_tokens.push(encode_semantic_token(
-                    &ctx.prev_start,
-                    token_type,
-                    &range.start_point,
-                    range.end_byte - range.start_byte,
-                    modifiers,
-                ));
-                ctx.prev_start = range.start_point;
-            } else {
-                // multi-line token is not allowed, so split which into multiple inline tokens
-                // token of 1st line
-                let first_start = range.start_point;
-                let first_line_len = doc.line_len(first_start.row).unwrap();
-                semantic_tokens.push(encode_semantic_token(
-                    &ctx.prev_start,
-                    token_type,
-                    &first_start,
-                    first_line_len,
-                    modifiers,
-                ));
-                ctx.prev_start = first_start;
-                // tokens from 2nd to last-1 line
-                let mut next_row = first_start.row + 1;
-                while next_row < range.end_point.row {
-                    let next_start = Point {
-                        row: next_row,
-                        column: 0,
-                    };
-                    let next_line_len = doc.line_len(next_row).unwrap();
-                    semantic_tokens.push(encode_semantic_token(
-                        &ctx.prev_start,
-                        token_type,
-                        &next_start,
-                        next_line_len,
-                        modifiers,
-                    ));
-                    next_row += 1;
-                    ctx.prev_start = next_start;
-                }
-                // token of last line
-                let last_start = Point {
-                    row: range.end_point.row,
-                    column: 0,
-                };
+        for (start, length) in lines {
+            let spans = match has_markers {
+                true => split_comment_line(doc, start, length),
+                false => vec![(modifiers, start, length)],
+            };
+            for (modifiers, start, length) in spans {
+                // the columns are sent in the negotiated encoding
+                let end = doc.byte_column_to_character(start.row, start.column + length);
+                let start = Point::new(
+                    start.row,
+                    doc.byte_column_to_character(start.row, start.column) as usize,
+                );
+                let length = end as usize - start.column;
                 semantic_tokens.push(encode_semantic_token(
                     &ctx.prev_start,
                     token_type,
-                    &last_start,
-                    range.end_point.column,
+                    &start,
+                    length,
                     modifiers,
                 ));
-                ctx.prev_start = last_start;
+                ctx.prev_start = start;
             }
         }
         self.add_semantic_tokens(semantic_tokens);
@@ -303,9 +458,348 @@ impl SemanticTokenFeature for Reactor {
         params: SemanticTokensParams,
     ) -> jsonrpc::Result<Option<SemanticTokensResult>> {
         let _ = params;
+        let result_id = self.version.to_string();
+        let data = self.get_analysis().get_analyzed_semantic_tokens();
+        self.get_tokens_cache()
+            .insert(result_id.clone(), data.clone());
         Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
-            result_id: Some(self.version.to_string()),
-            data: self.get_analysis().get_analyzed_semantic_tokens(),
+            result_id: Some(result_id),
+            data,
+        })))
+    }
+
+    async fn on_semantic_tokens_range(
+        &self,
+        params: SemanticTokensRangeParams,
+    ) -> jsonrpc::Result<Option<SemanticTokensRangeResult>> {
+        // the tokens of the whole document are analyzed already, only the viewport is sent
+        let tokens = self.get_analysis().get_analyzed_semantic_tokens();
+        Ok(Some(SemanticTokensRangeResult::Tokens(SemanticTokens {
+            result_id: None,
+            data: semantic_tokens_in_range(&tokens, &params.range),
         })))
     }
+
+    async fn on_semantic_tokens_full_delta(
+        &self,
+        params: SemanticTokensDeltaParams,
+    ) -> jsonrpc::Result<Option<SemanticTokensFullDeltaResult>> {
+        let result_id = self.version.to_string();
+        let data = self.get_analysis().get_analyzed_semantic_tokens();
+        let cache = self.get_tokens_cache();
+        // the whole array when the previous tokens are not known (anymore)
+        let result = match cache.get(&params.previous_result_id) {
+            Some(previous) => SemanticTokensFullDeltaResult::TokensDelta(SemanticTokensDelta {
+                result_id: Some(result_id.clone()),
+                edits: semantic_tokens_edits(&previous, &data),
+            }),
+            None => SemanticTokensFullDeltaResult::Tokens(SemanticTokens {
+                result_id: Some(result_id.clone()),
+                data: data.clone(),
+            }),
+        };
+        cache.insert(result_id, data);
+        Ok(Some(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tower_lsp_server::ls_types::{
+        Position, Range, SemanticToken, SemanticTokenModifier, SemanticTokensDeltaParams,
+        SemanticTokensFullDeltaResult, SemanticTokensParams, SemanticTokensRangeParams,
+        SemanticTokensRangeResult, SemanticTokensServerCapabilities,
+        TextDocumentContentChangeEvent, TextDocumentIdentifier, Uri,
+    };
+
+    use super::{Modifier, TODO, TokenType, semantic_token_capability};
+    use crate::{doc::PositionEncodingKind, reactor::Reactor, server::SemanticTokenFeature};
+
+    // (line, character, length, token type) of each token
+    fn decode_tokens(source: &str) -> Vec<(u32, u32, u32, u32)> {
+        let uri = Uri::from_str("file:///tmp/tokenizer.ftl").unwrap();
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        decode(reactor.get_analysis().get_analyzed_semantic_tokens())
+    }
+
+    fn decode(tokens: Vec<SemanticToken>) -> Vec<(u32, u32, u32, u32)> {
+        let (mut line, mut character) = (0, 0);
+        tokens
+            .into_iter()
+            .map(|token| {
+                if token.delta_line > 0 {
+                    character = 0;
+                }
+                line += token.delta_line;
+                character += token.delta_start;
+                (line, character, token.length, token.token_type)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_macro_call_decorators() {
+        let tokens = decode_tokens("<@foo/>\n<@lib.bar/>\n");
+        let decorator = TokenType::Decorator as u32;
+        let namespace = TokenType::Namespace as u32;
+        assert!(tokens.contains(&(0, 2, 3, decorator)));
+        assert!(tokens.contains(&(1, 2, 3, namespace)));
+        assert!(tokens.contains(&(1, 6, 3, decorator)));
+        // tokens must not overlap
+        assert!(
+            tokens
+                .windows(2)
+                .all(|pair| { pair[0].0 < pair[1].0 || pair[0].1 + pair[0].2 <= pair[1].1 })
+        );
+    }
+
+    #[test]
+    fn test_user_function_call() {
+        let tokens =
+            decode_tokens("<#function add a b>\n<#return a + b>\n</#function>\n${add(1, 2)}\n");
+        let call = TokenType::Call as u32;
+        // both the definition and the call
+        assert!(tokens.contains(&(0, 11, 3, call)));
+        assert!(tokens.contains(&(3, 2, 3, call)));
+        assert_eq!(
+            tokens
+                .iter()
+                .filter(|token| token.0 == 3 && token.1 == 2)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_capture_assign_body() {
+        let tokens = decode_tokens("<#assign html>\n<p>${x}</p>\n<#if y>z</#if>\n</#assign>\n");
+        let keyword = TokenType::Keyword as u32;
+        // the captured body is template content
+        assert!(tokens.contains(&(1, 3, 1, TokenType::Operator as u32)));
+        assert!(tokens.contains(&(1, 5, 1, TokenType::Variable as u32)));
+        assert!(tokens.contains(&(2, 0, 4, keyword)));
+        assert!(tokens.contains(&(2, 8, 6, keyword)));
+        assert!(tokens.contains(&(3, 0, 10, keyword)));
+    }
+
+    #[test]
+    fn test_interpolation_delimiters() {
+        let tokens = decode_tokens("${x}\n<@foo/>\n");
+        let operator = TokenType::Operator as u32;
+        let r#macro = TokenType::Macro as u32;
+        assert!(tokens.contains(&(0, 0, 1, operator)));
+        assert!(tokens.contains(&(0, 1, 1, operator)));
+        assert!(tokens.contains(&(0, 3, 1, operator)));
+        assert!(
+            !tokens
+                .iter()
+                .any(|token| token.0 == 0 && token.3 == r#macro)
+        );
+        // the macro constructs keep their token type
+        assert!(tokens.contains(&(1, 0, 2, r#macro)));
+        assert!(tokens.contains(&(1, 5, 2, r#macro)));
+    }
+
+    #[test]
+    fn test_interpolation_expression() {
+        let tokens = decode_tokens("${user.name?upper_case}\n${\n  a.b\n}\n");
+        let operator = TokenType::Operator as u32;
+        let variable = TokenType::Variable as u32;
+        let call = TokenType::Call as u32;
+        assert_eq!(
+            tokens,
+            vec![
+                (0, 0, 1, operator),
+                (0, 1, 1, operator),
+                (0, 2, 4, variable),
+                (0, 7, 4, variable),
+                (0, 12, 10, call),
+                (0, 22, 1, operator),
+                // the expression of a multi-line interpolation
+                (1, 0, 1, operator),
+                (1, 1, 1, operator),
+                (2, 2, 1, variable),
+                (2, 4, 1, variable),
+                (3, 0, 1, operator),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comment_markers() {
+        let uri = Uri::from_str("file:///tmp/tokenizer.ftl").unwrap();
+        let source = "<#-- TODO: a\nFIXME b TODOS -->\n<#-- c -->\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let tokens = reactor.get_analysis().get_analyzed_semantic_tokens();
+        let comment = TokenType::Comment as u32;
+        assert!(tokens.iter().all(|token| token.token_type == comment));
+        let todo = TODO.0;
+        let spans: Vec<_> = decode(tokens.clone())
+            .into_iter()
+            .zip(tokens.iter().map(|token| token.token_modifiers_bitset))
+            .map(|((line, character, length, _), modifiers)| (line, character, length, modifiers))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (0, 0, 5, 0),
+                (0, 5, 4, todo),
+                (0, 9, 4, 0),
+                (1, 0, 5, todo),
+                (1, 5, 12, 0),
+                (2, 0, 10, 0),
+            ]
+        );
+        let SemanticTokensServerCapabilities::SemanticTokensOptions(options) =
+            semantic_token_capability()
+        else {
+            panic!("expect the options");
+        };
+        assert_eq!(
+            options.legend.token_modifiers[Modifier::Todo as usize],
+            SemanticTokenModifier::new("todo")
+        );
+    }
+
+    #[test]
+    fn test_bom_first_line() {
+        let with_bom = decode_tokens("\u{feff}<#if a>${b}</#if>\n${c}\n");
+        // the columns of the client count the BOM on the first line only
+        let shifted: Vec<_> = decode_tokens("<#if a>${b}</#if>\n${c}\n")
+            .into_iter()
+            .map(|(line, character, length, kind)| match line {
+                0 => (line, character + 1, length, kind),
+                _ => (line, character, length, kind),
+            })
+            .collect();
+        assert_eq!(with_bom, shifted);
+        assert_eq!(with_bom[0].1, 1);
+    }
+
+    #[tokio::test]
+    async fn test_semantic_tokens_range() {
+        let uri = Uri::from_str("file:///tmp/tokenizer.ftl").unwrap();
+        let source = "<#assign a = 1>\n<#if a>\n${b} ${c}\n</#if>\n<@foo/>\n";
+        let reactor = Reactor::new(&uri, source, 0, Default::default(), Default::default());
+        let full = decode(reactor.get_analysis().get_analyzed_semantic_tokens());
+        let range = Range::new(Position::new(1, 5), Position::new(2, 7));
+        let Some(SemanticTokensRangeResult::Tokens(tokens)) = reactor
+            .on_semantic_tokens_range(SemanticTokensRangeParams {
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                range,
+            })
+            .await
+            .unwrap()
+        else {
+            panic!("expect tokens");
+        };
+        let in_range = decode(tokens.data);
+        // "a" of the "<#if a>" up to "c" which starts before the end
+        let expected: Vec<_> = full
+            .iter()
+            .copied()
+            .filter(|&(line, character, length, _)| {
+                Position::new(line, character + length) > range.start
+                    && Position::new(line, character) < range.end
+            })
+            .collect();
+        assert!(!expected.is_empty());
+        assert!(expected.iter().all(|token| token.0 == 1 || token.0 == 2));
+        let first = full.iter().position(|token| *token == expected[0]).unwrap();
+        assert_eq!(in_range, full[first..first + expected.len()]);
+    }
+
+    #[tokio::test]
+    async fn test_semantic_tokens_delta() {
+        let uri = Uri::from_str("file:///tmp/tokenizer.ftl").unwrap();
+        let source = "<#if a>\n${b}\n</#if>\n${c}\n";
+        let mut reactor = Reactor::new(&uri, source, 1, Default::default(), Default::default());
+        reactor
+            .on_semantic_tokens_full(SemanticTokensParams {
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+            })
+            .await
+            .unwrap();
+        let previous = reactor.get_analysis().get_analyzed_semantic_tokens();
+        // "${b}" becomes "${b?upper_case}"
+        reactor.apply_content_change(
+            2,
+            &TextDocumentContentChangeEvent {
+                range: Some(Range::new(Position::new(1, 3), Position::new(1, 3))),
+                range_length: None,
+                text: "?upper_case".to_owned(),
+            },
+            PositionEncodingKind::UTF16,
+        );
+        let delta = |previous_result_id: &str| SemanticTokensDeltaParams {
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            previous_result_id: previous_result_id.to_owned(),
+        };
+        let Some(SemanticTokensFullDeltaResult::TokensDelta(result)) = reactor
+            .on_semantic_tokens_full_delta(delta("1"))
+            .await
+            .unwrap()
+        else {
+            panic!("expect a delta");
+        };
+        assert_eq!(result.result_id.as_deref(), Some("2"));
+        assert_eq!(result.edits.len(), 1);
+        // applying the edit to the previous tokens gives the current ones
+        let edit = &result.edits[0];
+        let start = (edit.start / 5) as usize;
+        let mut patched: Vec<SemanticToken> = previous.clone();
+        patched.splice(
+            start..start + (edit.delete_count / 5) as usize,
+            edit.data.clone().unwrap(),
+        );
+        assert_eq!(
+            patched,
+            reactor.get_analysis().get_analyzed_semantic_tokens()
+        );
+        assert!(edit.start > 0 && (edit.delete_count as usize) < previous.len() * 5);
+        // the same version again, nothing changed
+        let Some(SemanticTokensFullDeltaResult::TokensDelta(result)) = reactor
+            .on_semantic_tokens_full_delta(delta("2"))
+            .await
+            .unwrap()
+        else {
+            panic!("expect a delta");
+        };
+        assert!(result.edits.is_empty());
+        // an unknown result id is answered by the whole array
+        assert!(matches!(
+            reactor
+                .on_semantic_tokens_full_delta(delta("1"))
+                .await
+                .unwrap(),
+            Some(SemanticTokensFullDeltaResult::Tokens(_))
+        ));
+    }
+
+    #[test]
+    fn test_many_multiline_tokens_in_linear_time() {
+        // generated templates may have thousands of multi-line comments and strings,
+        // it took over 15 seconds when every lookup copied the whole document
+        let uri = Uri::from_str("file:///tmp/tokenizer.ftl").unwrap();
+        let copied_bytes = |count: usize| {
+            let source = "<#-- a\nlong\ncomment --><#assign x = \"a\nb\"> ${x}\n".repeat(count);
+            let reactor = Reactor::new(&uri, &source, 0, Default::default(), Default::default());
+            assert_eq!(
+                decode(reactor.get_analysis().get_analyzed_semantic_tokens()).len(),
+                13 * count
+            );
+            reactor.get_document().copied_bytes()
+        };
+        // twice the text is copied about twice as much, not four times
+        let (once, twice) = (copied_bytes(1000), copied_bytes(2000));
+        assert!(twice < 3 * once, "{} bytes, then {} bytes", once, twice);
+    }
 }
@@ -5,33 +5,81 @@
 use tower_lsp_server::ls_types::{LanguageString, Position, Range};
 use tree_sitter::{Node, Point};
 
-pub fn parser_node_to_document_range(node: &Node) -> Range {
-    let start = node.start_position();
-    let end = node.end_position();
+use crate::doc::TextDocument;
+
+// the columns are converted to the negotiated encoding of the document
+pub fn parser_node_to_document_range(doc: &TextDocument, node: &Node) -> Range {
     Range {
-        start: Position {
-            line: start.row as u32,
-            character: start.column as u32,
-        },
-        end: Position {
-            line: end.row as u32,
-            character: end.column as u32,
-        },
+        start: doc.point_to_position(node.start_position()),
+        end: doc.point_to_position(node.end_position()),
     }
 }
 
-pub fn lsp_position_to_parser_point(position: &Position) -> Point {
-    Point {
-        row: position.line as usize,
-        column: position.character as usize,
+// A tag of a directive the grammar doesn't know yet, e.g. <#setting>, <#include>, <#escape>
+// or <#global>, which ends up as an ERROR node starting with the tag
+pub struct ErrorNodeDirective {
+    // "setting" of `<#setting locale="en_US">`
+    pub name: String,
+    // `</#escape>`
+    pub closing: bool,
+    // what follows the name, up to the end of the node
+    pub rest: String,
+    pub rest_byte: usize,
+}
+
+pub fn error_node_directive(node: &Node, doc: &TextDocument) -> Option<ErrorNodeDirective> {
+    if !node.is_error() {
+        return None;
     }
+    let text = doc.get_ranged_text(node.byte_range());
+    let (closing, tag) = match text.strip_prefix("</#") {
+        Some(tag) => (true, tag),
+        None => (false, text.strip_prefix("<#")?),
+    };
+    let name_len = tag
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(tag.len());
+    if name_len == 0 {
+        return None;
+    }
+    Some(ErrorNodeDirective {
+        name: tag[..name_len].to_owned(),
+        closing,
+        rest: tag[name_len..].to_owned(),
+        rest_byte: node.end_byte() - tag.len() + name_len,
+    })
+}
+
+pub fn lsp_position_to_parser_point(doc: &TextDocument, position: &Position) -> Point {
+    doc.position_to_point(position)
+}
+
+// the range which removes the bytes of a statement, along with its lines and the last
+// line break when nothing else is left on them
+pub fn removal_range(doc: &TextDocument, bytes: std::ops::Range<usize>) -> Range {
+    let rope = &doc.rope;
+    let line_start = rope.line_to_byte(rope.byte_to_line(bytes.start));
+    let next_line_start = rope.line_to_byte(rope.byte_to_line(bytes.end) + 1);
+    let blank = |range: std::ops::Range<usize>| doc.get_ranged_text(range).trim().is_empty();
+    let bytes = match blank(line_start..bytes.start) && blank(bytes.end..next_line_start) {
+        true => line_start..next_line_start,
+        false => bytes,
+    };
+    Range::new(
+        doc.byte_to_position(bytes.start),
+        doc.byte_to_position(bytes.end),
+    )
 }
 
 pub fn ftl_to_rust(ftl_text: &str) -> LanguageString {
     // for highlighting in hover
     let line_trimmed = ftl_text.trim();
     let mut result = String::from(line_trimmed);
-    if result.starts_with("<#import") || result.starts_with("<#macro") {
+    if result.starts_with("<#import")
+        || result.starts_with("<#macro")
+        || result.starts_with("<#assign")
+        || result.starts_with("<#local")
+    {
         result = result.replacen("<#", "", 1);
     }
     if result.ends_with('>') {
@@ -42,3 +90,19 @@ pub fn ftl_to_rust(ftl_text: &str) -> LanguageString {
         value: result,
     }
 }
+
+#[cfg(test)]
+pub fn fixture_dir(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+    let dir = std::env::temp_dir()
+        .join(crate::server::Server::CODE_NAME)
+        .join("fixtures")
+        .join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (file, text) in files {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, text).unwrap();
+    }
+    dir.canonicalize().unwrap()
+}
@@ -3,10 +3,19 @@
 // SPDX-License-Identifier: BSD-3-Clause
 
 use crate::{
+    action,
+    config::Config,
+    diagnosis::sort_diagnostics,
+    doc::PositionEncodingKind,
+    import::ImportCache,
+    progress::{ClientProgress, ProgressTracker},
     reactor::Reactor,
     server::{
-        ActionFeature, CompletionFeature, DiagnosticFeature, FoldingFeature, FormatFeature,
-        GotoFeature, HoverFeature, SemanticTokenFeature,
+        ActionFeature, CompletionFeature, DiagnosticFeature, DocumentHighlightFeature,
+        DocumentLinkFeature, DocumentSymbolFeature, FoldingFeature, FormatFeature, GotoFeature,
+        HoverFeature, InlayHintFeature, InlineValueFeature, OnTypeFormatFeature,
+        RangeFormatFeature, ReferencesFeature, RenameFeature, SelectionRangeFeature,
+        SemanticTokenFeature,
     },
     window_log_info,
 };
@@ -18,40 +27,93 @@ use tower_lsp_server::{
     ls_types::{
         CodeActionOrCommand, CodeActionParams, CompletionParams, CompletionResponse,
         DeleteFilesParams, DidChangeTextDocumentParams, DidChangeWatchedFilesParams,
-        DidOpenTextDocumentParams, DocumentDiagnosticParams, DocumentDiagnosticReportResult,
-        DocumentFormattingParams, FileChangeType, FoldingRange, FoldingRangeParams,
-        GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverParams, SemanticTokensParams,
-        SemanticTokensResult, TextDocumentContentChangeEvent, TextEdit, Uri,
+        DidOpenTextDocumentParams, DocumentDiagnosticParams, DocumentDiagnosticReport,
+        DocumentDiagnosticReportResult, DocumentFormattingParams, DocumentHighlight,
+        DocumentHighlightParams, DocumentLink, DocumentLinkParams, DocumentOnTypeFormattingParams,
+        DocumentRangeFormattingParams, DocumentSymbolParams, DocumentSymbolResponse,
+        FileChangeType, FoldingRange, FoldingRangeParams, GotoDefinitionParams,
+        GotoDefinitionResponse, Hover, HoverParams, InlayHint, InlayHintParams, InlineValue,
+        InlineValueParams, Location, PrepareRenameResponse, ReferenceParams, RenameParams,
+        SelectionRange, SelectionRangeParams, SemanticTokensDeltaParams,
+        SemanticTokensFullDeltaResult, SemanticTokensParams, SemanticTokensRangeParams,
+        SemanticTokensRangeResult, SemanticTokensResult, TextDocumentContentChangeEvent,
+        TextDocumentPositionParams, TextEdit, Uri, WorkspaceEdit,
     },
 };
 
 #[derive(Debug)]
 pub struct Workspace {
-    reactors: Arc<RwLock<HashMap<Uri, Reactor>>>,
+    pub(crate) reactors: Arc<RwLock<HashMap<Uri, Reactor>>>,
+    pub(crate) imports: ImportCache,
+    config: RwLock<Arc<Config>>,
+    // negotiated during initialization
+    position_encoding: RwLock<PositionEncodingKind>,
+    // the client computes the edits of the code actions when they are selected
+    code_action_resolve: RwLock<bool>,
 }
 
-const GET_REACTOR_EXPECT: &str = "get reactor via uri should always succeed";
+pub(crate) const GET_REACTOR_EXPECT: &str = "get reactor via uri should always succeed";
+
+const ANALYSIS_TOKEN: &str = "freemarker/analysis";
+const LARGE_DOCUMENT_BYTES: usize = 1 << 20;
 
 impl Workspace {
     pub fn new() -> Self {
         Self {
             reactors: Arc::new(RwLock::new(HashMap::new())),
+            imports: ImportCache::new(),
+            config: RwLock::new(Arc::new(Config::default())),
+            position_encoding: RwLock::new(PositionEncodingKind::UTF16),
+            code_action_resolve: RwLock::new(false),
         }
     }
 
+    pub async fn set_config(&self, config: Config) {
+        let config = Arc::new(config);
+        *self.config.write().await = config.clone();
+        self.imports.set_config(config).await;
+    }
+
+    pub async fn get_config(&self) -> Arc<Config> {
+        self.config.read().await.clone()
+    }
+
+    pub async fn set_position_encoding(&self, position_encoding: PositionEncodingKind) {
+        *self.position_encoding.write().await = position_encoding;
+    }
+
+    pub async fn set_code_action_resolve(&self, code_action_resolve: bool) {
+        *self.code_action_resolve.write().await = code_action_resolve;
+    }
+
     pub async fn on_did_open(&self, params: &DidOpenTextDocumentParams) {
         let uri: &Uri = &params.text_document.uri;
         window_log_info!(format!("on_did_open: {:?}", uri.to_string()));
         let version: i32 = params.text_document.version;
         window_log_info!(format!("document version: {:?}", version));
+        let source_code = params.text_document.text.as_str();
+        // the analysis of a large document takes a while, the token is requested before locking
+        let mut progress = match source_code.len() > LARGE_DOCUMENT_BYTES {
+            true => ClientProgress::create(ANALYSIS_TOKEN).await,
+            false => None,
+        };
         let mut write_guard = self.reactors.write().await;
         if match write_guard.get(uri) {
             Some(old_reactor) => old_reactor.version != version,
             None => true,
         } {
-            let source_code = params.text_document.text.as_str();
-            let reactor = Reactor::new(uri, source_code, version);
+            let mut tracker =
+                ProgressTracker::begin(&mut progress, "Analyzing FreeMarker template", 1).await;
+            let reactor = Reactor::new(
+                uri,
+                source_code,
+                version,
+                self.get_config().await,
+                self.imports.validity(),
+            );
             write_guard.insert(uri.clone(), reactor);
+            tracker.step(uri.to_string()).await;
+            tracker.end(format!("{} bytes", source_code.len())).await;
         }
     }
 
@@ -60,26 +122,30 @@ impl Workspace {
         let version = params.text_document.version;
         tracing::debug!("on_did_change: {}", uri.to_string());
         for change_event in &params.content_changes {
-            // assume only changes
-            if let Some(range) = change_event.range {
-                tracing::debug!("range: {:?}", range);
-                self.update_file(uri, version, change_event).await;
-            } else {
-                tracing::debug!("full text change");
+            match change_event.range {
+                Some(range) => tracing::debug!("range: {:?}", range),
+                // replaces the document, a BOM in the text included
+                None => tracing::debug!("full text change"),
             }
+            self.update_file(uri, version, change_event).await;
         }
     }
 
     async fn update_file(&self, uri: &Uri, version: i32, change: &TextDocumentContentChangeEvent) {
+        let position_encoding = *self.position_encoding.read().await;
         let mut write_guard = self.reactors.write().await;
         if let Some(reactor) = write_guard.get_mut(uri) {
             tracing::debug!("previous file version: {}", reactor.version);
-            reactor.apply_content_change(version, change);
+            reactor.apply_content_change(version, change, position_encoding);
         }
     }
 
     pub async fn on_did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
         let DidChangeWatchedFilesParams { changes } = params;
+        // imported templates might be changed
+        for change in &changes {
+            self.imports.invalidate(&change.uri).await;
+        }
         // filter delete events
         let uris: Vec<_> = changes
             .into_iter()
@@ -99,6 +165,7 @@ impl Workspace {
         for file_deletion in &params.files {
             let uri = Uri::from_str(&file_deletion.uri).unwrap();
             window_log_info!(format!("did delete file: {}", uri.to_string()));
+            self.imports.invalidate(&uri).await;
             self.reactors.write().await.remove(&uri);
         }
     }
@@ -111,7 +178,17 @@ impl Workspace {
         let uri = &params.text_document.uri;
         let read_guard = self.reactors.read().await;
         let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
-        reactor.on_diagnostic(params).await
+        let mut result = reactor.on_diagnostic(params).await?;
+        if let DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Full(report)) =
+            &mut result
+        {
+            report.full_document_diagnostic_report.items.extend(
+                self.diagnose_imported_macros(&read_guard, reactor.get_analysis())
+                    .await,
+            );
+            sort_diagnostics(&mut report.full_document_diagnostic_report.items);
+        }
+        Ok(result)
     }
 
     pub async fn on_semantic_tokens_full(
@@ -125,6 +202,26 @@ impl Workspace {
         reactor.on_semantic_tokens_full(params).await
     }
 
+    pub async fn on_semantic_tokens_range(
+        &self,
+        params: SemanticTokensRangeParams,
+    ) -> jsonrpc::Result<Option<SemanticTokensRangeResult>> {
+        let uri = &params.text_document.uri;
+        let read_guard = self.reactors.read().await;
+        let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
+        reactor.on_semantic_tokens_range(params).await
+    }
+
+    pub async fn on_semantic_tokens_full_delta(
+        &self,
+        params: SemanticTokensDeltaParams,
+    ) -> jsonrpc::Result<Option<SemanticTokensFullDeltaResult>> {
+        let uri = &params.text_document.uri;
+        let read_guard = self.reactors.read().await;
+        let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
+        reactor.on_semantic_tokens_full_delta(params).await
+    }
+
     pub async fn on_hover(&self, params: HoverParams) -> jsonrpc::Result<Option<Hover>> {
         let uri = &params.text_document_position_params.text_document.uri;
         let read_guard = self.reactors.read().await;
@@ -139,6 +236,26 @@ impl Workspace {
         let uri = &params.text_document_position.text_document.uri;
         let read_guard = self.reactors.read().await;
         let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
+        if let Some(items) = self
+            .macro_parameter_completion(
+                &read_guard,
+                reactor,
+                &params.text_document_position.position,
+            )
+            .await
+        {
+            return Ok(Some(CompletionResponse::Array(items)));
+        }
+        if let Some(items) = self
+            .namespace_member_completion(
+                &read_guard,
+                reactor,
+                &params.text_document_position.position,
+            )
+            .await
+        {
+            return Ok(Some(CompletionResponse::Array(items)));
+        }
         reactor.on_completion(params).await
     }
 
@@ -149,6 +266,13 @@ impl Workspace {
         let uri = &params.text_document_position_params.text_document.uri;
         let read_guard = self.reactors.read().await;
         let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
+        let position = params.text_document_position_params.position;
+        if let Some(location) = self
+            .goto_imported_macro(&read_guard, reactor, &position)
+            .await
+        {
+            return Ok(Some(GotoDefinitionResponse::Scalar(location)));
+        }
         reactor.on_goto_definition(params).await
     }
 
@@ -162,6 +286,26 @@ impl Workspace {
         reactor.on_formatting(params).await
     }
 
+    pub async fn on_range_formatting(
+        &self,
+        params: DocumentRangeFormattingParams,
+    ) -> jsonrpc::Result<Option<Vec<TextEdit>>> {
+        let uri = &params.text_document.uri;
+        let read_guard = self.reactors.read().await;
+        let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
+        reactor.on_range_formatting(params).await
+    }
+
+    pub async fn on_type_formatting(
+        &self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> jsonrpc::Result<Option<Vec<TextEdit>>> {
+        let uri = &params.text_document_position.text_document.uri;
+        let read_guard = self.reactors.read().await;
+        let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
+        reactor.on_type_formatting(params).await
+    }
+
     pub async fn on_folding_range(
         &self,
         params: FoldingRangeParams,
@@ -172,6 +316,93 @@ impl Workspace {
         reactor.on_folding_range(params).await
     }
 
+    pub async fn on_document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> jsonrpc::Result<Option<Vec<DocumentHighlight>>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let read_guard = self.reactors.read().await;
+        let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
+        reactor.on_document_highlight(params).await
+    }
+
+    pub async fn on_document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> jsonrpc::Result<Option<DocumentSymbolResponse>> {
+        let uri = &params.text_document.uri;
+        let read_guard = self.reactors.read().await;
+        let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
+        reactor.on_document_symbol(params).await
+    }
+
+    pub async fn on_references(
+        &self,
+        params: ReferenceParams,
+    ) -> jsonrpc::Result<Option<Vec<Location>>> {
+        let uri = &params.text_document_position.text_document.uri;
+        let read_guard = self.reactors.read().await;
+        let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
+        reactor.on_references(params).await
+    }
+
+    pub async fn on_prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> jsonrpc::Result<Option<PrepareRenameResponse>> {
+        let uri = &params.text_document.uri;
+        let read_guard = self.reactors.read().await;
+        let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
+        reactor.on_prepare_rename(params).await
+    }
+
+    pub async fn on_rename(&self, params: RenameParams) -> jsonrpc::Result<Option<WorkspaceEdit>> {
+        let uri = &params.text_document_position.text_document.uri;
+        let read_guard = self.reactors.read().await;
+        let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
+        reactor.on_rename(params).await
+    }
+
+    pub async fn on_selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> jsonrpc::Result<Option<Vec<SelectionRange>>> {
+        let uri = &params.text_document.uri;
+        let read_guard = self.reactors.read().await;
+        let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
+        reactor.on_selection_range(params).await
+    }
+
+    pub async fn on_document_link(
+        &self,
+        params: DocumentLinkParams,
+    ) -> jsonrpc::Result<Option<Vec<DocumentLink>>> {
+        let uri = &params.text_document.uri;
+        let read_guard = self.reactors.read().await;
+        let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
+        reactor.on_document_link(params).await
+    }
+
+    pub async fn on_inline_value(
+        &self,
+        params: InlineValueParams,
+    ) -> jsonrpc::Result<Option<Vec<InlineValue>>> {
+        let uri = &params.text_document.uri;
+        let read_guard = self.reactors.read().await;
+        let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
+        reactor.on_inline_value(params).await
+    }
+
+    pub async fn on_inlay_hint(
+        &self,
+        params: InlayHintParams,
+    ) -> jsonrpc::Result<Option<Vec<InlayHint>>> {
+        let uri = &params.text_document.uri;
+        let read_guard = self.reactors.read().await;
+        let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
+        reactor.on_inlay_hint(params).await
+    }
+
     pub async fn on_code_action(
         &self,
         params: CodeActionParams,
@@ -179,6 +410,80 @@ impl Workspace {
         let uri = &params.text_document.uri;
         let read_guard = self.reactors.read().await;
         let reactor = read_guard.get(uri).expect(GET_REACTOR_EXPECT);
-        reactor.on_code_action(params).await
+        let actions = reactor.on_code_action(params).await?;
+        if *self.code_action_resolve.read().await {
+            return Ok(actions);
+        }
+        // the client can't resolve them, the edits are computed at once, an action which
+        // turns out to have nothing to edit is dropped
+        Ok(actions.map(|actions| {
+            actions
+                .into_iter()
+                .filter_map(|action| match action {
+                    CodeActionOrCommand::CodeAction(action) if action.data.is_some() => {
+                        let action = action::resolve_code_action(reactor, action);
+                        action
+                            .edit
+                            .is_some()
+                            .then_some(CodeActionOrCommand::CodeAction(action))
+                    }
+                    action => Some(action),
+                })
+                .collect()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use tower_lsp_server::ls_types::{
+        DidChangeTextDocumentParams, DidOpenTextDocumentParams, Position, Range,
+        TextDocumentContentChangeEvent, TextDocumentItem, Uri, VersionedTextDocumentIdentifier,
+    };
+
+    use super::Workspace;
+
+    #[tokio::test]
+    async fn test_full_text_change() {
+        let workspace = Workspace::new();
+        let uri = Uri::from_str("file:///tmp/workspace.ftl").unwrap();
+        workspace
+            .on_did_open(&DidOpenTextDocumentParams {
+                text_document: TextDocumentItem {
+                    uri: uri.clone(),
+                    language_id: "ftl".to_owned(),
+                    version: 0,
+                    text: "<#assign x = 1>\n".to_owned(),
+                },
+            })
+            .await;
+        let change = |version, range, text: &str| DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier {
+                uri: uri.clone(),
+                version,
+            },
+            content_changes: vec![TextDocumentContentChangeEvent {
+                range,
+                range_length: None,
+                text: text.to_owned(),
+            }],
+        };
+        workspace
+            .on_did_change(&change(1, None, "\u{feff}<@a/>\n"))
+            .await;
+        {
+            let reactors = workspace.reactors.read().await;
+            let reactor = &reactors[&uri];
+            assert_eq!(reactor.version, 1);
+            assert!(reactor.get_document().has_bom());
+            assert_eq!(reactor.get_document().to_string(), "<@a/>\n");
+        }
+        // the columns of the first line count the BOM from now on
+        let range = Range::new(Position::new(0, 3), Position::new(0, 4));
+        workspace.on_did_change(&change(2, Some(range), "b")).await;
+        let reactors = workspace.reactors.read().await;
+        assert_eq!(reactors[&uri].get_document().to_string(), "<@b/>\n");
     }
 }